@@ -1,9 +1,21 @@
 use {
-    std::{env, io},
+    clap::CommandFactory,
+    clap_complete::Shell,
+    clap_mangen::Man,
+    roff::{bold, roman, Roff},
+    std::{env, fs, io, path::PathBuf},
     winres::WindowsResource,
 };
 
+#[allow(dead_code)]
+#[path = "src/cli.rs"]
+mod cli;
+
 fn main() -> io::Result<()> {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/cli.rs");
+    println!("cargo:rerun-if-changed=assets/pomodoro.ico");
+
     // From: https://stackoverflow.com/questions/30291757/attaching-an-icon-resource-to-a-rust-application/65393488#65393488
     if env::var_os("CARGO_CFG_WINDOWS").is_some() {
         WindowsResource::new()
@@ -11,5 +23,97 @@ fn main() -> io::Result<()> {
             .set_icon("assets/pomodoro.ico")
             .compile()?;
     }
+
+    generate_completions()?;
+    generate_man_page()?;
+    Ok(())
+}
+
+/// Generates the shell completion scripts into the `completions` directory of `OUT_DIR`.
+fn generate_completions() -> io::Result<()> {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo."));
+    let completions_dir = out_dir.join("completions");
+    fs::create_dir_all(&completions_dir)?;
+    let mut command = cli::Cli::command();
+    let bin_name = command.get_name().to_string();
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+        clap_complete::generate_to(shell, &mut command, &bin_name, &completions_dir)?;
+    }
     Ok(())
 }
+
+/// Generates the man page into the `man` directory of `OUT_DIR`.
+///
+/// Besides the sections generated from the command line arguments, the man page
+/// describes the files and environment variables used by the timer.
+fn generate_man_page() -> io::Result<()> {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo."));
+    let man_dir = out_dir.join("man");
+    fs::create_dir_all(&man_dir)?;
+    let man = Man::new(cli::Cli::command());
+
+    let mut buffer = Vec::new();
+    man.render_title(&mut buffer)?;
+    man.render_name_section(&mut buffer)?;
+    man.render_synopsis_section(&mut buffer)?;
+    man.render_description_section(&mut buffer)?;
+    man.render_options_section(&mut buffer)?;
+    render_files_section().to_writer(&mut buffer)?;
+    render_environment_section().to_writer(&mut buffer)?;
+    man.render_version_section(&mut buffer)?;
+    fs::write(man_dir.join(man.get_filename()), buffer)
+}
+
+/// Creates the FILES section of the man page.
+fn render_files_section() -> Roff {
+    let mut roff = Roff::new();
+    roff.control("SH", ["FILES"]);
+    roff.control("TP", []);
+    roff.text([bold("pomodoro_options.json")]);
+    roff.text([roman(
+        "The options of the timer, located next to the executable. \
+        It is created with the default options if it doesn't exist.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("pomodoro_logging.yaml")]);
+    roff.text([roman(
+        "The optional log4rs configuration in the working directory. \
+        If it exists, it is used instead of the verbosity flags.",
+    )]);
+    roff
+}
+
+/// Creates the ENVIRONMENT section of the man page.
+fn render_environment_section() -> Roff {
+    let mut roff = Roff::new();
+    roff.control("SH", ["ENVIRONMENT"]);
+    roff.control("TP", []);
+    roff.text([bold("POMODORO_WORK_DURATION")]);
+    roff.text([roman(
+        "Overrides durationPomodoro of the options file. The duration is given in seconds, \
+        e.g. 1500, or as MM:SS, e.g. 25:00, and must be whole minutes.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("POMODORO_BREAK_DURATION")]);
+    roff.text([roman(
+        "Overrides durationShortBreak of the options file like POMODORO_WORK_DURATION.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("POMODORO_SERVER_TOKEN")]);
+    roff.text([roman(
+        "The token every request to the JSON-RPC server of --server must contain as \
+        Authorization: Bearer <token>. If not set, a random token is written to \
+        $XDG_RUNTIME_DIR/pomodoro-server.token.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("HYPRLAND_INSTANCE_SIGNATURE")]);
+    roff.text([roman(
+        "If set, the screen is locked with hyprctl when the hyprland locker is configured.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("NIRI_SOCKET")]);
+    roff.text([roman(
+        "If set, the screen is locked with niri msg when the niri locker is configured.",
+    )]);
+    roff
+}
@@ -0,0 +1,91 @@
+//! Compile-time proofs that the public types can be shared between threads, e.g. moved into
+//! an async task or a GUI thread. A type which loses `Send` or `Sync` fails to compile here.
+//!
+//! The `#[repr(C)]` structs of the `plugins` module are excluded, as they hold raw pointers
+//! which are only valid during a call of the plugin.
+use locking_pomodoro_timer::cli_utilities::{AbortHandle, SessionLimitReached};
+use locking_pomodoro_timer::countdown::ScreenPosition;
+use locking_pomodoro_timer::email::{SmtpConfig, SmtpSecurity};
+use locking_pomodoro_timer::end_events::{
+    EndEvent, EndEventOutcome, ParseEndEventError, ValidationError,
+};
+use locking_pomodoro_timer::event_log::{TimerEvent, TimerEventLog, TimerEventRecord};
+use locking_pomodoro_timer::health_check::HealthCheck;
+use locking_pomodoro_timer::pomodoro_options::{
+    OptionsWatcher, PartialPomodoroOptions, PomodoroOptions, PomodoroOptionsError,
+};
+use locking_pomodoro_timer::pomodoro_phase::{PlannedPhase, PomodoroPhase};
+use locking_pomodoro_timer::remote::{RemoteCommand, RemoteControl, TimerStatus};
+use locking_pomodoro_timer::screen_lock::{
+    ContinuousLockConfig, LinuxLocker, LockError, LockEvent, LockReport, LockRetryConfig,
+};
+use locking_pomodoro_timer::session::{Session, SessionBuilder, SessionCallbacks, SessionPhase};
+use locking_pomodoro_timer::sound::{PlaySoundError, SoundBackend, SoundFormat};
+use locking_pomodoro_timer::statistics::{AbortReason, HistoryRecord, SessionSummary, Statistics};
+use locking_pomodoro_timer::theme::{Color, ThemeConfig};
+use locking_pomodoro_timer::timer::Timer;
+
+fn assert_send<T: Send>() {}
+
+fn assert_sync<T: Sync>() {}
+
+/// Asserts that each type is both `Send` and `Sync`.
+macro_rules! assert_send_sync {
+    ($($type:ty),+ $(,)?) => {
+        $(
+            assert_send::<$type>();
+            assert_sync::<$type>();
+        )+
+    };
+}
+
+#[test]
+fn test_send_sync() {
+    assert_send_sync!(
+        AbortHandle,
+        SessionLimitReached,
+        ScreenPosition,
+        SmtpConfig,
+        SmtpSecurity,
+        EndEvent,
+        EndEventOutcome,
+        ParseEndEventError,
+        ValidationError,
+        TimerEvent,
+        TimerEventLog,
+        TimerEventRecord,
+        HealthCheck,
+        OptionsWatcher,
+        PartialPomodoroOptions,
+        PomodoroOptions,
+        PomodoroOptionsError,
+        PlannedPhase,
+        PomodoroPhase,
+        RemoteCommand,
+        RemoteControl,
+        TimerStatus,
+        ContinuousLockConfig,
+        LinuxLocker,
+        LockError,
+        LockEvent,
+        LockReport,
+        LockRetryConfig,
+        Session,
+        SessionBuilder,
+        SessionPhase,
+        PlaySoundError,
+        SoundBackend,
+        SoundFormat,
+        AbortReason,
+        HistoryRecord,
+        SessionSummary,
+        Statistics,
+        Color,
+        ThemeConfig,
+        Timer,
+    );
+    // The callbacks only need to be moved to the thread running the timer
+    assert_send::<SessionCallbacks>();
+    #[cfg(all(feature = "mpris", target_os = "linux"))]
+    assert_send_sync!(locking_pomodoro_timer::mpris::MprisPlayer);
+}
@@ -0,0 +1,59 @@
+//! This module invokes AWS Lambda functions for the `AwsLambda` end event, e.g. to run backend
+//! logic at the end of a Pomodoro session.
+//!
+//! The credentials are taken from the standard AWS credential chain: the environment variables
+//! `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`, the profile in `~/.aws/credentials` and
+//! `~/.aws/config` selected by `AWS_PROFILE`, or the role of the instance or container.
+//!
+//! This module is only available with the `aws` feature.
+use anyhow::{bail, Context, Result};
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_lambda::primitives::Blob;
+use aws_sdk_lambda::types::InvocationType;
+use aws_sdk_lambda::Client;
+
+/// Invokes the function asynchronously with the payload, so that the function runs in the
+/// background and only its acceptance by AWS Lambda is awaited.
+///
+/// # Arguments
+/// * `function_name` - The name, ARN or partial ARN of the function, e.g. `pomodoro-finished`.
+/// * `region` - The AWS region of the function, e.g. `eu-central-1`.
+/// * `payload` - The JSON event the function is invoked with.
+///
+/// # Errors
+///
+/// Returns an error if no credentials are found, the function cannot be invoked or AWS Lambda
+/// did not accept the invocation.
+pub fn invoke(function_name: &str, region: &str, payload: &serde_json::Value) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the runtime of the AWS client")?;
+    runtime.block_on(async {
+        let config = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new(region.to_string()))
+            .load()
+            .await;
+        let output = Client::new(&config)
+            .invoke()
+            .function_name(function_name)
+            .invocation_type(InvocationType::Event)
+            .payload(Blob::new(serde_json::to_vec(payload)?))
+            .send()
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to invoke the AWS Lambda function '{}'",
+                    function_name
+                )
+            })?;
+        if output.status_code() != 202 {
+            bail!(
+                "The AWS Lambda function '{}' was not invoked, status code {}.",
+                function_name,
+                output.status_code()
+            );
+        }
+        Ok(())
+    })
+}
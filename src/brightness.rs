@@ -0,0 +1,245 @@
+//! This module sets the brightness of the displays for the `SetDisplayBrightness` end event,
+//! e.g. to restore it gradually after a break in which the display was dimmed.
+//!
+//! On Linux, the brightness is set with `xrandr --output <display> --brightness` for all
+//! connected displays on X11. On macOS, the `brightness` command line tool is used, e.g. from
+//! Homebrew. On Windows, the brightness of the internal display is set via WMI with PowerShell.
+use crate::clock::{current_clock, Clock};
+use std::process::Command;
+use std::time::Duration;
+
+/// The number of steps of a transition.
+const TRANSITION_STEPS: u32 = 20;
+
+/// Changes the brightness of the displays to the target in 20 steps over the transition.
+/// Blocks until the target is reached.
+///
+/// The transition starts at the current brightness if it can be read, else the target is set
+/// immediately. Each step is set at its share of the transition, so that slow commands don't
+/// make the transition take longer. Prints a warning if the brightness cannot be set.
+///
+/// # Arguments
+/// * `target_percent` - The brightness in percent of the maximum brightness.
+/// * `transition` - The duration of the transition. If zero, the target is set immediately.
+pub fn set_brightness(target_percent: u8, transition: Duration) {
+    let target = f64::from(target_percent.min(100)) / 100.0;
+    let displays = match Displays::find() {
+        Ok(displays) => displays,
+        Err(e) => {
+            log::warn!("Failed to set the display brightness: {}", e);
+            return;
+        }
+    };
+    let (levels, transition) = match displays.current_brightness() {
+        Some(current) if !transition.is_zero() => (
+            transition_levels(current, target, TRANSITION_STEPS),
+            transition,
+        ),
+        _ => (vec![target], Duration::ZERO),
+    };
+    let result = run_transition(&levels, transition, current_clock().as_ref(), |level| {
+        displays.apply_brightness(level)
+    });
+    match result {
+        Ok(()) => log::info!("Display brightness set to {}%.", target_percent),
+        Err(e) => log::warn!("Failed to set the display brightness: {}", e),
+    }
+}
+
+/// Sets the levels one after another, each at the end of its equal share of the transition.
+///
+/// # Errors
+///
+/// Returns the error of the first level which cannot be set.
+fn run_transition(
+    levels: &[f64],
+    transition: Duration,
+    clock: &dyn Clock,
+    mut apply: impl FnMut(f64) -> Result<(), String>,
+) -> Result<(), String> {
+    let start = clock.now();
+    let steps = u32::try_from(levels.len()).unwrap_or(u32::MAX);
+    for (step, level) in (1..=steps).zip(levels) {
+        let deadline = start + transition.mul_f64(f64::from(step) / f64::from(steps));
+        clock.sleep(deadline.saturating_duration_since(clock.now()));
+        apply(*level)?;
+    }
+    Ok(())
+}
+
+/// Gets the levels of the steps from the current brightness to the target, ending at the target.
+fn transition_levels(from: f64, to: f64, steps: u32) -> Vec<f64> {
+    (1..=steps)
+        .map(|step| from + (to - from) * f64::from(step) / f64::from(steps))
+        .collect()
+}
+
+/// The displays whose brightness is set, which are found once per transition.
+struct Displays {
+    /// The names of the connected displays of `xrandr` on Linux, empty on other platforms.
+    outputs: Vec<String>,
+}
+
+impl Displays {
+    /// Finds the connected displays.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on Linux if `xrandr` cannot be run or finds no connected display.
+    fn find() -> Result<Self, String> {
+        let mut outputs = Vec::new();
+        if cfg!(target_os = "linux") {
+            let query = command_stdout("xrandr", &["--query"])
+                .ok_or("Failed to run 'xrandr', is it installed and X11 running?")?;
+            outputs = parse_connected_outputs(&query);
+            if outputs.is_empty() {
+                return Err("'xrandr' found no connected display.".to_string());
+            }
+        }
+        Ok(Displays { outputs })
+    }
+
+    /// Gets the current brightness of the displays as fraction of the maximum brightness.
+    fn current_brightness(&self) -> Option<f64> {
+        if cfg!(target_os = "linux") {
+            let output = command_stdout("xrandr", &["--verbose"])?;
+            parse_xrandr_brightness(&output)
+        } else if cfg!(target_os = "macos") {
+            let output = command_stdout("brightness", &["-l"])?;
+            output
+                .lines()
+                .find_map(|line| line.split_once("brightness ")?.1.trim().parse().ok())
+        } else if cfg!(target_os = "windows") {
+            let output = command_stdout(
+                "powershell",
+                &[
+                    "-NoProfile",
+                    "-Command",
+                    "(Get-CimInstance -Namespace root/WMI -ClassName WmiMonitorBrightness).CurrentBrightness",
+                ],
+            )?;
+            let percent: f64 = output.lines().next()?.trim().parse().ok()?;
+            Some(percent / 100.0)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the brightness of the displays to the fraction of the maximum brightness.
+    fn apply_brightness(&self, level: f64) -> Result<(), String> {
+        if cfg!(target_os = "linux") {
+            let brightness = format!("{:.2}", level);
+            for output in &self.outputs {
+                run_command("xrandr", &["--output", output, "--brightness", &brightness])?;
+            }
+            Ok(())
+        } else if cfg!(target_os = "macos") {
+            run_command("brightness", &[&format!("{:.2}", level)])
+        } else if cfg!(target_os = "windows") {
+            let script = format!(
+                "(Get-WmiObject -Namespace root/WMI -Class WmiMonitorBrightnessMethods).WmiSetBrightness(1, {:.0})",
+                level * 100.0
+            );
+            run_command("powershell", &["-NoProfile", "-Command", &script])
+        } else {
+            Err("Setting the display brightness is not implemented for this platform.".to_string())
+        }
+    }
+}
+
+/// Gets the names of the connected displays from the output of `xrandr --query`.
+fn parse_connected_outputs(query: &str) -> Vec<String> {
+    query
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let name = words.next()?;
+            (words.next() == Some("connected")).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Gets the brightness of the first display from the output of `xrandr --verbose`.
+fn parse_xrandr_brightness(verbose: &str) -> Option<f64> {
+    verbose
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Brightness:")?.trim().parse().ok())
+}
+
+/// Runs the command and gets its stdout, or `None` if it fails.
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs the command and gets its stderr as error if it fails.
+fn run_command(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run '{}': {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[test]
+fn test_parse_xrandr() {
+    let query = "Screen 0: minimum 320 x 200, current 3840 x 1080, maximum 16384 x 16384\n\
+        eDP-1 connected primary 1920x1080+0+0 (normal left inverted right x axis y axis) 344mm x 193mm\n   \
+        1920x1080     60.02*+\n\
+        HDMI-1 disconnected (normal left inverted right x axis y axis)\n\
+        DP-1 connected 1920x1080+1920+0 (normal left inverted right x axis y axis) 527mm x 296mm\n";
+    assert_eq!(parse_connected_outputs(query), vec!["eDP-1", "DP-1"]);
+    let verbose = "eDP-1 connected primary 1920x1080+0+0 (0x4a) normal\n\
+        \tIdentifier: 0x42\n\
+        \tGamma:      1.0:1.0:1.0\n\
+        \tBrightness: 0.40\n";
+    assert_eq!(parse_xrandr_brightness(verbose), Some(0.4));
+
+    let levels = transition_levels(0.4, 0.9, TRANSITION_STEPS);
+    assert_eq!(levels.len(), 20);
+    assert!((levels[0] - 0.425).abs() < 1e-9);
+    assert_eq!(levels[19], 0.9);
+}
+
+#[test]
+fn test_run_transition() {
+    use crate::clock::ManualClock;
+
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let mut applied = Vec::new();
+    // Each step takes 50 ms, which is caught up by the following steps
+    run_transition(
+        &[0.25, 0.5, 0.75, 1.0],
+        Duration::from_secs(1),
+        &clock,
+        |level| {
+            applied.push((clock.now() - start, level));
+            clock.sleep(Duration::from_millis(50));
+            Ok(())
+        },
+    )
+    .unwrap();
+    let millis = |millis| Duration::from_millis(millis);
+    assert_eq!(
+        applied,
+        vec![
+            (millis(250), 0.25),
+            (millis(500), 0.5),
+            (millis(750), 0.75),
+            (millis(1000), 1.0)
+        ]
+    );
+
+    let result = run_transition(&[0.5, 1.0], Duration::ZERO, &clock, |_| {
+        Err("no display".into())
+    });
+    assert_eq!(result, Err("no display".to_string()));
+}
@@ -0,0 +1,112 @@
+//! This module writes finished Pomodoro sessions as calendar entries in the iCalendar format (RFC 5545).
+//!
+//! Every session is written to its own `.ics` file, so that tools like `vdirsyncer` or `khal`
+//! can pick it up from the directory.
+use crate::statistics::HistoryRecord;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The format of the date and time values in UTC, e.g. `20240501T090000Z`.
+const ICS_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Writes the record as a calendar entry to a new `.ics` file in the directory.
+///
+/// The directory is created if necessary. The file is named after the start of the session.
+///
+/// # Returns
+/// The path of the written file.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be created or the file cannot be written.
+pub fn write_ics_file(ics_output_dir: &Path, record: &HistoryRecord) -> Result<PathBuf> {
+    fs::create_dir_all(ics_output_dir)
+        .with_context(|| format!("Failed to create directory: {:?}", ics_output_dir))?;
+    let started_at = record.started_at.with_timezone(&Utc);
+    let path = ics_output_dir.join(format!(
+        "pomodoro-{}.ics",
+        started_at.format(ICS_DATE_TIME_FORMAT)
+    ));
+    fs::write(&path, format_ics_event(record, Utc::now()))
+        .with_context(|| format!("Failed to write calendar file: {:?}", path))?;
+    Ok(path)
+}
+
+/// Formats the record as a calendar with a single event.
+///
+/// The summary is the label of the record, or `Pomodoro session` without a label.
+/// `created_at` is the time the calendar entry is created, which is required as `DTSTAMP`.
+fn format_ics_event(record: &HistoryRecord, created_at: DateTime<Utc>) -> String {
+    let started_at = record.started_at.with_timezone(&Utc);
+    let ended_at = started_at + Duration::seconds(record.duration_secs as i64);
+    let minutes = record.duration_secs.div_ceil(60);
+    let (summary, description) = match &record.label {
+        Some(label) => (
+            label.clone(),
+            format!("Pomodoro session of {} minutes: {}", minutes, label),
+        ),
+        None => (
+            "Pomodoro session".to_string(),
+            format!("Pomodoro session of {} minutes.", minutes),
+        ),
+    };
+    let lines = [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//{}//{}//EN",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ),
+        "BEGIN:VEVENT".to_string(),
+        format!(
+            "UID:{}@{}",
+            started_at.format(ICS_DATE_TIME_FORMAT),
+            env!("CARGO_PKG_NAME")
+        ),
+        format!("DTSTAMP:{}", created_at.format(ICS_DATE_TIME_FORMAT)),
+        format!("DTSTART:{}", started_at.format(ICS_DATE_TIME_FORMAT)),
+        format!("DTEND:{}", ended_at.format(ICS_DATE_TIME_FORMAT)),
+        format!("SUMMARY:{}", escape_ics_text(&summary)),
+        format!("DESCRIPTION:{}", escape_ics_text(&description)),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+    ];
+    // RFC 5545 requires CRLF line endings
+    lines.iter().map(|line| format!("{}\r\n", line)).collect()
+}
+
+/// Escapes backslashes, semicolons, commas and newlines of a text value.
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+use crate::pomodoro_phase::PomodoroPhase;
+
+#[test]
+fn test_format_ics_event() {
+    let record = HistoryRecord {
+        started_at: "2024-05-01T09:00:00Z".parse().unwrap(),
+        phase: PomodoroPhase::Work,
+        duration_secs: 25 * 60,
+        completed: true,
+        skipped: false,
+        label: Some("Project A, part 1".to_string()),
+        aborted: None,
+    };
+    let created_at = "2024-05-01T09:25:00Z".parse().unwrap();
+    let ics = format_ics_event(&record, created_at);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.contains("\r\nDTSTAMP:20240501T092500Z\r\n"));
+    assert!(ics.contains("\r\nDTSTART:20240501T090000Z\r\n"));
+    assert!(ics.contains("\r\nDTEND:20240501T092500Z\r\n"));
+    assert!(ics.contains("\r\nSUMMARY:Project A\\, part 1\r\n"));
+    assert!(ics.contains("\r\nDESCRIPTION:Pomodoro session of 25 minutes: Project A\\, part 1\r\n"));
+}
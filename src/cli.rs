@@ -0,0 +1,314 @@
+//! This module defines the command line arguments of the Locking Pomodoro Timer.
+//!
+//! It only depends on `clap` and `clap_complete` so that it can also be included in `build.rs`.
+#[cfg(test)]
+use clap::CommandFactory;
+use clap::{ArgAction, Parser, ValueEnum};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+/// The help text of the environment variables.
+const ENV_HELP: &str = "\
+Environment variables:
+  POMODORO_WORK_DURATION   Overrides durationPomodoro of the options file, in seconds or as MM:SS
+  POMODORO_BREAK_DURATION  Overrides durationShortBreak of the options file, in seconds or as MM:SS
+  POMODORO_SERVER_TOKEN    The token requests to the JSON-RPC server of --server must contain";
+
+/// A Pomodoro timer with the capability of locking the screen.
+#[derive(Debug, Parser)]
+#[command(version, about, after_help = ENV_HELP)]
+pub struct Cli {
+    /// Print the effective options in the given format and exit.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "json"
+    )]
+    pub export_config: Option<ConfigFormat>,
+    /// Print the JSON Schema of the options file and exit.
+    #[arg(long)]
+    pub print_json_schema: bool,
+    /// Print the completion script for the given shell and exit.
+    #[arg(long, value_name = "SHELL", value_enum)]
+    pub completions: Option<Shell>,
+    /// Reload the options whenever the options file changes. End events use the new options
+    /// immediately, all other options take effect at the start of the next phase.
+    #[arg(long)]
+    pub watch_config: bool,
+    /// Start a JSON-RPC 2.0 server on 127.0.0.1 which controls the timer remotely, on port 7177
+    /// without PORT. Requires a build with the `server` feature.
+    #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "7177")]
+    pub server: Option<u16>,
+    /// Run the timer in the background on POSIX platforms. The output is appended to
+    /// `daemon.log` in the state directory and the PID is written to
+    /// `$XDG_RUNTIME_DIR/pomodoro.pid`.
+    #[arg(long, visible_alias = "fork")]
+    pub daemon: bool,
+    /// Print whether the daemon started with `--daemon` is running and exit.
+    #[arg(long, conflicts_with = "daemon")]
+    pub status: bool,
+    /// The label of the Pomodoro sessions in the history, e.g. the name of a project.
+    /// Overrides `workLabel` of the options file.
+    #[arg(long, value_name = "TEXT")]
+    pub work_label: Option<String>,
+    /// The label of the breaks in the history. Overrides `breakLabel` of the options file.
+    #[arg(long, value_name = "TEXT")]
+    pub break_label: Option<String>,
+    /// The maximum number of completed Pomodoro sessions per day. Overrides `maxSessionsPerDay`
+    /// of the options file. Exits with code 2 when the maximum is reached.
+    #[arg(long, value_name = "N")]
+    pub max_work_sessions_per_day: Option<u32>,
+    /// Start Pomodoro sessions even if the maximum number of sessions per day is reached.
+    #[arg(long)]
+    pub force: bool,
+    /// Run exactly one Pomodoro session and its break, then exit. Overrides `cycles` of the
+    /// options file. The next session is neither started automatically with
+    /// `autoStartPomodoro` nor offered with a prompt, but `autoStartBreak` still applies.
+    #[arg(short = '1', long)]
+    pub once: bool,
+    /// Run the time of the timer SPEED times faster than real time to test the options, e.g.
+    /// with 60 a Pomodoro session of 25 minutes takes 25 seconds. End events waiting for the
+    /// break, e.g. locking the screen, are sped up as well.
+    #[arg(long, value_name = "SPEED", value_parser = parse_speed)]
+    pub simulate: Option<f32>,
+    /// Print the planned phases of the next N cycles with their projected start times and exit.
+    /// Without N, the phases until the next long break are printed.
+    #[arg(long, value_name = "N", num_args = 0..=1)]
+    pub list_phases: Option<Option<u32>>,
+    /// The output format of `--list-phases`.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, requires = "list_phases")]
+    pub format: OutputFormat,
+    /// Check that the options file is valid and the configured end events will work, then exit.
+    /// Exits with code 1 if any check fails.
+    #[arg(long)]
+    pub health_check: bool,
+    /// Send a test notification to the topic of each `ntfyPush` end event of the options file
+    /// and exit. Exits with code 1 if a notification cannot be sent.
+    #[arg(long)]
+    pub test_push: bool,
+    /// Print the statistics of today's Pomodoro sessions from the history and exit.
+    #[arg(long)]
+    pub summary: bool,
+    /// Print the statistics of the last 7 days including today instead.
+    #[arg(long, requires = "summary")]
+    pub week: bool,
+    /// Delete the history of finished Pomodoro sessions and breaks and exit.
+    #[arg(long)]
+    pub reset_stats: bool,
+    /// Only delete the records of the history which started before the date, e.g. 2024-05-01.
+    #[arg(long, value_name = "DATE", requires = "reset_stats")]
+    pub before: Option<String>,
+    /// Do not ask for confirmation.
+    #[arg(short, long, requires = "reset_stats")]
+    pub yes: bool,
+    /// Print the history of finished Pomodoro sessions and breaks in the format and exit, e.g.
+    /// for spreadsheets. Without a format, CSV is printed.
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "csv")]
+    pub export_history: Option<HistoryFormat>,
+    /// Only export the records of the history which started on or after the date, e.g. 2024-05-01.
+    #[arg(long, value_name = "DATE", requires = "export_history")]
+    pub since: Option<String>,
+    /// Only export the records of the history which started on or before the date, e.g. 2024-05-31.
+    #[arg(long, value_name = "DATE", requires = "export_history")]
+    pub until: Option<String>,
+    /// Increase the diagnostic output: `-v` for info, `-vv` for debug and `-vvv` for trace messages.
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Suppress all diagnostic output except errors.
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// When to color the output: `auto` if it is written to a terminal, `always` also if it is
+    /// piped and `never` without any ANSI escape sequences. Without WHEN, `always` is used.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        default_value_t = ColorChoice::Auto,
+        default_missing_value = "always"
+    )]
+    pub color: ColorChoice,
+    /// Append the diagnostic output to the file instead of writing it to stderr, e.g. for
+    /// `--daemon`. The verbosity flags still apply.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// Rotate the log file once it exceeds the size in bytes: it is renamed to `<PATH>.1`,
+    /// replacing an older one, and a new log file is started.
+    #[arg(long, value_name = "BYTES", requires = "log_file")]
+    pub max_log_size: Option<u64>,
+}
+
+/// The formats the options can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    /// JSON, the format of the options file.
+    Json,
+    /// TOML.
+    Toml,
+}
+
+/// When the output is colored, see `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color the output.
+    Always,
+    /// Color the output if stderr, where the progress bar and diagnostic output are written
+    /// to, is a terminal.
+    Auto,
+    /// Don't color the output.
+    Never,
+}
+
+/// The formats the history can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistoryFormat {
+    /// CSV according to RFC 4180 with the columns `date`, `time`, `phase`, `duration_secs` and
+    /// `label`.
+    Csv,
+    /// A JSON array of the records of the history file.
+    Json,
+}
+
+/// Parses the speed of `--simulate`, which must be a positive number.
+fn parse_speed(value: &str) -> Result<f32, String> {
+    let speed: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(format!("the speed must be positive, not {}", value))
+    }
+}
+
+/// The formats of the lists printed by the command line flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A human-readable table.
+    Text,
+    /// JSON.
+    Json,
+}
+
+#[test]
+fn test_cli() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_parse_cli() {
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--export-config", "-vv"]).unwrap();
+    assert_eq!(cli.export_config, Some(ConfigFormat::Json));
+    assert_eq!(cli.verbose, 2);
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--export-config", "toml"]).unwrap();
+    assert_eq!(cli.export_config, Some(ConfigFormat::Toml));
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--export-config", "yaml"]).is_err());
+
+    let cli = Cli::try_parse_from([
+        "locking-pomodoro-timer",
+        "--reset-stats",
+        "--before",
+        "2024-05-01",
+    ])
+    .unwrap();
+    assert!(cli.reset_stats);
+    assert_eq!(cli.before.as_deref(), Some("2024-05-01"));
+
+    assert!(
+        Cli::try_parse_from(["locking-pomodoro-timer", "-1"])
+            .unwrap()
+            .once
+    );
+    let cli = Cli::try_parse_from([
+        "locking-pomodoro-timer",
+        "--list-phases",
+        "--format",
+        "json",
+    ])
+    .unwrap();
+    assert_eq!(cli.list_phases, Some(None));
+    assert_eq!(cli.format, OutputFormat::Json);
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--list-phases", "8"]).unwrap();
+    assert_eq!(cli.list_phases, Some(Some(8)));
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--format", "json"]).is_err());
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "-v", "-q"]).is_err());
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--yes"]).is_err());
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--export-history"]).unwrap();
+    assert_eq!(cli.export_history, Some(HistoryFormat::Csv));
+    let cli = Cli::try_parse_from([
+        "locking-pomodoro-timer",
+        "--export-history",
+        "json",
+        "--since",
+        "2024-05-01",
+    ])
+    .unwrap();
+    assert_eq!(cli.export_history, Some(HistoryFormat::Json));
+    assert_eq!(cli.since.as_deref(), Some("2024-05-01"));
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--until", "2024-05-01"]).is_err());
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--server"]).unwrap();
+    assert_eq!(cli.server, Some(7177));
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--server", "8080"]).unwrap();
+    assert_eq!(cli.server, Some(8080));
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--fork"]).unwrap();
+    assert!(cli.daemon);
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--daemon", "--status"]).is_err());
+    let cli = Cli::try_parse_from([
+        "locking-pomodoro-timer",
+        "--log-file",
+        "pomodoro.log",
+        "--max-log-size",
+        "1048576",
+    ])
+    .unwrap();
+    assert_eq!(cli.log_file, Some(PathBuf::from("pomodoro.log")));
+    assert_eq!(cli.max_log_size, Some(1_048_576));
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--max-log-size", "10"]).is_err());
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--simulate", "60"]).unwrap();
+    assert_eq!(cli.simulate, Some(60.0));
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--simulate", "0"]).is_err());
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--simulate", "fast"]).is_err());
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer"]).unwrap();
+    assert_eq!(cli.color, ColorChoice::Auto);
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--color"]).unwrap();
+    assert_eq!(cli.color, ColorChoice::Always);
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--color", "never"]).unwrap();
+    assert_eq!(cli.color, ColorChoice::Never);
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--color", "always"]).unwrap();
+    assert_eq!(cli.color, ColorChoice::Always);
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--color", "auto"]).unwrap();
+    assert_eq!(cli.color, ColorChoice::Auto);
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--color", "sometimes"]).is_err());
+}
+
+#[test]
+fn test_completions() {
+    let cli = Cli::try_parse_from(["locking-pomodoro-timer", "--completions", "zsh"]).unwrap();
+    assert_eq!(cli.completions, Some(Shell::Zsh));
+    assert!(Cli::try_parse_from(["locking-pomodoro-timer", "--completions", "tcsh"]).is_err());
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            shell,
+            &mut Cli::command(),
+            "locking-pomodoro-timer",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("completions"), "{:?}: {}", shell, script);
+    }
+}
+
+#[test]
+fn test_man_page() {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(Cli::command())
+        .render(&mut page)
+        .unwrap();
+    let page = String::from_utf8(page).unwrap();
+    assert!(page.contains(".TH locking-pomodoro-timer 1"), "{}", page);
+    assert!(page.contains("\\-\\-completions"), "{}", page);
+    assert!(page.contains("\\-\\-color"), "{}", page);
+}
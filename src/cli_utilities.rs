@@ -1,18 +1,98 @@
-use crate::end_events::{start_end_event, start_end_event_with_duration};
+//! This module contains the terminal loop driving the Pomodoro sessions and breaks.
+use crate::calendar::write_ics_file;
+use crate::clock::current_clock;
+use crate::email::send_summary_email;
+use crate::end_events::EndEvent;
+use crate::end_events::{
+    start_end_event_for_break, start_end_event_for_phase_with_final,
+    start_end_event_with_lock_config,
+};
+use crate::event_log::{session_log_dir, TimerEvent, TimerEventLog};
+#[cfg(feature = "native")]
 use crate::input_handler;
 use crate::message_creator::{
     generate_print_message_before_additional_break, generate_print_message_before_break,
     generate_print_message_before_pomodoro,
 };
+#[cfg(all(feature = "mpris", target_os = "linux"))]
+use crate::mpris::MprisPlayer;
 use crate::pomo_info::PomoInfo;
-use crate::pomodoro_options::PomodoroOptions;
+use crate::pomodoro_options::{OptionsWatcher, PomodoroOptions};
+use crate::pomodoro_phase::PomodoroPhase;
+use crate::remote::RemoteControl;
+use crate::screen_lock::SuspendedCaffeinate;
+use crate::session::SessionCallbacks;
+#[cfg(all(feature = "mpris", target_os = "linux"))]
+use crate::sound::with_stop_signal;
+use crate::statistics::{
+    append_to_history, history_file_path, sanitize_label, AbortReason, HistoryRecord, Statistics,
+};
 use crate::timer::Timer;
-use crate::end_events::EndEvent;
+use chrono::{DateTime, Local};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::debug;
 use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use thiserror::Error;
+
+/// The interval in which a waiting prompt checks whether the timer was aborted.
+const ABORT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The error returned if no more Pomodoro sessions may be started today.
+#[derive(Error, Debug)]
+#[error("{completed} Pomodoro sessions were completed today, the maximum is {max}.")]
+pub struct SessionLimitReached {
+    /// The number of completed Pomodoro sessions today.
+    pub completed: u32,
+    /// The maximum number of Pomodoro sessions per day.
+    pub max: u32,
+}
+
+/// A handle to abort the Pomodoro timer started with `start_pomodoro_with_abort_handle`,
+/// e.g. from the stop button of a GUI running in another thread.
+///
+/// Pressing ctrl+c in the terminal aborts the timer in the same way.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    reason: Arc<Mutex<Option<AbortReason>>>,
+}
+
+impl AbortHandle {
+    /// Creates a new handle which is not aborted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Aborts the timer. The running Pomodoro session or break is stopped without its end event
+    /// and recorded in the history as aborted, then the timer returns.
+    ///
+    /// Only the first reason is kept if the timer is aborted several times.
+    pub fn abort(&self, reason: AbortReason) {
+        let mut current = self.reason.lock().unwrap_or_else(PoisonError::into_inner);
+        if current.is_none() {
+            log::info!("Aborting the timer: {:?}", reason);
+            *current = Some(reason);
+        }
+    }
+
+    /// Gets the reason the timer was aborted with, or `None` if it wasn't aborted.
+    #[must_use]
+    pub fn reason(&self) -> Option<AbortReason> {
+        self.reason
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Checks whether the timer was aborted.
+    fn is_aborted(&self) -> bool {
+        self.reason().is_some()
+    }
+}
 
 /// Starts the Pomodoro timer.
 ///
@@ -24,51 +104,305 @@ use std::time::{Duration, Instant};
 ///
 /// # Arguments
 /// * `options` - The Pomodoro options.
-pub(crate) fn start_pomodoro(options: &PomodoroOptions) {
-    // Use the imported data
-    println!(
-        "Options: {}",
-        serde_json::to_string_pretty(options).unwrap()
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if a Pomodoro session would exceed `max_sessions_per_day`.
+pub fn start_pomodoro(options: &PomodoroOptions) -> Result<(), SessionLimitReached> {
+    start_pomodoro_with_watcher(options, None)
+}
+
+/// Starts the Pomodoro timer like `start_pomodoro` and applies reloaded options of the watcher.
+///
+/// The end events are always executed with the latest options, while all other options
+/// like the durations take effect at the start of the next Pomodoro session, additional Pomodoro or break.
+///
+/// # Arguments
+/// * `options` - The Pomodoro options.
+/// * `watcher` - The watcher of the options file, if the options should be reloaded on changes.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if a Pomodoro session would exceed `max_sessions_per_day`.
+pub fn start_pomodoro_with_watcher(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+) -> Result<(), SessionLimitReached> {
+    start_pomodoro_with_abort_handle(options, watcher, &AbortHandle::new())
+}
+
+/// Starts the Pomodoro timer like `start_pomodoro_with_watcher` until it is quit or aborted
+/// with the handle.
+///
+/// # Arguments
+/// * `options` - The Pomodoro options.
+/// * `watcher` - The watcher of the options file, if the options should be reloaded on changes.
+/// * `abort` - The handle to abort the timer from another thread.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if a Pomodoro session would exceed `max_sessions_per_day`.
+pub fn start_pomodoro_with_abort_handle(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    abort: &AbortHandle,
+) -> Result<(), SessionLimitReached> {
+    start_pomodoro_with_remote_control(options, watcher, abort, &RemoteControl::new())
+}
+
+/// Starts the Pomodoro timer like `start_pomodoro_with_abort_handle`, which can also be
+/// controlled and configured with the remote control, e.g. by the JSON-RPC server.
+///
+/// # Arguments
+/// * `options` - The Pomodoro options.
+/// * `watcher` - The watcher of the options file, if the options should be reloaded on changes.
+/// * `abort` - The handle to abort the timer from another thread.
+/// * `remote` - The handle to control the timer from another thread.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if a Pomodoro session would exceed `max_sessions_per_day`.
+pub fn start_pomodoro_with_remote_control(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    abort: &AbortHandle,
+    remote: &RemoteControl,
+) -> Result<(), SessionLimitReached> {
+    start_pomodoro_with_callbacks(
+        options,
+        watcher,
+        abort,
+        remote,
+        &SessionCallbacks::default(),
+    )
+}
+
+/// Starts the Pomodoro timer like `start_pomodoro_with_remote_control` and calls the callbacks,
+/// e.g. to update the UI of an application embedding the timer.
+///
+/// `on_phase_start`, `on_phase_recorded` and `on_phase_end` are called for every Pomodoro session
+/// and break, but not for the additional Pomodoro. `on_session_complete` is called when the timer returns, with
+/// the summary of the Pomodoro sessions it ran.
+///
+/// The keys of the terminal are only read with the `native` feature.
+///
+/// # Arguments
+/// * `options` - The Pomodoro options.
+/// * `watcher` - The watcher of the options file, if the options should be reloaded on changes.
+/// * `abort` - The handle to abort the timer from another thread.
+/// * `remote` - The handle to control the timer from another thread.
+/// * `callbacks` - The callbacks of the lifecycle of the timer.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if a Pomodoro session would exceed `max_sessions_per_day`.
+pub fn start_pomodoro_with_callbacks(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    abort: &AbortHandle,
+    remote: &RemoteControl,
+    callbacks: &SessionCallbacks,
+) -> Result<(), SessionLimitReached> {
+    let (sender, receiver) = mpsc::channel();
+    #[cfg(feature = "native")]
+    {
+        debug!("Starting input stream.");
+        input_handler::start_input_stream(sender.clone());
+    }
+    run_with_remote_control(
+        options, watcher, abort, remote, callbacks, sender, &receiver,
+    )
+}
+
+/// Starts the Pomodoro timer like `start_pomodoro_with_callbacks` without the terminal, e.g. in
+/// an application with its own UI.
+///
+/// The keys of the terminal are not read, so the timer is only controlled with the remote
+/// control. The breaks and the Pomodoro sessions after them start automatically instead of
+/// asking on the terminal, regardless of `auto_start_break` and `auto_start_pomodoro`, until
+/// `cycles` finished or the timer is aborted.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if a Pomodoro session would exceed `max_sessions_per_day`.
+pub fn start_pomodoro_without_terminal(
+    options: &PomodoroOptions,
+    abort: &AbortHandle,
+    remote: &RemoteControl,
+    callbacks: &SessionCallbacks,
+) -> Result<(), SessionLimitReached> {
+    let options = PomodoroOptions {
+        auto_start_break: true,
+        auto_start_pomodoro: true,
+        ..options.clone()
+    };
+    let (sender, receiver) = mpsc::channel();
+    run_with_remote_control(&options, None, abort, remote, callbacks, sender, &receiver)
+}
+
+/// Connects the remote control to the input of the timer and runs the cycles.
+fn run_with_remote_control(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    abort: &AbortHandle,
+    remote: &RemoteControl,
+    callbacks: &SessionCallbacks,
+    sender: mpsc::Sender<String>,
+    receiver: &mpsc::Receiver<String>,
+) -> Result<(), SessionLimitReached> {
+    remote.connect(sender);
+    let mut records = Vec::new();
+    let result = run_cycles(
+        options,
+        watcher,
+        abort,
+        remote,
+        callbacks,
+        &mut records,
+        receiver,
     );
+    remote.disconnect();
+    callbacks.session_completed(records);
+    result
+}
 
-    // Convert the duration and additional duration to `Duration` type
-    let duration: Duration = Duration::from_secs((options.duration_pomodoro * 60) as u64);
-    let additional_duration: Duration =
-        Duration::from_secs((options.additional_duration * 60) as u64);
+/// Runs the Pomodoro sessions and breaks until all cycles finished or the timer is quit or aborted.
+///
+/// The records of the finished phases are added to `records`.
+fn run_cycles(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    abort: &AbortHandle,
+    remote: &RemoteControl,
+    callbacks: &SessionCallbacks,
+    records: &mut Vec<HistoryRecord>,
+    receiver: &mpsc::Receiver<String>,
+) -> Result<(), SessionLimitReached> {
+    let mut options = options.clone();
+    // Use the imported data
+    log::info!("Options: {}", options.to_redacted_json());
 
     let mut counter = 0;
     let mut input = String::new();
-    let end_event = || {
-        start_end_event(&options.end_event_pomodoro);
-    };
-    debug!("Starting input stream.");
-    let receiver = input_handler::start_input_stream();
     loop {
+        remote.set_completed_cycles(u32::try_from(counter).unwrap_or_default());
+        if cycles_finished(&options, counter) {
+            log::info!("All {} cycles finished.", counter);
+            break;
+        }
         // Check if the timer should be repeated
         if counter != 0 && !options.auto_start_pomodoro {
             input.clear();
-            input = ask_for_new_pomodoro(&receiver, &options);
+            input = ask_for_new_pomodoro(receiver, &options, watcher, abort);
         } else {
             input = "".to_string();
         }
 
         if input.trim().is_empty() {
-            let pomo_info = PomoInfo::from_options(options, counter);
+            let event_log = TimerEventLog::new();
+            apply_reloaded_options(&mut options, watcher, remote);
+            check_session_limit(&options)?;
+            let pomo_info = PomoInfo::from_options(&options, counter);
+            // Convert the duration to `Duration` type
+            let duration = Duration::from_secs((options.duration_pomodoro * 60) as u64);
+            let break_phase = if pomo_info.is_long_break_coming {
+                PomodoroPhase::LongBreak
+            } else {
+                PomodoroPhase::ShortBreak
+            };
+            let is_final_session = cycles_finished(&options, counter + 1);
+            start_current_end_event(
+                &options,
+                watcher,
+                |o| &o.event_start_pomodoro,
+                PomodoroPhase::Work,
+                duration,
+                false,
+            );
 
             let print_message = generate_print_message_before_pomodoro(&pomo_info, &options);
             println!("{}", print_message);
 
-            execute_timer(duration, &receiver, end_event);
+            let started_at = Local::now();
+            event_log.record(TimerEvent::PhaseStarted {
+                phase: PomodoroPhase::Work,
+                duration_secs: duration.as_secs(),
+            });
+            remote.start_phase(PomodoroPhase::Work, duration);
+            callbacks.phase_started(PomodoroPhase::Work);
+            let timed = execute_timer(
+                duration,
+                receiver,
+                abort,
+                &event_log,
+                remote,
+                options.theme.progress_style(PomodoroPhase::Work),
+                || {
+                    start_current_end_event(
+                        &options,
+                        watcher,
+                        |o| &o.end_event_pomodoro,
+                        break_phase,
+                        pomo_info.break_duration,
+                        is_final_session,
+                    )
+                },
+            );
+            let record = record_phase(
+                PomodoroPhase::Work,
+                started_at,
+                timed,
+                duration,
+                &options.work_label,
+                abort.reason(),
+            );
+            record_phase_end(&event_log, &record);
+            callbacks.phase_recorded(&record);
+            records.push(record.clone());
+            callbacks.phase_ended(PomodoroPhase::Work);
+            if record.aborted.is_some() {
+                write_event_log(&event_log);
+                return Ok(());
+            }
+            if record.completed {
+                start_recorded_session_events(&options, &record);
+            }
 
+            apply_reloaded_options(&mut options, watcher, remote);
+            let pomo_info = PomoInfo::from_options(&options, counter);
             if options.additional_duration != 0 {
                 let print_message =
                     generate_print_message_before_additional_break(&pomo_info, &options);
                 println!("{}", print_message);
-                time_with_progress_bar(additional_duration, &receiver, || {
-                    start_end_event(&options.end_event_additional_pomodoro)
-                });
+                let additional_duration =
+                    Duration::from_secs((options.additional_duration * 60) as u64);
+                remote.start_phase(PomodoroPhase::Work, additional_duration);
+                time_with_progress_bar(
+                    additional_duration,
+                    receiver,
+                    abort,
+                    &event_log,
+                    remote,
+                    options.theme.progress_style(PomodoroPhase::Work),
+                    || {
+                        start_current_end_event(
+                            &options,
+                            watcher,
+                            |o| &o.end_event_additional_pomodoro,
+                            break_phase,
+                            pomo_info.break_duration,
+                            is_final_session,
+                        )
+                    },
+                );
+                if abort.is_aborted() {
+                    write_event_log(&event_log);
+                    return Ok(());
+                }
             }
 
+            apply_reloaded_options(&mut options, watcher, remote);
+            let pomo_info = PomoInfo::from_options(&options, counter);
             if !pomo_info.break_duration.is_zero() {
                 if !options.auto_start_break {
                     if pomo_info.is_long_break_coming {
@@ -82,42 +416,390 @@ pub(crate) fn start_pomodoro(options: &PomodoroOptions) {
                             pomo_info.break_duration.as_secs() / 60
                         );
                     }
-                    loop {
-                        let pressed_key = receiver.recv().expect("Failed to receive input.");
-                        if pressed_key == "\n" {
-                            input = "".to_string();
-                            break;
-                        }
+                    if !wait_for_enter(receiver, abort) {
+                        write_event_log(&event_log);
+                        return Ok(());
                     }
                 }
                 let print_message = generate_print_message_before_break(&pomo_info, &options);
                 println!("{}", print_message);
 
                 // Check if any end event is LockScreen
-                let is_lock_screen = matches!(options.end_event_pomodoro, EndEvent::LockScreen)
-                    || matches!(options.end_event_additional_pomodoro, EndEvent::LockScreen);
-
-                if is_lock_screen && options.enforce_lock_screen {
-                    // Enforce mode: continuously lock screen during break (re-lock if unlocked)
-                    println!("Locking screen for break. Screen will re-lock if unlocked before break ends.");
-                    start_end_event_with_duration(&EndEvent::LockScreen, pomo_info.break_duration);
-                    println!("Break finished!");
-                } else if is_lock_screen && !options.enforce_lock_screen {
-                    // Non-enforce mode: lock once at start, then run normal timer
-                    println!("Locking screen for break.");
-                    start_end_event(&EndEvent::LockScreen);
-                    execute_timer(pomo_info.break_duration, &receiver, end_event);
+                let lock_screen_event = [
+                    &options.end_event_pomodoro,
+                    &options.end_event_additional_pomodoro,
+                ]
+                .into_iter()
+                .find(|end_event| matches!(end_event, EndEvent::LockScreen { .. }))
+                .cloned();
+                let work_duration = Duration::from_secs((options.duration_pomodoro * 60) as u64);
+                let end_event = || {
+                    start_current_end_event(
+                        &options,
+                        watcher,
+                        |o| &o.end_event_pomodoro,
+                        PomodoroPhase::Work,
+                        work_duration,
+                        false,
+                    )
+                };
+                let phase = if pomo_info.is_long_break_coming {
+                    PomodoroPhase::LongBreak
                 } else {
-                    // No lock screen event
-                    execute_timer(pomo_info.break_duration, &receiver, end_event);
+                    PomodoroPhase::ShortBreak
+                };
+                let started_at = Local::now();
+                event_log.record(TimerEvent::PhaseStarted {
+                    phase,
+                    duration_secs: pomo_info.break_duration.as_secs(),
+                });
+                remote.start_phase(phase, pomo_info.break_duration);
+                callbacks.phase_started(phase);
+
+                let timed = match lock_screen_event {
+                    Some(lock_screen_event) if options.enforce_lock_screen => {
+                        // Enforce mode: continuously lock screen during break (re-lock if unlocked)
+                        println!("Locking screen for break. Screen will re-lock if unlocked before break ends.");
+                        println!("Press 'q' to quit the break early.");
+                        let (sender, lock_events) = mpsc::channel();
+                        let event_log = &event_log;
+                        let stop = Arc::new(AtomicBool::new(false));
+                        let lock_config = options
+                            .lock_config
+                            .clone()
+                            .with_linux_lock_priority(options.linux_lock_priority.clone())
+                            .with_stop_signal(stop.clone());
+                        let clock = current_clock();
+                        let lock_started_at = clock.now();
+                        let outcome = thread::scope(|scope| {
+                            scope.spawn(move || {
+                                lock_events
+                                    .iter()
+                                    .filter_map(TimerEvent::from_lock_event)
+                                    .for_each(|event| event_log.record(event))
+                            });
+                            let lock = scope.spawn(|| {
+                                start_end_event_with_lock_config(
+                                    &lock_screen_event,
+                                    pomo_info.break_duration,
+                                    &lock_config,
+                                    Some(sender),
+                                )
+                            });
+                            wait_for_enforced_lock(receiver, abort, &stop, || lock.is_finished());
+                            match lock.join() {
+                                Ok(outcome) => outcome,
+                                Err(panic) => std::panic::resume_unwind(panic),
+                            }
+                        });
+                        log::info!(
+                            "The screen was re-locked {} times and locked for {} seconds.",
+                            outcome.relocks_performed,
+                            outcome.locked_duration.as_secs()
+                        );
+                        remote.end_phase();
+                        if outcome.early_cancelled {
+                            TimedPhase {
+                                elapsed: clock
+                                    .now()
+                                    .saturating_duration_since(lock_started_at)
+                                    .min(pomo_info.break_duration),
+                                skipped: false,
+                            }
+                        } else {
+                            println!("Break finished!");
+                            TimedPhase {
+                                elapsed: pomo_info.break_duration,
+                                skipped: false,
+                            }
+                        }
+                    }
+                    Some(lock_screen_event) => {
+                        // Non-enforce mode: lock once at start, then run normal timer
+                        println!("Locking screen for break.");
+                        let _caffeinate = SuspendedCaffeinate::suspend();
+                        start_end_event_for_break(
+                            &lock_screen_event,
+                            pomo_info.break_duration,
+                            &options.linux_lock_priority,
+                        );
+                        execute_timer(
+                            pomo_info.break_duration,
+                            receiver,
+                            abort,
+                            &event_log,
+                            remote,
+                            options.theme.progress_style(phase),
+                            end_event,
+                        )
+                    }
+                    None => {
+                        // No lock screen event
+                        execute_timer(
+                            pomo_info.break_duration,
+                            receiver,
+                            abort,
+                            &event_log,
+                            remote,
+                            options.theme.progress_style(phase),
+                            end_event,
+                        )
+                    }
+                };
+                let record = record_phase(
+                    phase,
+                    started_at,
+                    timed,
+                    pomo_info.break_duration,
+                    &options.break_label,
+                    abort.reason(),
+                );
+                record_phase_end(&event_log, &record);
+                callbacks.phase_recorded(&record);
+                records.push(record);
+                callbacks.phase_ended(phase);
+                if abort.is_aborted() {
+                    write_event_log(&event_log);
+                    return Ok(());
                 }
             }
+            write_event_log(&event_log);
         } else {
             break;
         }
         counter += 1;
     }
+    Ok(())
+}
+
+/// Checks whether the number of finished cycles reached `cycles` of the options.
+///
+/// Always returns false if the number of cycles is not limited.
+fn cycles_finished(options: &PomodoroOptions, finished: i32) -> bool {
+    options
+        .cycles
+        .is_some_and(|cycles| i64::from(finished) >= i64::from(cycles))
 }
+
+/// Checks that another Pomodoro session doesn't exceed `max_sessions_per_day`.
+///
+/// Prints a warning and allows the session if the history file cannot be read.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if the maximum number of sessions was completed today.
+fn check_session_limit(options: &PomodoroOptions) -> Result<(), SessionLimitReached> {
+    let Some(max) = options.max_sessions_per_day else {
+        return Ok(());
+    };
+    match history_file_path().and_then(|path| Statistics::from_history_file(&path)) {
+        Ok(statistics) => check_session_limit_of(&statistics, max),
+        Err(e) => {
+            log::warn!("Failed to check the maximum sessions per day: {:#}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Checks that another Pomodoro session doesn't exceed `max` Pomodoro sessions completed today
+/// according to the statistics.
+///
+/// # Errors
+///
+/// Returns `SessionLimitReached` if the maximum number of sessions was completed today.
+fn check_session_limit_of(statistics: &Statistics, max: u32) -> Result<(), SessionLimitReached> {
+    let completed = statistics.today_count();
+    if completed >= max {
+        return Err(SessionLimitReached { completed, max });
+    }
+    Ok(())
+}
+
+/// Replaces the options with the options reloaded by the watcher since the last call, if there
+/// are any, and applies the options changed with the remote control.
+///
+/// Options changed with the remote control are verified after they were merged and ignored
+/// with a warning if they are invalid, e.g. a negative duration.
+fn apply_reloaded_options(
+    options: &mut PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    remote: &RemoteControl,
+) {
+    if let Some(reloaded) = watcher.and_then(OptionsWatcher::take) {
+        options.log_diff(&reloaded);
+        *options = reloaded;
+    }
+    for partial in remote.take_options() {
+        let merged = PomodoroOptions::merge(options.clone(), partial);
+        match merged.verify() {
+            Ok(()) => {
+                log::info!("Applying the options changed with the remote control.");
+                *options = merged;
+            }
+            Err(e) => log::warn!(
+                "Ignoring the options changed with the remote control: {}",
+                e
+            ),
+        }
+    }
+}
+
+/// Starts the end event selected from the latest options.
+///
+/// The latest options are the options reloaded by the watcher if there are any, else the given options.
+///
+/// # Arguments
+/// * `options` - The current Pomodoro options.
+/// * `watcher` - The watcher of the options file.
+/// * `end_event` - Selects the end event to start from the options.
+/// * `next_phase` - The phase after the end event, e.g. a break after a Pomodoro session.
+/// * `next_duration` - The duration of the next phase.
+/// * `is_final` - Whether the last Pomodoro session of the cycles ended.
+fn start_current_end_event(
+    options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    end_event: fn(&PomodoroOptions) -> &EndEvent,
+    next_phase: PomodoroPhase,
+    next_duration: Duration,
+    is_final: bool,
+) {
+    let latest = watcher.and_then(OptionsWatcher::latest);
+    let options = latest.as_ref().unwrap_or(options);
+    let end_event = end_event(options);
+    with_media_keys(end_event, || {
+        start_end_event_for_phase_with_final(
+            end_event,
+            next_phase,
+            next_duration,
+            &options.linux_lock_priority,
+            is_final,
+        )
+    });
+}
+
+/// Starts the end event while the alarm is published as MPRIS2 media player if it plays a
+/// sound, so that its sounds can be stopped with the media keys, see `mpris::MprisPlayer`.
+#[cfg(all(feature = "mpris", target_os = "linux"))]
+fn with_media_keys(end_event: &EndEvent, start: impl FnOnce()) {
+    let plays_sound = end_event
+        .flatten()
+        .into_iter()
+        .any(|end_event| matches!(end_event, EndEvent::Sound { .. }));
+    if !plays_sound {
+        return start();
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    // The player is removed from the session bus when it is dropped after the end event
+    let _player = MprisPlayer::new(stop.clone())
+        .inspect_err(|e| log::warn!("Failed to publish the alarm as MPRIS2 media player: {}", e));
+    with_stop_signal(stop, start);
+}
+
+/// Starts the end event, the media keys are only supported with the `mpris` feature on Linux.
+#[cfg(not(all(feature = "mpris", target_os = "linux")))]
+fn with_media_keys(_end_event: &EndEvent, start: impl FnOnce()) {
+    start();
+}
+
+/// Records the finished phase in the history file.
+///
+/// The phase is completed if the elapsed time reached the duration of the phase and it was
+/// neither skipped to its end nor aborted.
+/// The label is sanitized before it is written.
+/// Prints a warning if the history file cannot be written.
+///
+/// # Returns
+/// The recorded record.
+fn record_phase(
+    phase: PomodoroPhase,
+    started_at: DateTime<Local>,
+    timed: TimedPhase,
+    duration: Duration,
+    label: &Option<String>,
+    aborted: Option<AbortReason>,
+) -> HistoryRecord {
+    let record = HistoryRecord {
+        started_at,
+        phase,
+        duration_secs: timed.elapsed.as_secs(),
+        completed: timed.elapsed >= duration && !timed.skipped && aborted.is_none(),
+        skipped: timed.skipped,
+        label: label
+            .as_deref()
+            .map(sanitize_label)
+            .filter(|label| !label.is_empty()),
+        aborted,
+    };
+    if let Err(e) = history_file_path().and_then(|path| append_to_history(&path, &record)) {
+        log::warn!(
+            "Failed to record the {} phase in the history file: {:#}",
+            phase,
+            e
+        );
+    }
+    record
+}
+
+/// Records the end of the phase of the history record in the event log.
+fn record_phase_end(event_log: &TimerEventLog, record: &HistoryRecord) {
+    event_log.record(TimerEvent::PhaseEnded {
+        phase: record.phase,
+        elapsed_secs: record.duration_secs,
+        completed: record.completed,
+    });
+}
+
+/// Writes the event log of the Pomodoro session to the directory of the session logs.
+///
+/// Prints a warning if the event log cannot be written.
+fn write_event_log(event_log: &TimerEventLog) {
+    match session_log_dir().and_then(|dir| event_log.write_to_dir(&dir)) {
+        Ok(Some(path)) => log::debug!("Event log written to {:?}.", path),
+        Ok(None) => (),
+        Err(e) => log::warn!("Failed to write the event log: {:#}", e),
+    }
+}
+
+/// Starts the end events after the session which need the recorded session.
+///
+/// For every `SyncCalendar` end event, a calendar entry of the session is written.
+/// For every `Email` end event, the summary of today is sent.
+/// The end events of sequences are included.
+/// Prints a warning if an event fails.
+fn start_recorded_session_events(options: &PomodoroOptions, record: &HistoryRecord) {
+    let end_events = [
+        &options.end_event_pomodoro,
+        &options.end_event_additional_pomodoro,
+    ];
+    for end_event in end_events.into_iter().flat_map(EndEvent::flatten) {
+        match end_event {
+            EndEvent::SyncCalendar { ics_output_dir, .. } => {
+                match write_ics_file(ics_output_dir, record) {
+                    Ok(path) => log::info!("Calendar entry written to {:?}.", path),
+                    Err(e) => log::warn!("Failed to write the calendar entry: {:#}", e),
+                }
+            }
+            EndEvent::Email {
+                to,
+                subject,
+                smtp_config,
+                ..
+            } => {
+                let result = history_file_path()
+                    .and_then(|path| Statistics::from_history_file(&path))
+                    .and_then(|statistics| {
+                        send_summary_email(to, subject, smtp_config, &statistics.today_summary())
+                    });
+                match result {
+                    Ok(()) => log::info!("Summary sent to {}.", to),
+                    Err(e) => log::warn!("Failed to send the summary: {:#}", e),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
 /// Asks the user if they want to repeat the Pomodoro timer.
 ///
 /// This function prompts the user to press enter to repeat the timer or 'q' to quit.
@@ -127,21 +809,32 @@ pub(crate) fn start_pomodoro(options: &PomodoroOptions) {
 /// # Arguments
 /// * `receiver` - The receiver for input events.
 /// * `options` - The Pomodoro options.
+/// * `watcher` - The watcher of the options file.
+/// * `abort` - The handle to abort the timer.
 ///
 /// # Returns
-/// A string indicating the user's choice.
+/// A string indicating the user's choice, which is `q` if the timer was aborted.
 fn ask_for_new_pomodoro(
     receiver: &std::sync::mpsc::Receiver<String>,
     options: &PomodoroOptions,
+    watcher: Option<&OptionsWatcher>,
+    abort: &AbortHandle,
 ) -> String {
     let input;
     println!("Do you want to repeat the timer? (Press enter to repeat and 'q' to quit.)");
-    let mut start_time = Instant::now();
+    let clock = current_clock();
+    let mut start_time = clock.now();
     loop {
+        if abort.is_aborted() {
+            input = "q".to_string();
+            break;
+        }
         let pressed_key = receiver.try_recv();
         match pressed_key {
             Ok(pressed_key) => {
-                if pressed_key == "q" {
+                if pressed_key == "ctrl+c" {
+                    abort.abort(AbortReason::UserRequested);
+                } else if pressed_key == "q" {
                     input = "q".to_string();
                     break;
                 } else if pressed_key == "\n" {
@@ -152,11 +845,18 @@ fn ask_for_new_pomodoro(
             Err(_) => {
                 let reminder_is_active = options.interval_reminder_after_break != 0;
                 if reminder_is_active {
-                    let elapsed_time = start_time.elapsed().as_secs();
+                    let elapsed_time = clock.now().saturating_duration_since(start_time).as_secs();
                     if elapsed_time >= options.interval_reminder_after_break as u64 * 60 {
                         println!("Get back to work!");
-                        start_end_event(&options.event_reminder_after_break);
-                        start_time = Instant::now();
+                        start_current_end_event(
+                            options,
+                            watcher,
+                            |o| &o.event_reminder_after_break,
+                            PomodoroPhase::Work,
+                            Duration::from_secs((options.duration_pomodoro * 60) as u64),
+                            false,
+                        );
+                        start_time = clock.now();
                     }
                 }
             }
@@ -165,6 +865,64 @@ fn ask_for_new_pomodoro(
     input
 }
 
+/// Waits until the enter key is pressed.
+///
+/// Pressing ctrl+c aborts the timer.
+///
+/// # Returns
+/// `true` if the enter key was pressed, `false` if the timer was aborted.
+fn wait_for_enter(receiver: &std::sync::mpsc::Receiver<String>, abort: &AbortHandle) -> bool {
+    while !abort.is_aborted() {
+        match receiver.recv_timeout(ABORT_CHECK_INTERVAL) {
+            Ok(pressed_key) if pressed_key == "\n" => return true,
+            Ok(pressed_key) if pressed_key == "ctrl+c" => abort.abort(AbortReason::UserRequested),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => panic!("Failed to receive input."),
+        }
+    }
+    false
+}
+
+/// Waits until the enforced screen lock of a break finished.
+///
+/// Pressing 'q', a remote stop and ctrl+c set the stop signal of the lock to end the break
+/// early, ctrl+c and the abort handle also abort the timer. Other keys are ignored.
+fn wait_for_enforced_lock(
+    receiver: &std::sync::mpsc::Receiver<String>,
+    abort: &AbortHandle,
+    stop: &AtomicBool,
+    is_finished: impl Fn() -> bool,
+) {
+    while !is_finished() {
+        if abort.is_aborted() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        match receiver.recv_timeout(ABORT_CHECK_INTERVAL) {
+            Ok(pressed_key) if pressed_key == "q" => {
+                println!("Exiting the current timer.");
+                stop.store(true, Ordering::Relaxed);
+            }
+            Ok(pressed_key) if pressed_key == "ctrl+c" => {
+                println!("Exiting the program.");
+                abort.abort(AbortReason::UserRequested);
+            }
+            Ok(pressed_key) => debug!("Input ignored while the screen is locked: {}", pressed_key),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => panic!("Failed to receive input."),
+        }
+    }
+}
+
+/// The time spent in a phase timed with `time_with_progress_bar`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimedPhase {
+    /// The elapsed time, which is less than the duration if the phase was quit, aborted or
+    /// skipped to its end.
+    elapsed: Duration,
+    /// Whether the phase was skipped to its end with `e`.
+    skipped: bool,
+}
+
 /// Executes the timer with the specified duration.
 ///
 /// This function runs the timer for the given duration and executes the end event when the timer ends.
@@ -172,47 +930,81 @@ fn ask_for_new_pomodoro(
 /// # Arguments
 /// * `duration` - The duration of the timer.
 /// * `receiver` - The receiver for input events.
+/// * `abort` - The handle to abort the timer.
+/// * `event_log` - The event log pausing and resuming the timer is recorded in.
+/// * `remote` - The remote control the remaining time is reported to.
+/// * `style` - The style of the progress bar.
 /// * `end_event` - The function to execute when the timer ends.
+///
+/// # Returns
+/// The time spent in the phase.
 fn execute_timer<F: Fn()>(
     duration: Duration,
     receiver: &std::sync::mpsc::Receiver<String>,
+    abort: &AbortHandle,
+    event_log: &TimerEventLog,
+    remote: &RemoteControl,
+    style: ProgressStyle,
     end_event: F,
-) {
-    time_with_progress_bar(duration, &receiver, end_event);
-    println!("Times up!");
+) -> TimedPhase {
+    let timed = time_with_progress_bar(
+        duration, receiver, abort, event_log, remote, style, end_event,
+    );
+    if !abort.is_aborted() {
+        println!("Times up!");
+    }
+    timed
 }
 
 /// Executes the timer with the specified duration and displays a progress bar.
 ///
 /// The timer runs in a separate thread and increments the progress bar every second.
 /// It can be paused and resumed using the 'p' and 'r' keys respectively.
-/// Also it can be stopped using the 'q' key.
+/// Also it can be stopped using the 'q' key or aborted with ctrl+c or the abort handle.
+/// The 'e' key skips to the end of the timer, which then ends like after its duration.
 ///
 /// # Arguments
 /// * `duration` - The duration of the timer.
 /// * `receiver` - The receiver for input events.
+/// * `abort` - The handle to abort the timer.
+/// * `event_log` - The event log pausing and resuming the timer is recorded in.
+/// * `remote` - The remote control the remaining time is reported to.
+/// * `style` - The style of the progress bar, see `ThemeConfig::progress_style`.
 /// * `end_event` - The function to execute when the timer ends.
+///
+/// # Returns
+/// The time spent in the phase. The elapsed time is less than the duration if the timer was
+/// quit, aborted or skipped to its end.
 fn time_with_progress_bar<F: Fn()>(
     duration: Duration,
     receiver: &std::sync::mpsc::Receiver<String>,
+    abort: &AbortHandle,
+    event_log: &TimerEventLog,
+    remote: &RemoteControl,
+    style: ProgressStyle,
     end_event: F,
-) {
-    let timer = Timer::new(duration);
+) -> TimedPhase {
+    let clock = current_clock();
+    let timer = Timer::with_clock(duration, clock.clone());
     let mut bar = ProgressBar::new(duration.as_secs());
-    bar.set_style(
-        ProgressStyle::with_template("[{elapsed}/{eta}] {wide_bar:.cyan/blue} ").unwrap(),
-    );
+    bar.set_style(style);
     let delta: u64 = 100;
     let mut cumulative_delta: u64 = 0;
     timer.start();
-    println!("Press 'p' to pause, 'q' to quit current timer and 's' to skip 1 minute.");
+    println!("Press 'p' to pause, 'q' to quit current timer, 's' to skip 1 minute and 'e' to skip to the end.");
     let mut control_flow;
     while timer.get_elapsed_time() < duration {
-        (bar,control_flow) = handle_user_input(receiver, &timer, bar);
-        if control_flow == ControlFlow::Break(()) {
-            return;
+        (bar, control_flow) = handle_user_input(receiver, &timer, bar, abort, event_log);
+        remote.update_phase(timer.remaining(), timer.is_paused());
+        if control_flow == ControlFlow::Break(()) || abort.is_aborted() {
+            bar.abandon();
+            remote.end_phase();
+            return TimedPhase {
+                elapsed: timer.get_elapsed_time(),
+                skipped: false,
+            };
         }
-        thread::sleep(Duration::from_millis(delta));
+        clock.sleep(Duration::from_millis(delta));
         if !timer.is_paused() {
             cumulative_delta += delta;
             if cumulative_delta >= 1000 {
@@ -222,7 +1014,12 @@ fn time_with_progress_bar<F: Fn()>(
         }
     }
     bar.finish();
+    remote.end_phase();
     end_event();
+    TimedPhase {
+        elapsed: timer.skipped_at().unwrap_or(duration),
+        skipped: timer.skipped_at().is_some(),
+    }
 }
 
 /// Handles user input during the timer execution.
@@ -234,21 +1031,30 @@ fn time_with_progress_bar<F: Fn()>(
 /// * `receiver` - The receiver for input events.
 /// * `timer` - The timer instance.
 /// * `bar` - The progress bar instance.
+/// * `abort` - The handle to abort the timer, which is aborted on ctrl+c.
+/// * `event_log` - The event log pausing and resuming the timer is recorded in.
 ///
 /// # Returns
 /// A tuple containing the updated progress bar and a control flow indicating whether to continue or break.
-fn handle_user_input(receiver: &std::sync::mpsc::Receiver<String>, timer: &Timer, mut bar: ProgressBar) -> (ProgressBar,  ControlFlow<()>)
- {
+fn handle_user_input(
+    receiver: &std::sync::mpsc::Receiver<String>,
+    timer: &Timer,
+    mut bar: ProgressBar,
+    abort: &AbortHandle,
+    event_log: &TimerEventLog,
+) -> (ProgressBar, ControlFlow<()>) {
     if let Ok(input) = receiver.try_recv() {
         if input == "p" {
             timer.pause();
+            event_log.record(TimerEvent::Paused);
             println!("Timer paused.");
             println!("Press 'r' to resume, 'q' to quit current timer.");
         } else if input == "r" {
             timer.resume();
+            event_log.record(TimerEvent::Resumed);
             println!("Timer resumed.");
             println!(
-                "Press 'p' to pause, 'q' to quit current timer and 's' to skip 1 minute."
+                "Press 'p' to pause, 'q' to quit current timer, 's' to skip 1 minute and 'e' to skip to the end."
             );
             bar = bar.with_elapsed(timer.get_elapsed_time());
             bar.reset_eta();
@@ -265,9 +1071,23 @@ fn handle_user_input(receiver: &std::sync::mpsc::Receiver<String>, timer: &Timer
             bar.set_position(timer.get_elapsed_time().as_secs());
             bar.reset_eta();
             log::trace!("Progress bar updated.");
+        } else if input == "e" {
+            println!("Skipping to the end of the current timer.");
+            timer.skip_to_end();
+            event_log.record(TimerEvent::SkippedToEnd);
+            bar.set_position(timer.get_elapsed_time().as_secs());
+        } else if input == "status" {
+            let remaining = timer.remaining().as_secs();
+            println!(
+                "{:02}:{:02} remaining{}.",
+                remaining / 60,
+                remaining % 60,
+                if timer.is_paused() { ", paused" } else { "" }
+            );
         } else if input == "ctrl+c" {
             println!("Exiting the program.");
-            std::process::exit(0);
+            abort.abort(AbortReason::UserRequested);
+            return (bar, ControlFlow::Break(()));
         } else {
             debug!("Invalid input: {}", input);
         }
@@ -275,3 +1095,43 @@ fn handle_user_input(receiver: &std::sync::mpsc::Receiver<String>, timer: &Timer
     }
     (bar, ControlFlow::Continue(()))
 }
+
+#[test]
+fn test_check_session_limit_of() {
+    let record = HistoryRecord {
+        started_at: Local::now(),
+        phase: PomodoroPhase::Work,
+        duration_secs: 25 * 60,
+        completed: true,
+        skipped: false,
+        label: None,
+        aborted: None,
+    };
+    let statistics = Statistics::new(vec![record.clone(), record]);
+    assert!(check_session_limit_of(&statistics, 3).is_ok());
+    let error = check_session_limit_of(&statistics, 2).unwrap_err();
+    assert_eq!((error.completed, error.max), (2, 2));
+    assert_eq!(
+        error.to_string(),
+        "2 Pomodoro sessions were completed today, the maximum is 2."
+    );
+    assert!(check_session_limit_of(&Statistics::new(Vec::new()), 0).is_err());
+}
+
+#[test]
+fn test_cycles_finished() {
+    let options = PomodoroOptions::default();
+    assert!(!cycles_finished(&options, 0));
+    assert!(!cycles_finished(&options, i32::MAX));
+    let options = PomodoroOptions {
+        cycles: Some(1),
+        ..Default::default()
+    };
+    assert!(!cycles_finished(&options, 0));
+    assert!(cycles_finished(&options, 1));
+    let options = PomodoroOptions {
+        cycles: Some(0),
+        ..Default::default()
+    };
+    assert!(cycles_finished(&options, 0));
+}
@@ -0,0 +1,232 @@
+//! This module contains the clocks the timer waits and measures time with, so that the time can
+//! run faster than real time with `--simulate`, e.g. to test the options without waiting for a
+//! full Pomodoro session.
+//!
+//! The timer, the end events waiting for the phases and the lock monitoring use the clock of
+//! `current_clock`. Waiting for external programs, e.g. retrying to lock the screen or fading
+//! out a sound, always takes real time.
+//!
+//! In the browser, i.e. with the `wasm` feature on `wasm32`, the clock of `current_clock` is
+//! the `BrowserClock`, since the clock and `thread::sleep` of the standard library are not
+//! available there.
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// The point in time the clocks return, which is `std::time::Instant` except in the browser,
+/// where it is measured with `performance.now()`.
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+pub use std::time::Instant;
+/// The point in time the clocks return, which is `std::time::Instant` except in the browser,
+/// where it is measured with `performance.now()`.
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use web_time::Instant;
+
+/// A source of the current time which can also wait.
+pub trait Clock: Send + Sync {
+    /// Gets the current time.
+    fn now(&self) -> Instant;
+    /// Blocks the current thread for the duration.
+    fn sleep(&self, duration: Duration);
+    /// Gets how many times faster than real time the clock runs.
+    fn speed(&self) -> f32 {
+        1.0
+    }
+}
+
+/// The clock of the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// The clock of the browser, which measures the time with `performance.now()`.
+///
+/// The main thread of a browser must not block, so `sleep` waits actively and should only be
+/// used for short durations or in a web worker. Asynchronous code should wait with
+/// `sleep_async` instead.
+///
+/// This clock is only available with the `wasm` feature on `wasm32`.
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BrowserClock;
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+impl Clock for BrowserClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let end = Instant::now() + duration;
+        while Instant::now() < end {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Waits for the duration with `setTimeout` of the browser without blocking the thread, e.g.
+/// between the updates of a web page showing the remaining time of a `Timer`.
+///
+/// This function is only available with the `wasm` feature on `wasm32`.
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub async fn sleep_async(duration: Duration) {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let millis = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        // `setTimeout` of the global object works in windows and web workers
+        let set_timeout = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))
+            .and_then(JsCast::dyn_into::<js_sys::Function>);
+        let result = set_timeout.and_then(|set_timeout| {
+            set_timeout.call2(&JsValue::UNDEFINED, &resolve, &JsValue::from(millis))
+        });
+        if let Err(e) = result {
+            let _ = reject.call1(&JsValue::UNDEFINED, &e);
+        }
+    });
+    if let Err(e) = wasm_bindgen_futures::JsFuture::from(promise).await {
+        log::warn!("Failed to wait with setTimeout: {:?}", e);
+    }
+}
+
+/// A clock which runs faster than real time by a factor, so that all durations waited for are
+/// divided by the factor, e.g. a Pomodoro session of 25 minutes takes 25 seconds at 60×.
+#[derive(Debug, Clone, Copy)]
+pub struct TestClock {
+    start: Instant,
+    speed: f32,
+}
+
+impl TestClock {
+    /// Creates a clock which starts at the current time and runs at the speed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the speed is not a positive finite number, see `is_valid_speed`.
+    #[must_use]
+    pub fn new(speed: f32) -> Self {
+        assert!(is_valid_speed(speed), "Invalid clock speed {}.", speed);
+        TestClock {
+            start: Instant::now(),
+            speed,
+        }
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.start + self.start.elapsed().mul_f32(self.speed)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration.div_f32(self.speed));
+    }
+
+    fn speed(&self) -> f32 {
+        self.speed
+    }
+}
+
+/// A clock which only advances when it is slept with, so that tests waiting with it finish
+/// immediately and don't depend on the speed of the machine.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct ManualClock {
+    start: Instant,
+    elapsed: std::sync::Mutex<Duration>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    /// Creates a clock which starts at the current time and stands still until it is slept with.
+    pub(crate) fn new() -> Self {
+        ManualClock {
+            start: Instant::now(),
+            elapsed: std::sync::Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Locks the time the clock advanced since its start.
+    fn lock_elapsed(&self) -> std::sync::MutexGuard<'_, Duration> {
+        self.elapsed
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.lock_elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        *self.lock_elapsed() += duration;
+    }
+}
+
+/// The clock set with `simulate`.
+static SIMULATED_CLOCK: OnceLock<TestClock> = OnceLock::new();
+
+/// Checks that the speed is a positive finite number.
+#[must_use]
+pub fn is_valid_speed(speed: f32) -> bool {
+    speed.is_finite() && speed > 0.0
+}
+
+/// Makes the time of the timer run at the speed for the rest of the process, see
+/// `current_clock`. Should be called before the timer is started.
+///
+/// # Returns
+/// `false` if the time is already simulated, in which case the speed is not changed.
+///
+/// # Panics
+///
+/// Panics if the speed is not a positive finite number, see `is_valid_speed`.
+pub fn simulate(speed: f32) -> bool {
+    let is_set = SIMULATED_CLOCK.set(TestClock::new(speed)).is_ok();
+    if is_set {
+        log::info!("Simulating the time at {}× speed.", speed);
+    }
+    is_set
+}
+
+/// Gets the clock of the timer, which is the clock set with `simulate` or else the clock of the
+/// operating system, or the `BrowserClock` in the browser.
+#[must_use]
+pub fn current_clock() -> Arc<dyn Clock> {
+    match SIMULATED_CLOCK.get() {
+        Some(clock) => Arc::new(*clock),
+        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+        None => Arc::new(BrowserClock),
+        #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+        None => Arc::new(SystemClock),
+    }
+}
+
+#[test]
+fn test_test_clock() {
+    assert!(is_valid_speed(0.5));
+    assert!(!is_valid_speed(0.0));
+    assert!(!is_valid_speed(-2.0));
+    assert!(!is_valid_speed(f32::NAN));
+    assert!(!is_valid_speed(f32::INFINITY));
+
+    let clock = TestClock::new(100.0);
+    let started_at = clock.now();
+    let real_start = Instant::now();
+    clock.sleep(Duration::from_secs(2));
+    assert!(real_start.elapsed() < Duration::from_secs(1));
+    assert!(clock.now() - started_at >= Duration::from_secs(2));
+    assert_eq!(clock.speed(), 100.0);
+    assert_eq!(SystemClock.speed(), 1.0);
+}
@@ -0,0 +1,140 @@
+//! This module shows the remaining time of a break on the screen for the `ShowCountdown` end
+//! event, e.g. as reminder at a standing desk, without locking the screen.
+//!
+//! On Linux, the countdown is shown with `osd_cat` on X11. If `osd_cat` is not available, a
+//! notification is sent instead, which notification daemons like `dunst` replace in place.
+use crate::clock::current_clock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// The interval in which the remaining time of the countdown is updated.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The position of the countdown on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScreenPosition {
+    /// The top left corner.
+    TopLeft,
+    /// The top right corner.
+    #[default]
+    TopRight,
+    /// The bottom left corner.
+    BottomLeft,
+    /// The bottom right corner.
+    BottomRight,
+    /// The center of the screen.
+    Center,
+}
+
+impl ScreenPosition {
+    /// Gets the vertical position and the alignment as arguments of `osd_cat`.
+    fn osd_cat_args(self) -> [&'static str; 2] {
+        match self {
+            ScreenPosition::TopLeft => ["--pos=top", "--align=left"],
+            ScreenPosition::TopRight => ["--pos=top", "--align=right"],
+            ScreenPosition::BottomLeft => ["--pos=bottom", "--align=left"],
+            ScreenPosition::BottomRight => ["--pos=bottom", "--align=right"],
+            ScreenPosition::Center => ["--pos=middle", "--align=center"],
+        }
+    }
+}
+
+/// Shows the remaining time on the screen and updates it every 30 seconds until the duration
+/// expired. Blocks until then.
+///
+/// Prints a warning if the countdown cannot be shown on this platform, but still waits for the
+/// duration, so that a break waiting for the countdown doesn't end early.
+pub fn show_countdown(duration: Duration, position: ScreenPosition) {
+    let clock = current_clock();
+    if !cfg!(target_os = "linux") {
+        log::warn!("Showing a countdown is only supported on Linux.");
+        clock.sleep(duration);
+        return;
+    }
+    let end_time = clock.now() + duration;
+    let mut use_osd_cat = true;
+    loop {
+        let started_at = clock.now();
+        let remaining = end_time.saturating_duration_since(started_at);
+        if remaining.is_zero() {
+            break;
+        }
+        let interval = remaining.min(UPDATE_INTERVAL);
+        // The text is shown in real time, which is shorter if the time is simulated
+        let display_time = interval.div_f32(clock.speed());
+        let text = format!("Break: {} remaining", format_countdown(remaining));
+        if use_osd_cat {
+            if let Err(e) = show_with_osd_cat(&text, position, display_time) {
+                log::debug!(
+                    "Failed to show the countdown with 'osd_cat': {}. Sending notifications instead.",
+                    e
+                );
+                use_osd_cat = false;
+            }
+        }
+        if !use_osd_cat {
+            show_with_notification(&text, display_time);
+        }
+        clock.sleep(interval.saturating_sub(clock.now().saturating_duration_since(started_at)));
+    }
+}
+
+/// Formats the remaining time rounded up to full seconds as `M:SS`, e.g. `4:30`.
+fn format_countdown(remaining: Duration) -> String {
+    let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Shows the text with `osd_cat` for the duration and waits until it disappeared.
+fn show_with_osd_cat(text: &str, position: ScreenPosition, duration: Duration) -> io::Result<()> {
+    let mut child = Command::new("osd_cat")
+        .args(position.osd_cat_args())
+        .arg(format!("--delay={}", duration.as_secs().max(1)))
+        .args(["--shadow=2", "--outline=1"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{}", text)?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Sends the text as notification which expires after the duration. The stack tag makes `dunst`
+/// replace the previous notification of the countdown instead of showing another one.
+fn show_with_notification(text: &str, duration: Duration) {
+    let result = Command::new("notify-send")
+        .arg(format!("--expire-time={}", duration.as_millis()))
+        .args([
+            "--hint=string:x-dunst-stack-tag:locking-pomodoro-timer-countdown",
+            "Locking Pomodoro Timer",
+            text,
+        ])
+        .output();
+    match result {
+        Ok(output) if output.status.success() => (),
+        _ => log::warn!("Failed to show the countdown with 'notify-send'."),
+    }
+}
+
+#[test]
+fn test_format_countdown() {
+    assert_eq!(format_countdown(Duration::from_secs(270)), "4:30");
+    assert_eq!(format_countdown(Duration::from_millis(59_500)), "1:00");
+    assert_eq!(format_countdown(Duration::from_secs(5)), "0:05");
+    assert_eq!(
+        ScreenPosition::default().osd_cat_args(),
+        ["--pos=top", "--align=right"]
+    );
+}
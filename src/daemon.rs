@@ -0,0 +1,178 @@
+//! This module runs the timer in the background with `--daemon` on POSIX platforms, e.g. on a
+//! server or in a tmux session, and finds the running daemon by its PID file for `--status`.
+//!
+//! The daemon has no terminal, so it is best combined with `autoStartPomodoro` and
+//! `autoStartBreak`, or controlled with the JSON-RPC server of `--server`.
+#[cfg(unix)]
+use anyhow::bail;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the PID file in the runtime directory.
+const PID_FILE_NAME: &str = "pomodoro.pid";
+
+/// Gets the path of the PID file of the daemon, `$XDG_RUNTIME_DIR/pomodoro.pid`.
+///
+/// If the runtime directory is unknown, e.g. on macOS, the PID file is in the temporary directory.
+#[must_use]
+pub fn pid_file_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(PID_FILE_NAME)
+}
+
+/// Gets the path of the log file the output of the daemon is appended to, e.g.
+/// `~/.local/state/pomodoro/daemon.log` on Linux.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined.
+pub fn log_file_path() -> Result<PathBuf> {
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .context("Failed to determine the state directory.")?;
+    Ok(state_dir.join("pomodoro").join("daemon.log"))
+}
+
+/// Turns the process into a daemon by forking twice, so that it is detached from the terminal.
+///
+/// Stdin is read from `/dev/null`, stdout and stderr are appended to the log file and the PID of
+/// the daemon is written to the PID file. The calling processes exit, only the daemon returns.
+///
+/// This must be called before any other thread is started, as only the calling thread is forked.
+///
+/// # Errors
+///
+/// Returns an error if the daemon is already running, the files cannot be opened or forking fails.
+#[cfg(unix)]
+pub fn daemonize(log_file: &Path, pid_file: &Path) -> Result<()> {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+
+    if let Some(pid) = read_running_pid(pid_file)? {
+        bail!("The daemon is already running with PID {}.", pid);
+    }
+    if let Some(dir) = log_file.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create the directory {:?}", dir))?;
+    }
+    let log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .with_context(|| format!("Failed to open the log file {:?}", log_file))?;
+    let dev_null = fs::File::open("/dev/null").context("Failed to open /dev/null")?;
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+
+    fork_and_exit_parent()?;
+    // SAFETY: setsid has no preconditions.
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error()).context("Failed to create a new session");
+    }
+    // Fork again so that the daemon can never acquire a controlling terminal
+    fork_and_exit_parent()?;
+
+    for (file, fd) in [
+        (dev_null.as_raw_fd(), libc::STDIN_FILENO),
+        (log.as_raw_fd(), libc::STDOUT_FILENO),
+        (log.as_raw_fd(), libc::STDERR_FILENO),
+    ] {
+        // SAFETY: Both file descriptors are valid while the files are alive.
+        if unsafe { libc::dup2(file, fd) } == -1 {
+            return Err(io::Error::last_os_error()).context("Failed to redirect the output");
+        }
+    }
+    fs::write(pid_file, format!("{}\n", std::process::id()))
+        .with_context(|| format!("Failed to write the PID file {:?}", pid_file))?;
+    log::info!("Daemon started with PID {}.", std::process::id());
+    Ok(())
+}
+
+/// Forks the process and exits in the parent process, so that only the child returns.
+#[cfg(unix)]
+fn fork_and_exit_parent() -> Result<()> {
+    // SAFETY: No other threads are running, see `daemonize`.
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()).context("Failed to fork the daemon"),
+        0 => Ok(()),
+        // SAFETY: _exit ends the parent process without running any handlers of the child.
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+/// Reads the PID of the daemon from the PID file and checks that the process is still running.
+///
+/// # Returns
+/// The PID, or `None` if there is no PID file or the process is not running anymore.
+///
+/// # Errors
+///
+/// Returns an error if the PID file cannot be read or doesn't contain a PID.
+pub fn read_running_pid(pid_file: &Path) -> Result<Option<u32>> {
+    let content = match fs::read_to_string(pid_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read the PID file {:?}", pid_file))
+        }
+    };
+    let pid = content
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid PID in {:?}", pid_file))?;
+    Ok(is_process_running(pid).then_some(pid))
+}
+
+/// Removes the PID file if it belongs to this process, e.g. when the daemon finished.
+pub fn remove_pid_file(pid_file: &Path) {
+    let is_own = fs::read_to_string(pid_file)
+        .is_ok_and(|content| content.trim() == std::process::id().to_string());
+    if is_own {
+        if let Err(e) = fs::remove_file(pid_file) {
+            log::warn!("Failed to remove the PID file {:?}: {}", pid_file, e);
+        }
+    }
+}
+
+/// Checks whether a process with the PID is running by sending it the null signal.
+#[cfg(unix)]
+fn is_process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: The null signal only checks whether the process exists.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Checks whether a process with the PID is running. Always `false` on non-POSIX platforms,
+/// where no daemon can be started.
+#[cfg(not(unix))]
+fn is_process_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_running_pid() {
+    let pid_file = std::env::temp_dir().join("locking_pomodoro_timer_test_daemon.pid");
+    let _ = fs::remove_file(&pid_file);
+    assert_eq!(read_running_pid(&pid_file).unwrap(), None);
+    fs::write(&pid_file, format!("{}\n", std::process::id())).unwrap();
+    assert_eq!(
+        read_running_pid(&pid_file).unwrap(),
+        Some(std::process::id())
+    );
+    fs::write(&pid_file, format!("{}\n", i32::MAX)).unwrap();
+    assert_eq!(read_running_pid(&pid_file).unwrap(), None);
+    remove_pid_file(&pid_file);
+    assert!(pid_file.exists());
+    fs::write(&pid_file, "no pid").unwrap();
+    assert!(read_running_pid(&pid_file).is_err());
+    fs::write(&pid_file, std::process::id().to_string()).unwrap();
+    remove_pid_file(&pid_file);
+    assert!(!pid_file.exists());
+}
@@ -0,0 +1,129 @@
+//! This module sends the summary of the Pomodoro sessions of the day via SMTP for the `Email` end event.
+//!
+//! Sending emails requires the `native` feature.
+use crate::statistics::SessionSummary;
+#[cfg(feature = "native")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "native")]
+use lettre::message::header::ContentType;
+#[cfg(feature = "native")]
+use lettre::transport::smtp::authentication::Credentials;
+#[cfg(feature = "native")]
+use lettre::{Message, SmtpTransport, Transport};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The encryption of the connection to the SMTP server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SmtpSecurity {
+    /// Upgrade the connection to TLS with `STARTTLS`, usually on port 587.
+    #[default]
+    StartTls,
+    /// Connect with TLS from the start, usually on port 465.
+    Tls,
+    /// Don't encrypt the connection. Only use this for a server on the local machine.
+    None,
+}
+
+/// The configuration of the SMTP server the emails are sent with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpConfig {
+    /// The host name of the SMTP server, e.g. `smtp.example.com`.
+    pub host: String,
+    /// The port of the SMTP server.
+    pub port: u16,
+    /// The encryption of the connection.
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    /// The user name to log in to the SMTP server. Also used as sender address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// The password to log in to the SMTP server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Sends the summary as pretty-printed JSON in the body of an email.
+///
+/// # Arguments
+/// * `to` - The address the email is sent to.
+/// * `subject` - The subject of the email.
+/// * `smtp_config` - The configuration of the SMTP server.
+/// * `summary` - The summary of the Pomodoro sessions.
+///
+/// # Errors
+///
+/// Returns an error if an address is invalid or the email cannot be sent.
+#[cfg(feature = "native")]
+pub fn send_summary_email(
+    to: &str,
+    subject: &str,
+    smtp_config: &SmtpConfig,
+    summary: &SessionSummary,
+) -> Result<()> {
+    let from = smtp_config.username.as_deref().unwrap_or(to);
+    let from = from
+        .parse()
+        .with_context(|| format!("Invalid sender address: {}", from))?;
+    let to = to
+        .parse()
+        .with_context(|| format!("Invalid recipient address: {}", to))?;
+    let email = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(serde_json::to_string_pretty(summary)?)
+        .context("Failed to create the email.")?;
+
+    let builder = match smtp_config.security {
+        SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&smtp_config.host)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(&smtp_config.host)?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(&smtp_config.host),
+    };
+    let mut builder = builder.port(smtp_config.port);
+    if let (Some(username), Some(password)) = (&smtp_config.username, &smtp_config.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    builder
+        .build()
+        .send(&email)
+        .with_context(|| format!("Failed to send the email via {}", smtp_config.host))?;
+    Ok(())
+}
+
+/// Fails to send the summary like `send_summary_email`, since emails can only be sent with the
+/// `native` feature.
+///
+/// # Errors
+///
+/// Always returns an error.
+#[cfg(not(feature = "native"))]
+pub fn send_summary_email(
+    _to: &str,
+    _subject: &str,
+    _smtp_config: &SmtpConfig,
+    _summary: &SessionSummary,
+) -> Result<()> {
+    anyhow::bail!("Sending emails requires the `native` feature.")
+}
+
+/// Checks that the email address is valid, e.g. `pomodoro@example.com`.
+///
+/// Without the `native` feature, it is only checked that the address has a local part and a
+/// domain separated by `@`.
+pub(crate) fn is_valid_address(address: &str) -> bool {
+    #[cfg(feature = "native")]
+    {
+        address.parse::<lettre::Address>().is_ok()
+    }
+    #[cfg(not(feature = "native"))]
+    {
+        address
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && !domain.is_empty())
+    }
+}
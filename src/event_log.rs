@@ -0,0 +1,178 @@
+//! This module records the state transitions of the timer in an event log per Pomodoro session.
+//!
+//! The event log is more granular than the history file and is meant for debugging: besides the
+//! start and end of the phases, it contains when the timer was paused and resumed and every
+//! attempt to lock the screen during an enforced break. After each Pomodoro session and its
+//! break, the log is written as JSON file to the state directory of the user, e.g.
+//! `$XDG_STATE_HOME/pomodoro/sessions/2024-05-01T09-00-00.json` on Linux.
+use crate::pomodoro_phase::PomodoroPhase;
+use crate::screen_lock::LockEvent;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// A state transition of the timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "event",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum TimerEvent {
+    /// A phase started.
+    PhaseStarted {
+        /// The phase.
+        phase: PomodoroPhase,
+        /// The planned duration of the phase in seconds.
+        duration_secs: u64,
+    },
+    /// A phase ended, because it ran to the end, was quit or the timer was aborted.
+    PhaseEnded {
+        /// The phase.
+        phase: PomodoroPhase,
+        /// The time spent in the phase in seconds.
+        elapsed_secs: u64,
+        /// Whether the phase ran to the end.
+        completed: bool,
+    },
+    /// The timer was paused.
+    Paused,
+    /// The timer was resumed.
+    Resumed,
+    /// The phase was skipped to its end.
+    SkippedToEnd,
+    /// Locking the screen was attempted.
+    LockAttempted,
+    /// The screen was locked.
+    LockSucceeded,
+    /// Locking the screen failed.
+    LockFailed,
+    /// The screen was unlocked before the end of the break.
+    UnlockDetected,
+    /// The screen was locked again after an unlock.
+    Relocked,
+}
+
+impl TimerEvent {
+    /// Converts the event of the screen lock monitoring, or returns `None` for the events
+    /// which are no state transitions of their own.
+    #[must_use]
+    pub fn from_lock_event(event: LockEvent) -> Option<Self> {
+        match event {
+            LockEvent::LockAttempted => Some(TimerEvent::LockAttempted),
+            LockEvent::LockSucceeded => Some(TimerEvent::LockSucceeded),
+            LockEvent::LockFailed => Some(TimerEvent::LockFailed),
+            LockEvent::UnlockDetected => Some(TimerEvent::UnlockDetected),
+            LockEvent::Relocked => Some(TimerEvent::Relocked),
+            LockEvent::Locked | LockEvent::MonitoringEnded => None,
+        }
+    }
+}
+
+/// A state transition with the time it happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerEventRecord {
+    /// The time of the transition.
+    pub at: DateTime<Local>,
+    /// The transition.
+    #[serde(flatten)]
+    pub event: TimerEvent,
+}
+
+/// The state transitions of a Pomodoro session and its break.
+///
+/// The events can be recorded from several threads, e.g. by the screen lock monitoring.
+#[derive(Debug, Default)]
+pub struct TimerEventLog {
+    records: Mutex<Vec<TimerEventRecord>>,
+}
+
+impl TimerEventLog {
+    /// Creates a new empty event log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the event at the current time.
+    pub fn record(&self, event: TimerEvent) {
+        log::trace!("Timer event: {:?}", event);
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(TimerEventRecord {
+                at: Local::now(),
+                event,
+            });
+    }
+
+    /// Gets the recorded events in the order they happened.
+    #[must_use]
+    pub fn records(&self) -> Vec<TimerEventRecord> {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Writes the recorded events as pretty-printed JSON to a new file in the directory, which is
+    /// created if necessary. The file is named after the time of the first event.
+    ///
+    /// # Returns
+    /// The path of the written file, or `None` if no event was recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write_to_dir(&self, dir: &Path) -> Result<Option<PathBuf>> {
+        let records = self.records();
+        let Some(first) = records.first() else {
+            return Ok(None);
+        };
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+        let path = dir.join(format!("{}.json", first.at.format("%Y-%m-%dT%H-%M-%S")));
+        fs::write(&path, serde_json::to_string_pretty(&records)?)
+            .with_context(|| format!("Failed to write event log: {:?}", path))?;
+        Ok(Some(path))
+    }
+}
+
+/// Gets the directory of the event logs in the state directory of the user, which is
+/// `$XDG_STATE_HOME/pomodoro/sessions` on Linux. On other platforms, the local data directory
+/// is used instead.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined.
+pub fn session_log_dir() -> Result<PathBuf> {
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .context("Failed to determine the state directory.")?;
+    Ok(state_dir.join("pomodoro").join("sessions"))
+}
+
+#[test]
+fn test_write_event_log() {
+    let dir = std::env::temp_dir().join("locking-pomodoro-timer-test-event-log");
+    let _ = fs::remove_dir_all(&dir);
+    let event_log = TimerEventLog::new();
+    assert_eq!(event_log.write_to_dir(&dir).unwrap(), None);
+
+    event_log.record(TimerEvent::PhaseStarted {
+        phase: PomodoroPhase::Work,
+        duration_secs: 1500,
+    });
+    event_log.record(TimerEvent::Paused);
+    let path = event_log.write_to_dir(&dir).unwrap().unwrap();
+    let json = fs::read_to_string(&path).unwrap();
+    assert!(json.contains(r#""event": "phaseStarted""#));
+    assert!(json.contains(r#""durationSecs": 1500"#));
+    let records: Vec<TimerEventRecord> = serde_json::from_str(&json).unwrap();
+    assert_eq!(records, event_log.records());
+    assert_eq!(records[1].event, TimerEvent::Paused);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,125 @@
+//! This module sends D-Bus signals to GNOME Shell extensions for the `GnomeExtensionSignal` end
+//! event, so that an extension can react to the end of a Pomodoro session, e.g. by changing its
+//! panel indicator.
+//!
+//! The signals are broadcast on the session bus from the object path
+//! `/org/gnome/Shell/Extensions/<uuid>` with the interface `org.gnome.Shell.Extensions.<uuid>`,
+//! where every character of the UUID of the extension which is not allowed in D-Bus names is
+//! replaced by `_`, e.g. `/org/gnome/Shell/Extensions/pomodoro_example_com` for
+//! `pomodoro@example.com`. The payload is the only argument of the signal if it is given.
+//!
+//! This module is only available on Linux with the `gnome` feature.
+//!
+//! # Examples
+//!
+//! An extension subscribes to the signals in its `enable()` like this:
+//!
+//! ```js
+//! this._subscription = Gio.DBus.session.signal_subscribe(
+//!     null, 'org.gnome.Shell.Extensions.pomodoro_example_com', 'PhaseEnded',
+//!     '/org/gnome/Shell/Extensions/pomodoro_example_com', null, Gio.DBusSignalFlags.NONE,
+//!     (connection, sender, path, iface, signal, parameters) => log(parameters.deep_unpack()));
+//! ```
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedValue;
+
+/// The environment variable which is set in GNOME Shell sessions.
+const SLOWDOWN_FACTOR_VARIABLE: &str = "GNOME_SHELL_SLOWDOWN_FACTOR";
+/// The state of enabled extensions in the info returned by `GetExtensionInfo`.
+const ENABLED_STATE: f64 = 1.0;
+
+/// Broadcasts the signal to the extension on the session bus.
+///
+/// If `GNOME_SHELL_SLOWDOWN_FACTOR` is not set, GNOME Shell is asked whether the extension is
+/// installed before the signal is sent.
+///
+/// # Arguments
+/// * `extension_uuid` - The UUID of the extension, e.g. `pomodoro@example.com`.
+/// * `signal_name` - The name of the signal, e.g. `PhaseEnded`.
+/// * `payload` - The string argument of the signal, or `None` to send the signal without
+///   arguments.
+///
+/// # Errors
+///
+/// Returns an error if there is no session bus, the extension is not installed or the signal
+/// cannot be sent, e.g. because its name is not a valid D-Bus member name.
+pub fn emit_signal(extension_uuid: &str, signal_name: &str, payload: Option<&str>) -> Result<()> {
+    let connection = Connection::session().context("Failed to connect to the session bus")?;
+    if std::env::var_os(SLOWDOWN_FACTOR_VARIABLE).is_none() {
+        check_extension(&connection, extension_uuid)?;
+    }
+    let name = dbus_name(extension_uuid);
+    let path = format!("/org/gnome/Shell/Extensions/{}", name);
+    let interface = format!("org.gnome.Shell.Extensions.{}", name);
+    let result = match payload {
+        Some(payload) => {
+            connection.emit_signal(None::<&str>, path, interface, signal_name, &(payload,))
+        }
+        None => connection.emit_signal(None::<&str>, path, interface, signal_name, &()),
+    };
+    result.with_context(|| {
+        format!(
+            "Failed to send the signal '{}' to the GNOME Shell extension '{}'",
+            signal_name, extension_uuid
+        )
+    })
+}
+
+/// Checks with `org.gnome.Shell.Extensions.GetExtensionInfo` that the extension is installed.
+/// Only prints a warning if it is disabled, as it may be enabled before the signal is received.
+fn check_extension(connection: &Connection, extension_uuid: &str) -> Result<()> {
+    let reply = connection
+        .call_method(
+            Some("org.gnome.Shell"),
+            "/org/gnome/Shell",
+            Some("org.gnome.Shell.Extensions"),
+            "GetExtensionInfo",
+            &(extension_uuid,),
+        )
+        .context("GNOME Shell is not running on the session bus")?;
+    let info: HashMap<String, OwnedValue> = reply.body().deserialize()?;
+    if info.is_empty() {
+        bail!(
+            "The GNOME Shell extension '{}' is not installed.",
+            extension_uuid
+        );
+    }
+    let state = info
+        .get("state")
+        .and_then(|state| state.downcast_ref::<f64>().ok());
+    if state != Some(ENABLED_STATE) {
+        log::warn!(
+            "The GNOME Shell extension '{}' is not enabled.",
+            extension_uuid
+        );
+    }
+    Ok(())
+}
+
+/// Converts the UUID of an extension into an element of D-Bus object paths and interface names,
+/// which only consist of ASCII letters, digits and `_` and do not start with a digit.
+fn dbus_name(extension_uuid: &str) -> String {
+    let name: String = extension_uuid
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+#[test]
+fn test_dbus_name() {
+    assert_eq!(dbus_name("pomodoro@example.com"), "pomodoro_example_com");
+    assert_eq!(
+        dbus_name("dash-to-dock@micxgx.gmail.com"),
+        "dash_to_dock_micxgx_gmail_com"
+    );
+    assert_eq!(dbus_name("42@example.com"), "_42_example_com");
+    assert_eq!(dbus_name("tööl"), "t__l");
+    assert_eq!(dbus_name(""), "_");
+}
@@ -0,0 +1,297 @@
+//! This module checks that the configured options and end events will work before a session is started.
+//!
+//! This module is only available with the `native` feature.
+use crate::end_events::{is_focus_mode_supported, EndEvent};
+use crate::mqtt::check_broker;
+use crate::ntfy;
+use crate::pomodoro_options::{parse_options_from_json, PomodoroOptions, PomodoroOptionsError};
+use crate::screen_lock::{lock_failure_warning, lock_screen_dry_run};
+use crate::sound::{check_sound_with_format, open_output_stream};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+use ureq::Agent;
+
+/// The time the servers of the end events have to respond to the health check.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The result of a single check of the health check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    /// The name of the check.
+    pub name: String,
+    /// Whether the check passed.
+    pub passed: bool,
+    /// A description of the result.
+    pub details: String,
+}
+
+impl HealthCheck {
+    /// Creates a passed check.
+    fn passed(name: impl Into<String>, details: impl Into<String>) -> Self {
+        HealthCheck {
+            name: name.into(),
+            passed: true,
+            details: details.into(),
+        }
+    }
+
+    /// Creates a failed check.
+    fn failed(name: impl Into<String>, details: impl Into<String>) -> Self {
+        HealthCheck {
+            name: name.into(),
+            passed: false,
+            details: details.into(),
+        }
+    }
+}
+
+/// Runs the health check of the options file.
+///
+/// Checks that the options file can be read and is valid, that the sounds of all `sound` events
+/// can be decoded, that the focus mode of `setFocusMode` events can be set on this platform, that
+/// the servers of `triggerHomeAssistant`, `ntfyPush` and `mqtt` events respond and that a lock
+/// command is available if any `lockScreen` event is configured, see `lock_screen_dry_run`.
+/// Nothing is played, sent or locked.
+///
+/// If `filepath_json` is `None`, the options file next to the executable is checked.
+/// If the options file doesn't exist, the default options are checked.
+#[must_use]
+pub fn run_health_check(filepath_json: Option<PathBuf>) -> Vec<HealthCheck> {
+    let mut checks = Vec::new();
+    let options = match parse_options_from_json(filepath_json) {
+        Ok(options) => {
+            match options.verify() {
+                Ok(()) => checks.push(HealthCheck::passed("Options file", "valid")),
+                Err(e) => checks.push(HealthCheck::failed("Options file", e.to_string())),
+            }
+            options
+        }
+        Err(e) => {
+            match e.downcast_ref::<PomodoroOptionsError>() {
+                Some(PomodoroOptionsError::OptionFileNotFound(path)) => {
+                    checks.push(HealthCheck::passed(
+                        "Options file",
+                        format!("{:?} not found, using default options", path),
+                    ))
+                }
+                None => checks.push(HealthCheck::failed("Options file", format!("{:#}", e))),
+            }
+            PomodoroOptions::default()
+        }
+    };
+    checks.extend(check_end_events(&options));
+    checks
+}
+
+/// Checks the end events of the options.
+fn check_end_events(options: &PomodoroOptions) -> Vec<HealthCheck> {
+    let end_events = options.end_events();
+    let mut checks = Vec::new();
+    for (name, end_event) in end_events
+        .iter()
+        .flat_map(|(name, end_event)| end_event.flatten().into_iter().map(move |e| (name, e)))
+    {
+        if matches!(end_event, EndEvent::SetFocusMode { .. }) {
+            let focus_name = format!("Focus mode of {}", name);
+            if is_focus_mode_supported() {
+                checks.push(HealthCheck::passed(focus_name, "supported"));
+            } else {
+                checks.push(HealthCheck::failed(
+                    focus_name,
+                    "the focus mode cannot be set on this platform",
+                ));
+            }
+        }
+        checks.extend(check_server(name, end_event));
+        if let EndEvent::Sound {
+            filepath_sound,
+            backend,
+            sound_format,
+            ..
+        } = end_event
+        {
+            let sound_name = format!("Sound of {}", name);
+            match check_sound_with_format(filepath_sound, *sound_format) {
+                Ok(()) => checks.push(HealthCheck::passed(sound_name, "decodable")),
+                Err(e) => checks.push(HealthCheck::failed(sound_name, format!("{:#}", e))),
+            }
+            if let Some(backend) = backend {
+                let backend_name = format!("Sound backend of {}", name);
+                match open_output_stream(*backend) {
+                    Ok(_) => checks.push(HealthCheck::passed(
+                        backend_name,
+                        format!("{:?} has an output device", backend),
+                    )),
+                    Err(e) => checks.push(HealthCheck::failed(backend_name, e.to_string())),
+                }
+            }
+        }
+    }
+    let uses_lock_screen = end_events.iter().any(|(_, end_event)| {
+        end_event
+            .flatten()
+            .into_iter()
+            .any(|end_event| matches!(end_event, EndEvent::LockScreen { .. }))
+    });
+    if uses_lock_screen {
+        match lock_screen_dry_run(&options.linux_lock_priority) {
+            Ok(command) => checks.push(HealthCheck::passed(
+                "Screen lock",
+                format!("`{}` is available", command),
+            )),
+            Err(e) => checks.push(HealthCheck::failed("Screen lock", lock_failure_warning(&e))),
+        }
+    }
+    checks
+}
+
+/// Checks that the server an end event sends its requests to responds, i.e. that Home Assistant
+/// and the ntfy server respond to a HEAD request and that a TCP connection to the MQTT broker
+/// can be opened.
+///
+/// Returns `None` for end events which don't send requests.
+fn check_server(name: &str, end_event: &EndEvent) -> Option<HealthCheck> {
+    let (check_name, result) = match end_event {
+        EndEvent::TriggerHomeAssistant { base_url, .. } => (
+            format!("Home Assistant of {}", name),
+            head_request(base_url),
+        ),
+        EndEvent::NtfyPush { server, .. } => (
+            format!("ntfy server of {}", name),
+            head_request(server.as_deref().unwrap_or(ntfy::DEFAULT_SERVER)),
+        ),
+        // The URL of the broker is not printed as it may contain the password
+        EndEvent::Mqtt { broker_url, .. } => (
+            format!("MQTT broker of {}", name),
+            check_broker(broker_url, RESPONSE_TIMEOUT).map(|()| "reachable".to_string()),
+        ),
+        _ => return None,
+    };
+    Some(match result {
+        Ok(details) => HealthCheck::passed(check_name, details),
+        Err(e) => HealthCheck::failed(check_name, format!("{:#}", e)),
+    })
+}
+
+/// Sends a HEAD request to the URL and describes the response.
+///
+/// Every HTTP response counts as success, e.g. `401 Unauthorized` of Home Assistant, as the
+/// request is sent without credentials.
+///
+/// # Errors
+///
+/// Returns an error if the URL is invalid or the server doesn't respond within 5 seconds.
+fn head_request(url: &str) -> Result<String> {
+    let agent: Agent = Agent::config_builder()
+        .timeout_global(Some(RESPONSE_TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = agent
+        .head(url)
+        .call()
+        .with_context(|| format!("{} does not respond", url))?;
+    Ok(format!(
+        "{} responds with HTTP {}",
+        url,
+        response.status().as_u16()
+    ))
+}
+
+#[test]
+fn test_check_end_events() {
+    let options = PomodoroOptions {
+        end_event_pomodoro: EndEvent::Sound {
+            filepath_sound: Some(PathBuf::from("locking-pomodoro-timer-missing.wav")),
+            backend: None,
+            sound_format: None,
+            completion_sound: None,
+            delay_before: None,
+        },
+        end_event_additional_pomodoro: EndEvent::DoNothing,
+        ..PomodoroOptions::default()
+    };
+    let checks = check_end_events(&options);
+    assert_eq!(checks.len(), 2);
+    assert!(!checks[0].passed);
+    assert_eq!(checks[0].name, "Sound of endEventPomodoro");
+    assert!(checks[1].passed);
+}
+
+#[test]
+fn test_check_focus_mode() {
+    let options = PomodoroOptions {
+        event_start_pomodoro: EndEvent::SetFocusMode {
+            enable: true,
+            profile: None,
+            delay_before: None,
+        },
+        end_event_pomodoro: EndEvent::DoNothing,
+        end_event_additional_pomodoro: EndEvent::DoNothing,
+        event_reminder_after_break: EndEvent::DoNothing,
+        ..PomodoroOptions::default()
+    };
+    let checks = check_end_events(&options);
+    assert_eq!(checks.len(), 1, "{:?}", checks);
+    assert_eq!(checks[0].name, "Focus mode of eventStartPomodoro");
+    assert_eq!(checks[0].passed, is_focus_mode_supported());
+}
+
+#[test]
+fn test_check_server() {
+    assert_eq!(check_server("endEventPomodoro", &EndEvent::DoNothing), None);
+
+    // A server which responds to one request
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        use std::io::{BufRead, BufReader, Write};
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request_line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut request_line)
+            .unwrap();
+        stream
+            .write_all(
+                b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+        request_line
+    });
+    let home_assistant = EndEvent::TriggerHomeAssistant {
+        base_url: base_url.clone(),
+        token: "abc".to_string(),
+        entity_id: "light.desk".to_string(),
+        service: "turn_off".to_string(),
+        delay_before: None,
+    };
+    let check = check_server("endEventPomodoro", &home_assistant).unwrap();
+    assert!(check.passed, "{:?}", check);
+    assert_eq!(check.name, "Home Assistant of endEventPomodoro");
+    assert!(
+        check.details.ends_with("responds with HTTP 401"),
+        "{}",
+        check.details
+    );
+    assert!(server.join().unwrap().starts_with("HEAD / "));
+
+    // Nothing listens on the port anymore, so the connection is refused
+    let ntfy = EndEvent::NtfyPush {
+        topic: "pomodoro".to_string(),
+        message: "Break!".to_string(),
+        server: Some(base_url),
+        delay_before: None,
+    };
+    let check = check_server("endEventPomodoro", &ntfy).unwrap();
+    assert!(!check.passed);
+    assert_eq!(check.name, "ntfy server of endEventPomodoro");
+    let mqtt = EndEvent::Mqtt {
+        broker_url: "https://broker.local".to_string(),
+        topic: "pomodoro/state".to_string(),
+        payload: String::new(),
+        qos: 0,
+        delay_before: None,
+    };
+    assert!(!check_server("endEventPomodoro", &mqtt).unwrap().passed);
+}
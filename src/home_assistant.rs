@@ -0,0 +1,152 @@
+//! This module calls services of Home Assistant via its REST API for the `TriggerHomeAssistant`
+//! end event, e.g. to turn off the lights during a break.
+//!
+//! Calling services requires the `native` feature.
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "native")]
+use std::time::Duration;
+#[cfg(feature = "native")]
+use ureq::Agent;
+
+/// The time to wait for the connection to Home Assistant.
+#[cfg(feature = "native")]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// The time to wait for the response of Home Assistant once the request was sent.
+#[cfg(feature = "native")]
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Calls the service of Home Assistant for the entity with `POST /api/services/<domain>/<service>`.
+///
+/// # Arguments
+/// * `base_url` - The URL of the Home Assistant instance, e.g. `http://homeassistant.local:8123`.
+/// * `token` - The long-lived access token, or the name of an environment variable containing
+///   it prefixed with `$`, e.g. `$HASS_TOKEN`.
+/// * `entity_id` - The entity the service is called for, e.g. `light.desk`.
+/// * `service` - The service, e.g. `light.turn_off`. Without a domain, the domain of the entity
+///   is used, e.g. `turn_off` for `light.desk` calls `light.turn_off`.
+///
+/// # Errors
+///
+/// Returns an error if the token or service is invalid or the request fails, e.g. because
+/// Home Assistant doesn't respond within 5 seconds for the connection and 10 seconds for the
+/// response.
+#[cfg(feature = "native")]
+pub fn call_service(base_url: &str, token: &str, entity_id: &str, service: &str) -> Result<()> {
+    let agent = agent(CONNECT_TIMEOUT, RESPONSE_TIMEOUT);
+    call_service_with_agent(&agent, base_url, token, entity_id, service)
+}
+
+/// Checks the service and the token like `call_service`, but fails to call the service since
+/// requests can only be sent with the `native` feature.
+///
+/// # Errors
+///
+/// Returns an error if the token or service is invalid, or else that the `native` feature is
+/// required.
+#[cfg(not(feature = "native"))]
+pub fn call_service(base_url: &str, token: &str, entity_id: &str, service: &str) -> Result<()> {
+    let url = service_url(base_url, entity_id, service)?;
+    resolve_token(token)?;
+    bail!(
+        "Calling the Home Assistant service {} requires the `native` feature.",
+        url
+    )
+}
+
+/// Calls the service like `call_service` with the agent.
+#[cfg(feature = "native")]
+fn call_service_with_agent(
+    agent: &Agent,
+    base_url: &str,
+    token: &str,
+    entity_id: &str,
+    service: &str,
+) -> Result<()> {
+    let url = service_url(base_url, entity_id, service)?;
+    let token = resolve_token(token)?;
+    let body = serde_json::json!({ "entity_id": entity_id }).to_string();
+    agent
+        .post(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .send(&body)
+        .with_context(|| format!("Failed to call the Home Assistant service {}", url))?;
+    Ok(())
+}
+
+/// Creates an agent whose requests fail if the connection or the response takes too long.
+#[cfg(feature = "native")]
+fn agent(connect_timeout: Duration, response_timeout: Duration) -> Agent {
+    Agent::config_builder()
+        .timeout_connect(Some(connect_timeout))
+        .timeout_recv_response(Some(response_timeout))
+        .build()
+        .new_agent()
+}
+
+/// Builds the URL of the service, see `call_service`.
+fn service_url(base_url: &str, entity_id: &str, service: &str) -> Result<String> {
+    let (domain, service) = match service.split_once('.') {
+        Some((domain, service)) => (domain, service),
+        None => match entity_id.split_once('.') {
+            Some((domain, _)) => (domain, service),
+            None => bail!(
+                "The service '{}' has no domain and the entity '{}' neither.",
+                service,
+                entity_id
+            ),
+        },
+    };
+    if domain.is_empty() || service.is_empty() {
+        bail!("Invalid Home Assistant service '{}.{}'.", domain, service);
+    }
+    Ok(format!(
+        "{}/api/services/{}/{}",
+        base_url.trim_end_matches('/'),
+        domain,
+        service
+    ))
+}
+
+/// Gets the token, which is read from the environment variable if it starts with `$`.
+fn resolve_token(token: &str) -> Result<String> {
+    match token.strip_prefix('$') {
+        Some(variable) => std::env::var(variable).with_context(|| {
+            format!(
+                "Failed to read the Home Assistant token from the environment variable {}",
+                variable
+            )
+        }),
+        None => Ok(token.to_string()),
+    }
+}
+
+#[test]
+fn test_service_url() {
+    assert_eq!(
+        service_url("http://homeassistant.local:8123/", "light.desk", "turn_off").unwrap(),
+        "http://homeassistant.local:8123/api/services/light/turn_off"
+    );
+    assert_eq!(
+        service_url("http://ha:8123", "light.desk", "scene.turn_on").unwrap(),
+        "http://ha:8123/api/services/scene/turn_on"
+    );
+    assert!(service_url("http://ha:8123", "desk", "turn_off").is_err());
+    assert_eq!(resolve_token("abc").unwrap(), "abc");
+    assert!(resolve_token("$LOCKING_POMODORO_TIMER_MISSING_TOKEN").is_err());
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_call_service_timeout() {
+    // A server which accepts the connection, but never responds
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || listener.accept().unwrap());
+
+    let agent = agent(Duration::from_millis(200), Duration::from_millis(200));
+    let start = std::time::Instant::now();
+    assert!(call_service_with_agent(&agent, &base_url, "abc", "light.desk", "turn_off").is_err());
+    assert!(start.elapsed() < RESPONSE_TIMEOUT);
+    drop(server.join().unwrap());
+}
@@ -5,24 +5,32 @@ use crossterm::terminal::disable_raw_mode;
 use crossterm::terminal::enable_raw_mode;
 use log::debug;
 use log::trace;
+use std::io::BufRead;
+use std::io::IsTerminal;
 
-/// Creates a channel to communicate key events from the terminal to the main thread.
+/// Sends key events from the terminal through the channel to the main thread.
 /// 
 /// Not all key events are sent through the channel. Only printable characters and the enter key.
 /// The ctrl+c key combination is used to exit the program and also sent through the channel.
 /// Only the key press events are sent through the channel. The key release events are ignored.
 ///
-/// # Returns
+/// The channel can be shared with other senders, e.g. the commands of a `RemoteControl`.
+///
+/// # Arguments
 ///
-/// A Receiver that can be used to receive messages sent through the channel.
+/// * `sender` - The sender of the channel the main thread receives the input from.
 ///
 /// # Panics
 ///
-/// This function will panic if the underlying operating system is unable to create the channel or
-/// if the input thread is unable to start or if the terminal is unable to enter raw mode.
+/// This function will panic if the input thread is unable to start or if the terminal is unable to enter raw mode.
 ///
-pub(crate) fn start_input_stream() -> std::sync::mpsc::Receiver<String> {
-    let (sender, receiver) = std::sync::mpsc::channel::<String>();
+/// If stdin is not a terminal, e.g. a pipe of a script, the input is read line by line
+/// with `start_stdin_controller` instead.
+pub(crate) fn start_input_stream(sender: std::sync::mpsc::Sender<String>) {
+    if !std::io::stdin().is_terminal() {
+        start_stdin_controller(sender);
+        return;
+    }
     std::thread::Builder::new()
         .name("input_stream".to_string())
         .spawn(move || {
@@ -36,7 +44,6 @@ pub(crate) fn start_input_stream() -> std::sync::mpsc::Receiver<String> {
             }
         })
         .expect("Failed to spawn input thread.");
-    receiver
 }
 
 /// Processes the key events received from the terminal.
@@ -81,3 +88,98 @@ fn process_key_event(sender: &std::sync::mpsc::Sender<String>) -> bool {
     }
     exit
 }
+
+/// A command read from stdin by the stdin controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StdinCommand {
+    Pause,
+    Resume,
+    Skip,
+    SkipToEnd,
+    Status,
+    Quit,
+    /// An empty line, which confirms prompts like the enter key.
+    Continue,
+}
+
+impl StdinCommand {
+    /// Parses a line of stdin. Leading and trailing whitespace and the case are ignored.
+    fn parse(line: &str) -> Option<Self> {
+        match line.trim().to_ascii_lowercase().as_str() {
+            "pause" => Some(StdinCommand::Pause),
+            "resume" => Some(StdinCommand::Resume),
+            "skip" => Some(StdinCommand::Skip),
+            "end" => Some(StdinCommand::SkipToEnd),
+            "status" => Some(StdinCommand::Status),
+            "quit" => Some(StdinCommand::Quit),
+            "" => Some(StdinCommand::Continue),
+            _ => None,
+        }
+    }
+
+    /// Gets the input of the key event with the same effect.
+    fn as_input(self) -> &'static str {
+        match self {
+            StdinCommand::Pause => "p",
+            StdinCommand::Resume => "r",
+            StdinCommand::Skip => "s",
+            StdinCommand::SkipToEnd => "e",
+            StdinCommand::Status => "status",
+            // Quits the program like ctrl+c, not only the current timer like 'q'
+            StdinCommand::Quit => "ctrl+c",
+            StdinCommand::Continue => "\n",
+        }
+    }
+}
+
+/// Reads the commands `pause`, `resume`, `skip`, `end`, `status` and `quit` line by line from stdin
+/// and sends them through the channel like the corresponding key events, `quit` like ctrl+c.
+///
+/// This allows controlling the timer through a pipe, e.g. from a script. An empty line
+/// confirms prompts like the enter key. Unknown commands are ignored with a warning.
+///
+/// # Panics
+///
+/// This function will panic if the controller thread is unable to start.
+fn start_stdin_controller(sender: std::sync::mpsc::Sender<String>) {
+    std::thread::Builder::new()
+        .name("stdin_controller".to_string())
+        .spawn(move || {
+            trace!("Spawning stdin controller thread.");
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match StdinCommand::parse(&line) {
+                    Some(command) => {
+                        debug!("Received stdin command: {:?}", command);
+                        if sender.send(command.as_input().to_string()).is_err() {
+                            return;
+                        }
+                    }
+                    None => log::warn!(
+                        "Unknown command '{}'. Expected one of: pause, resume, skip, end, status, quit.",
+                        line.trim()
+                    ),
+                }
+            }
+            // The timer keeps running without further input, as the remote control holds
+            // another sender of the channel until the timer finished
+            debug!("Stdin closed.");
+        })
+        .expect("Failed to spawn stdin controller thread.");
+}
+
+#[test]
+fn test_parse_stdin_command() {
+    assert_eq!(StdinCommand::parse("pause"), Some(StdinCommand::Pause));
+    assert_eq!(
+        StdinCommand::parse("  Resume\r"),
+        Some(StdinCommand::Resume)
+    );
+    assert_eq!(StdinCommand::parse(""), Some(StdinCommand::Continue));
+    assert_eq!(StdinCommand::parse("end"), Some(StdinCommand::SkipToEnd));
+    assert_eq!(StdinCommand::parse("stop"), None);
+    assert_eq!(StdinCommand::parse("quit"), Some(StdinCommand::Quit));
+    assert_eq!(StdinCommand::Quit.as_input(), "ctrl+c");
+}
@@ -0,0 +1,46 @@
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+#[cfg(feature = "aws")]
+pub mod aws_lambda;
+pub mod brightness;
+pub mod calendar;
+pub mod cli_utilities;
+pub mod clock;
+pub mod countdown;
+pub mod daemon;
+pub mod email;
+pub mod end_events;
+pub mod event_log;
+#[cfg(all(feature = "gnome", target_os = "linux"))]
+pub mod gnome_extension;
+#[cfg(feature = "native")]
+pub mod health_check;
+pub mod home_assistant;
+#[cfg(feature = "native")]
+mod input_handler;
+mod message_creator;
+#[cfg(all(feature = "mpris", target_os = "linux"))]
+pub mod mpris;
+pub mod mqtt;
+pub mod ntfy;
+#[cfg(all(feature = "pw-cat", target_os = "linux"))]
+mod pipewire;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+mod pomo_info;
+pub mod pomodoro_options;
+pub mod pomodoro_phase;
+pub mod popup;
+pub mod remote;
+pub mod screen_lock;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
+pub mod sound;
+pub mod statistics;
+pub mod theme;
+pub mod timer;
+#[cfg(all(feature = "tray", target_os = "linux"))]
+pub mod tray;
@@ -1,32 +1,109 @@
-#![warn(missing_docs)]
-#![doc = include_str!("../README.md")]
-use std::path::PathBuf;
+//! The executable of the Locking Pomodoro Timer.
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-use crate::pomodoro_options::{
-    read_options_from_json, write_default_options_to_json_next_to_executable,
+use anyhow::{bail, Context, Result};
+use chrono::{Days, Local, NaiveDate};
+use clap::{CommandFactory, Parser};
+use locking_pomodoro_timer::cli_utilities::{
+    start_pomodoro_with_callbacks, AbortHandle, SessionLimitReached,
 };
-use crate::pomodoro_options::{PomodoroOptions, PomodoroOptionsError};
-use crate::cli_utilities::start_pomodoro;
-mod input_handler;
-mod message_creator;
-mod pomo_info;
-mod pomodoro_options;
-mod timer;
-mod cli_utilities;
-mod end_events;
+use locking_pomodoro_timer::clock::simulate;
+use locking_pomodoro_timer::daemon::{pid_file_path, read_running_pid, remove_pid_file};
+use locking_pomodoro_timer::end_events::EndEvent;
+use locking_pomodoro_timer::health_check::run_health_check;
+use locking_pomodoro_timer::ntfy::push;
+use locking_pomodoro_timer::pomodoro_options::{
+    options_from_env, options_json_schema, read_options_from_json, watch_options,
+    write_default_options_to_json_next_to_executable,
+};
+use locking_pomodoro_timer::pomodoro_options::{
+    PartialPomodoroOptions, PomodoroOptions, PomodoroOptionsError,
+};
+use locking_pomodoro_timer::pomodoro_phase::plan_phases;
+use locking_pomodoro_timer::remote::{RemoteControl, TimerStatus};
+use locking_pomodoro_timer::session::SessionCallbacks;
+use locking_pomodoro_timer::statistics::{
+    history_file_path, history_to_csv, reset_history, Statistics,
+};
+use locking_pomodoro_timer::theme::{colorize, set_colorize};
+use log::LevelFilter;
+use log4rs::append::console::{ConsoleAppender, Target};
+use log4rs::append::file::FileAppender;
+use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
+use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
+use log4rs::append::rolling_file::RollingFileAppender;
+use log4rs::append::Append;
+use log4rs::config::{Appender, Config, Logger, Root};
+use log4rs::encode::pattern::PatternEncoder;
+
+use crate::cli::{Cli, ColorChoice, ConfigFormat, HistoryFormat, OutputFormat};
+mod cli;
+
 /// The main entry point of the program.
 ///
-/// This function initializes the logger, reads the Pomodoro options from a JSON file,
-/// and starts the Pomodoro timer. If the options file is not found, it writes default
-/// options to a new JSON file and informs the user.
-///
-/// # Panics
-/// This function will panic if it fails to write default options to the JSON file.
+/// Parses the command line arguments and runs the program with them.
+/// If an error occurs, it is printed and the program exits with code 1.
 fn main() {
-    // Initialize the logger
-    let logging_config_file = PathBuf::from("pomodoro_logging.yaml");
-    if logging_config_file.is_file() {
-        log4rs::init_file(logging_config_file, Default::default()).unwrap();
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        if e.downcast_ref::<SessionLimitReached>().is_some() {
+            println!("{} Use --force to start another session anyway.", e);
+            std::process::exit(2);
+        }
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Runs the program with the parsed command line arguments.
+///
+/// This function handles the flags which print something and exit, e.g. `--completions` or
+/// `--summary`, and otherwise initializes the logger, reads the Pomodoro options from a JSON file
+/// and starts the Pomodoro timer. If the options file is not found, it writes default options to
+/// a new JSON file and informs the user. The options are overridden by environment variables,
+/// which are in turn overridden by the command line arguments, see `override_options`.
+///
+/// # Errors
+///
+/// Returns an error if the logger cannot be initialized, the default options cannot be written,
+/// one of the flags which exit fails, a check of the health check fails, an environment
+/// variable is invalid, or the server or the daemon cannot be started.
+fn run(cli: Cli) -> Result<()> {
+    set_colorize(match cli.color {
+        ColorChoice::Always => true,
+        ColorChoice::Auto => std::io::stderr().is_terminal(),
+        ColorChoice::Never => false,
+    });
+    if let Some(shell) = cli.completions {
+        let mut command = Cli::command();
+        let bin_name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, bin_name, &mut std::io::stdout());
+        return Ok(());
+    }
+    if cli.print_json_schema {
+        println!("{}", serde_json::to_string_pretty(&options_json_schema())?);
+        return Ok(());
+    }
+    // The daemon initializes the logger after it is detached, so that it writes to the log file
+    if !cli.daemon {
+        init_logging(&cli)?;
+    }
+    if cli.health_check {
+        return health_check();
+    }
+    if cli.summary {
+        return print_summary(cli.week);
+    }
+    if cli.status {
+        return print_daemon_status(cli.server);
+    }
+    if cli.reset_stats {
+        return reset_stats(cli.before.as_deref(), cli.yes);
+    }
+    if let Some(format) = cli.export_history {
+        return export_history(format, cli.since.as_deref(), cli.until.as_deref());
     }
     // Read the JSON file
     let data = read_options_from_json(None);
@@ -38,7 +115,7 @@ fn main() {
                 Some(PomodoroOptionsError::OptionFileNotFound(_)) => {
                     // Write default options to JSON file
                     write_default_options_to_json_next_to_executable()
-                        .expect("Failed to write default options to JSON file.");
+                        .context("Failed to write default options to JSON file.")?;
 
                     // Print a message for first-time users
                     println!(
@@ -61,9 +138,661 @@ fn main() {
         }
     };
 
+    let cli_options = cli_options(&cli);
+    let env_options = options_from_env()?;
+    let force = cli.force;
+    // The options are overridden at the start and whenever the file is reloaded
+    let layer_options = move |options| override_options(options, &env_options, &cli_options, force);
+    let json_data = layer_options(json_data);
+
+    if let Some(format) = cli.export_config {
+        println!("{}", export_config(&json_data, format)?);
+        return Ok(());
+    }
+    if let Some(cycles) = cli.list_phases {
+        let cycles = cycles.unwrap_or(json_data.interval_long_break.max(1) as u32);
+        println!("{}", list_phases(&json_data, cycles, cli.format)?);
+        return Ok(());
+    }
+    if cli.test_push {
+        return test_push(&json_data);
+    }
+
+    // Fork before any thread is started, e.g. by the watcher or the server
+    if cli.daemon {
+        start_daemon()?;
+        init_logging(&cli)?;
+    }
+    let watcher = if cli.watch_config {
+        match watch_options(None) {
+            Ok(watcher) => Some(watcher.with_overrides(layer_options)),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                eprintln!("Options will not be reloaded.");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    load_plugins(&json_data);
+    if let Some(speed) = cli.simulate {
+        simulate(speed);
+    }
+    let remote = RemoteControl::new();
+    start_server(cli.server, &remote)?;
+
     // Start the Pomodoro timer
-    start_pomodoro(&json_data)
-    // if let Err(e) = std::panic::catch_unwind(|| start_pomodoro(&json_data)) {
-        // log::error!("An error occurred: {:#?}", e);
-    // }
+    let result = start_pomodoro_with_callbacks(
+        &json_data,
+        watcher.as_ref(),
+        &AbortHandle::new(),
+        &remote,
+        &tray_icon_callbacks(),
+    );
+    if cli.daemon {
+        remove_pid_file(&pid_file_path());
+    }
+    result?;
+    Ok(())
+}
+
+/// Creates the options overridden by the command line arguments.
+fn cli_options(cli: &Cli) -> PartialPomodoroOptions {
+    PartialPomodoroOptions {
+        work_label: cli.work_label.clone(),
+        break_label: cli.break_label.clone(),
+        max_sessions_per_day: cli.max_work_sessions_per_day,
+        cycles: cli.once.then_some(1),
+        ..Default::default()
+    }
+}
+
+/// Overrides the options of the file by the environment variables and those by the command line
+/// arguments. If `force` is set, the maximum number of sessions per day is removed.
+fn override_options(
+    options: PomodoroOptions,
+    env_options: &PartialPomodoroOptions,
+    cli_options: &PartialPomodoroOptions,
+    force: bool,
+) -> PomodoroOptions {
+    let options = PomodoroOptions::merge(options, env_options.clone());
+    let mut options = PomodoroOptions::merge(options, cli_options.clone());
+    if force {
+        options.max_sessions_per_day = None;
+    }
+    options
+}
+
+/// Detaches the process from the terminal with `daemonize`.
+///
+/// # Errors
+///
+/// Returns an error if the daemon is already running or cannot be started.
+#[cfg(unix)]
+fn start_daemon() -> Result<()> {
+    let log_file = locking_pomodoro_timer::daemon::log_file_path()?;
+    println!(
+        "Starting the timer in the background. The output is written to {:?}.",
+        log_file
+    );
+    locking_pomodoro_timer::daemon::daemonize(&log_file, &pid_file_path())
+}
+
+/// Returns an error, as the timer can only run in the background on POSIX platforms.
+#[cfg(not(unix))]
+fn start_daemon() -> Result<()> {
+    bail!("--daemon is only supported on POSIX platforms.");
+}
+
+/// Initializes the logger with `--log-file`, `pomodoro_logging.yaml` in the working directory or
+/// else for the console.
+///
+/// # Errors
+///
+/// Returns an error if the logger cannot be initialized.
+fn init_logging(cli: &Cli) -> Result<()> {
+    let logging_config_file = PathBuf::from("pomodoro_logging.yaml");
+    if let Some(log_file) = &cli.log_file {
+        init_file_logger(log_level(cli), log_file, cli.max_log_size)
+            .with_context(|| format!("Failed to initialize the log file {:?}.", log_file))?;
+    } else if logging_config_file.is_file() {
+        log4rs::init_file(logging_config_file, Default::default())?;
+    } else {
+        init_console_logger(log_level(cli)).context("Failed to initialize the logger.")?;
+    }
+    Ok(())
+}
+
+/// Prints the status of the timer from its JSON-RPC server on the port of `--server`, or else
+/// whether the daemon is running and its PID.
+///
+/// # Errors
+///
+/// Returns an error if the server doesn't respond and the PID file cannot be read.
+fn print_daemon_status(port: Option<u16>) -> Result<()> {
+    match query_server_status(port) {
+        Ok(status) => {
+            println!("{}", describe_status(&status));
+            return Ok(());
+        }
+        Err(e) => log::debug!("{:#}. Checking the PID file instead.", e),
+    }
+    let pid_file = pid_file_path();
+    match read_running_pid(&pid_file)? {
+        Some(pid) => println!("The daemon is running with PID {}.", pid),
+        None => println!("The daemon is not running."),
+    }
+    Ok(())
+}
+
+/// Starts the JSON-RPC server controlling the timer with the remote control if a port is given.
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be started.
+#[cfg(feature = "server")]
+fn start_server(port: Option<u16>, remote: &RemoteControl) -> Result<()> {
+    let Some(port) = port else {
+        return Ok(());
+    };
+    let token = locking_pomodoro_timer::server::load_or_create_token()?;
+    let address = locking_pomodoro_timer::server::start_server(port, remote.clone(), &token)?;
+    println!("The JSON-RPC server listens on {}.", address);
+    if std::env::var_os(locking_pomodoro_timer::server::TOKEN_ENV).is_none() {
+        println!(
+            "Authorize the requests with the token in {:?}.",
+            locking_pomodoro_timer::server::token_file_path()
+        );
+    }
+    Ok(())
+}
+
+/// Gets the status of the timer from its JSON-RPC server on the port, or the default port.
+///
+/// # Errors
+///
+/// Returns an error if the token cannot be read or the server doesn't respond.
+#[cfg(feature = "server")]
+fn query_server_status(port: Option<u16>) -> Result<TimerStatus> {
+    use locking_pomodoro_timer::server::{query_status, read_token, DEFAULT_PORT};
+
+    query_status(port.unwrap_or(DEFAULT_PORT), &read_token()?)
+}
+
+/// Returns an error, as the server cannot be queried without the `server` feature.
+#[cfg(not(feature = "server"))]
+fn query_server_status(_port: Option<u16>) -> Result<TimerStatus> {
+    bail!("The JSON-RPC server is not supported by this build.");
+}
+
+/// Describes the status of the timer in plain words, e.g.
+/// `short break, 3:20 of 5:00 remaining. 2 cycles completed.`
+fn describe_status(status: &TimerStatus) -> String {
+    let minutes = |secs: u64| format!("{}:{:02}", secs / 60, secs % 60);
+    let phase = match status.phase {
+        Some(phase) => format!(
+            "{}, {} of {} remaining{}",
+            phase.description(),
+            minutes(status.remaining_secs),
+            minutes(status.duration_secs),
+            if status.paused { " (paused)" } else { "" }
+        ),
+        None => "waiting for the next phase".to_string(),
+    };
+    format!(
+        "The timer is running: {}. {} cycles completed.",
+        phase, status.completed_cycles
+    )
+}
+
+/// Returns an error if a port is given, as the server is not supported without the `server` feature.
+#[cfg(not(feature = "server"))]
+fn start_server(port: Option<u16>, _remote: &RemoteControl) -> Result<()> {
+    if port.is_some() {
+        bail!("--server is not supported by this build, it requires the `server` feature.");
+    }
+    Ok(())
+}
+
+/// Loads the end event plugins of `pluginDir` and prints an error if the directory cannot be read.
+#[cfg(feature = "plugins")]
+fn load_plugins(options: &PomodoroOptions) {
+    let Some(plugin_dir) = &options.plugin_dir else {
+        return;
+    };
+    match locking_pomodoro_timer::plugins::load_plugins(plugin_dir) {
+        Ok(names) => log::info!("Loaded {} end events from plugins.", names.len()),
+        Err(e) => eprintln!("Error: {:#}", e),
+    }
+}
+
+/// Prints a warning if `pluginDir` is set, as plugins are not supported without the `plugins` feature.
+#[cfg(not(feature = "plugins"))]
+fn load_plugins(options: &PomodoroOptions) {
+    if options.plugin_dir.is_some() {
+        log::warn!("pluginDir is ignored because plugins are not supported by this build.");
+    }
+}
+
+/// Shows the tray icon with the number of Pomodoro sessions completed today and creates the
+/// callbacks which update it whenever a Pomodoro session is recorded, see `tray::TrayIcon`.
+///
+/// Prints a warning and returns no callbacks if the tray icon cannot be shown.
+#[cfg(all(feature = "tray", target_os = "linux"))]
+fn tray_icon_callbacks() -> SessionCallbacks {
+    use locking_pomodoro_timer::pomodoro_phase::PomodoroPhase;
+    use locking_pomodoro_timer::tray::TrayIcon;
+
+    let tray = match TrayIcon::new(today_count()) {
+        Ok(tray) => tray,
+        Err(e) => {
+            log::warn!("Failed to show the tray icon: {}", e);
+            return SessionCallbacks::default();
+        }
+    };
+    SessionCallbacks {
+        on_phase_recorded: Some(Box::new(move |record| {
+            if record.phase == PomodoroPhase::Work && record.completed {
+                if let Err(e) = tray.set_badge(today_count()) {
+                    log::warn!("Failed to update the badge of the tray icon: {}", e);
+                }
+            }
+        })),
+        ..SessionCallbacks::default()
+    }
+}
+
+/// Creates no callbacks, the tray icon is only supported with the `tray` feature on Linux.
+#[cfg(not(all(feature = "tray", target_os = "linux")))]
+fn tray_icon_callbacks() -> SessionCallbacks {
+    SessionCallbacks::default()
+}
+
+/// Counts the Pomodoro sessions completed today in the history file.
+///
+/// Prints a warning and returns 0 if the history file cannot be read.
+#[cfg(all(feature = "tray", target_os = "linux"))]
+fn today_count() -> u32 {
+    match history_file_path().and_then(|path| Statistics::from_history_file(&path)) {
+        Ok(statistics) => statistics.today_count(),
+        Err(e) => {
+            log::warn!("Failed to count the Pomodoro sessions of today: {:#}", e);
+            0
+        }
+    }
+}
+
+/// Runs the health check and prints the result of each check.
+///
+/// # Errors
+///
+/// Returns an error if any check fails.
+fn health_check() -> Result<()> {
+    let checks = run_health_check(None);
+    for check in &checks {
+        let status = if check.passed { " OK " } else { "FAIL" };
+        println!("[{}] {}: {}", status, check.name, check.details);
+    }
+    let failed = checks.iter().filter(|check| !check.passed).count();
+    if failed > 0 {
+        bail!("{} of {} checks failed.", failed, checks.len());
+    }
+    Ok(())
+}
+
+/// Sends a test notification to the topic of each `ntfyPush` end event of the options and prints
+/// the result of each notification.
+///
+/// # Errors
+///
+/// Returns an error if no `ntfyPush` end event is configured or a notification cannot be sent.
+fn test_push(options: &PomodoroOptions) -> Result<()> {
+    let end_events = [
+        &options.event_start_pomodoro,
+        &options.end_event_pomodoro,
+        &options.end_event_additional_pomodoro,
+        &options.event_reminder_after_break,
+    ];
+    let mut topics = Vec::new();
+    for end_event in end_events.into_iter().flat_map(EndEvent::flatten) {
+        if let EndEvent::NtfyPush { topic, server, .. } = end_event {
+            if !topics.contains(&(server, topic)) {
+                topics.push((server, topic));
+            }
+        }
+    }
+    if topics.is_empty() {
+        bail!("No ntfyPush end event is configured in the options file.");
+    }
+    let mut failed = 0;
+    for (server, topic) in &topics {
+        match push(server.as_deref(), topic, "This is a test notification.") {
+            Ok(()) => println!("[ OK ] Sent a test notification to '{}'.", topic),
+            Err(e) => {
+                println!("[FAIL] {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} notifications failed.", failed, topics.len());
+    }
+    Ok(())
+}
+
+/// Serializes the effective options to the given format.
+///
+/// # Errors
+///
+/// Returns an error if the options cannot be serialized to the format.
+fn export_config(options: &PomodoroOptions, format: ConfigFormat) -> Result<String> {
+    let exported = match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(options)?,
+        ConfigFormat::Toml => toml::to_string_pretty(options)?,
+    };
+    Ok(exported)
+}
+
+/// Lists the planned phases of the given number of cycles starting now in the given format.
+///
+/// # Errors
+///
+/// Returns an error if the phases cannot be serialized.
+fn list_phases(options: &PomodoroOptions, cycles: u32, format: OutputFormat) -> Result<String> {
+    let phases = plan_phases(options, cycles, Local::now());
+    if format == OutputFormat::Json {
+        return Ok(serde_json::to_string_pretty(&phases)?);
+    }
+    let mut table = format!("{:<16}{:<8}{}", "Phase", "Start", "Duration");
+    for planned in &phases {
+        let mut name = planned.phase.description().to_string();
+        name[..1].make_ascii_uppercase();
+        table.push_str(&format!(
+            "\n{:<16}{:<8}{}",
+            format!("{} {}", name, planned.number),
+            planned.start.format("%H:%M").to_string(),
+            format_phase_duration(planned.duration_secs)
+        ));
+    }
+    Ok(table)
+}
+
+/// Formats the duration of a planned phase as minutes and seconds, e.g. `25:00` or `0:30`, so
+/// that phases shorter than a minute aren't shown as 0 minutes.
+fn format_phase_duration(duration_secs: u64) -> String {
+    format!("{}:{:02}", duration_secs / 60, duration_secs % 60)
+}
+
+/// Prints the summary of the Pomodoro sessions of today or of the last 7 days including today.
+///
+/// # Errors
+///
+/// Returns an error if the history file cannot be read.
+fn print_summary(week: bool) -> Result<()> {
+    let statistics = Statistics::from_history_file(&history_file_path()?)?;
+    let today = Local::now().date_naive();
+    let from = if week { today - Days::new(6) } else { today };
+    println!("{}", statistics.summary(from, today));
+    Ok(())
+}
+
+/// Resets the history file after asking the user for confirmation.
+///
+/// # Arguments
+/// * `before` - If given, only the records which started before this date are deleted.
+/// * `yes` - If `true`, the user is not asked for confirmation.
+///
+/// # Errors
+///
+/// Returns an error if the date is invalid or the history file cannot be reset.
+fn reset_stats(before: Option<&str>, yes: bool) -> Result<()> {
+    let before = before.map(parse_date).transpose()?;
+    let path = history_file_path()?;
+    if !yes {
+        match before {
+            Some(before) => print!(
+                "Delete all records before {} from {:?}? [y/N] ",
+                before, path
+            ),
+            None => print!("Delete the history file {:?}? [y/N] ", path),
+        }
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("The history was not reset.");
+            return Ok(());
+        }
+    }
+    let removed = reset_history(&path, before)?;
+    println!("Deleted {} records from the history.", removed);
+    Ok(())
+}
+
+/// Prints the records of the history file in the format.
+///
+/// # Arguments
+/// * `format` - The format of the records.
+/// * `since` - If given, only the records which started on or after this date are printed.
+/// * `until` - If given, only the records which started on or before this date are printed.
+///
+/// # Errors
+///
+/// Returns an error if a date is invalid or the history file cannot be read.
+fn export_history(format: HistoryFormat, since: Option<&str>, until: Option<&str>) -> Result<()> {
+    let since = since.map(parse_date).transpose()?;
+    let until = until.map(parse_date).transpose()?;
+    let statistics = Statistics::from_history_file(&history_file_path()?)?;
+    let records = statistics.records_between(since, until);
+    match format {
+        HistoryFormat::Csv => print!("{}", history_to_csv(&records)),
+        HistoryFormat::Json => println!("{}", serde_json::to_string_pretty(&records)?),
+    }
+    Ok(())
+}
+
+/// Parses a date of a command line argument in the format YYYY-MM-DD.
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+}
+
+/// Gets the log level of this crate from the verbosity flags.
+fn log_level(cli: &Cli) -> LevelFilter {
+    if cli.quiet {
+        return LevelFilter::Error;
+    }
+    match cli.verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Initializes a logger writing the messages of this crate up to the given level to stderr.
+/// The level of the messages is highlighted unless the output is not colored.
+///
+/// Messages of dependencies are only written if they are warnings or errors.
+///
+/// # Errors
+///
+/// Returns an error if the logger cannot be initialized.
+fn init_console_logger(level: LevelFilter) -> Result<()> {
+    let stderr = ConsoleAppender::builder()
+        .target(Target::Stderr)
+        .encoder(Box::new(PatternEncoder::new(if colorize() {
+            "{h({l})}: {m}{n}"
+        } else {
+            "{l}: {m}{n}"
+        })))
+        .build();
+    init_logger(level, Box::new(stderr))
+}
+
+/// Initializes a logger appending the messages like `init_console_logger` to the file, with the
+/// time of each message.
+///
+/// # Arguments
+/// * `level` - The level of the messages of this crate.
+/// * `log_file` - The file the messages are appended to. It is created if it doesn't exist.
+/// * `max_size` - The size in bytes after which the file is renamed to `<log_file>.1` and a
+///   new file is started, or `None` to never rotate it.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or the logger cannot be initialized.
+fn init_file_logger(level: LevelFilter, log_file: &Path, max_size: Option<u64>) -> Result<()> {
+    init_logger(level, file_appender(log_file, max_size)?)
+}
+
+/// Creates the appender of `init_file_logger`, which rotates the file if `max_size` is given.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened.
+fn file_appender(log_file: &Path, max_size: Option<u64>) -> Result<Box<dyn Append>> {
+    let encoder = Box::new(PatternEncoder::new("{d(%Y-%m-%d %H:%M:%S)} {l}: {m}{n}"));
+    let appender: Box<dyn Append> = match max_size {
+        Some(max_size) => {
+            let mut rotated_file = log_file.as_os_str().to_owned();
+            rotated_file.push(".{}");
+            let roller = FixedWindowRoller::builder()
+                .base(1)
+                .build(&rotated_file.to_string_lossy(), 1)?;
+            let policy =
+                CompoundPolicy::new(Box::new(SizeTrigger::new(max_size)), Box::new(roller));
+            Box::new(
+                RollingFileAppender::builder()
+                    .append(true)
+                    .encoder(encoder)
+                    .build(log_file, Box::new(policy))?,
+            )
+        }
+        None => Box::new(
+            FileAppender::builder()
+                .append(true)
+                .encoder(encoder)
+                .build(log_file)?,
+        ),
+    };
+    Ok(appender)
+}
+
+/// Initializes a logger writing the messages of this crate up to the given level to the
+/// appender, and only warnings and errors of dependencies.
+///
+/// # Errors
+///
+/// Returns an error if the logger cannot be initialized.
+fn init_logger(level: LevelFilter, appender: Box<dyn Append>) -> Result<()> {
+    let config = Config::builder()
+        .appender(Appender::builder().build("output", appender))
+        .logger(Logger::builder().build("locking_pomodoro_timer", level))
+        .build(
+            Root::builder()
+                .appender("output")
+                .build(LevelFilter::Warn.min(level)),
+        )?;
+    log4rs::init_config(config)?;
+    Ok(())
+}
+
+#[test]
+fn test_log_level() {
+    let level = |args: &[&str]| {
+        let cli = Cli::try_parse_from(
+            std::iter::once("locking-pomodoro-timer").chain(args.iter().copied()),
+        )
+        .unwrap();
+        log_level(&cli)
+    };
+    assert_eq!(level(&[]), LevelFilter::Warn);
+    assert_eq!(level(&["-v"]), LevelFilter::Info);
+    assert_eq!(level(&["-vv"]), LevelFilter::Debug);
+    assert_eq!(level(&["--verbose", "-vv"]), LevelFilter::Trace);
+    assert_eq!(level(&["-q"]), LevelFilter::Error);
+}
+
+#[test]
+fn test_file_appender() {
+    let directory = std::env::temp_dir().join(format!(
+        "locking-pomodoro-timer-test-log-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let log_file = directory.join("pomodoro.log");
+    let rotated_file = directory.join("pomodoro.log.1");
+    let log = |appender: &dyn Append, message: &str| {
+        appender
+            .append(
+                &log::Record::builder()
+                    .args(format_args!("{}", message))
+                    .level(log::Level::Warn)
+                    .build(),
+            )
+            .unwrap();
+        appender.flush();
+    };
+
+    // Without a maximum size, the messages are appended to the file
+    let appender = file_appender(&log_file, None).unwrap();
+    log(appender.as_ref(), "first");
+    log(appender.as_ref(), "second");
+    let content = std::fs::read_to_string(&log_file).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", content);
+    assert!(lines[0].ends_with(" WARN: first") && lines[1].ends_with(" WARN: second"));
+
+    // The file is rotated once it exceeds the maximum size
+    let appender = file_appender(&log_file, Some(10)).unwrap();
+    log(appender.as_ref(), "third");
+    assert!(std::fs::read_to_string(&rotated_file)
+        .unwrap()
+        .ends_with("WARN: third\n"));
+    assert!(!rotated_file.with_extension("2").exists());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_format_phase_duration() {
+    assert_eq!(format_phase_duration(25 * 60), "25:00");
+    assert_eq!(format_phase_duration(30), "0:30");
+    assert_eq!(format_phase_duration(90), "1:30");
+    assert_eq!(format_phase_duration(0), "0:00");
+}
+
+#[test]
+fn test_override_options() {
+    let options = |args: &[&str]| {
+        let cli = Cli::try_parse_from(
+            std::iter::once("locking-pomodoro-timer").chain(args.iter().copied()),
+        )
+        .unwrap();
+        let file_options = PomodoroOptions {
+            max_sessions_per_day: Some(4),
+            ..Default::default()
+        };
+        let env_options = PartialPomodoroOptions {
+            max_sessions_per_day: Some(5),
+            ..Default::default()
+        };
+        override_options(file_options, &env_options, &cli_options(&cli), cli.force)
+    };
+    assert_eq!(options(&[]).max_sessions_per_day, Some(5));
+    assert_eq!(
+        options(&["--max-work-sessions-per-day", "6"]).max_sessions_per_day,
+        Some(6)
+    );
+    assert_eq!(
+        options(&["--max-work-sessions-per-day", "6", "--force"]).max_sessions_per_day,
+        None
+    );
+    assert_eq!(options(&["--force"]).max_sessions_per_day, None);
+    assert_eq!(options(&[]).cycles, None);
+    assert_eq!(options(&["--once"]).cycles, Some(1));
+    assert_eq!(options(&["-1"]).cycles, Some(1));
 }
@@ -1,4 +1,4 @@
-/// This module contains functions and structs related to creating terminal print messages for the Pomodoro app.
+//! This module contains functions and structs related to creating terminal print messages for the Pomodoro app.
 use crate::pomodoro_options::PomodoroOptions;
 
 use crate::pomo_info::PomoInfo;
@@ -80,8 +80,7 @@ pub(crate) fn generate_print_message_before_pomodoro(
         pomodoros_till_long_break: pomo_info.pomodoros_till_long_break,
         minutes_till_long_break,
     };
-    let print_message = message_data.generate_print_message();
-    print_message
+    message_data.generate_print_message()
 }
 
 /// Generates a print message to be displayed before starting an additional break.
@@ -128,8 +127,7 @@ pub(crate) fn generate_print_message_before_additional_break(
         pomodoros_till_long_break: pomo_info.pomodoros_till_long_break,
         minutes_till_long_break,
     };
-    let print_message = message_data.generate_print_message();
-    print_message
+    message_data.generate_print_message()
 }
 
 /// Generates a print message to be displayed before starting a break.
@@ -169,6 +167,5 @@ pub(crate) fn generate_print_message_before_break(
         pomodoros_till_long_break: pomo_info.pomodoros_till_long_break - 1,
         minutes_till_long_break,
     };
-    let print_message = message_data.generate_print_message();
-    print_message
+    message_data.generate_print_message()
 }
@@ -0,0 +1,246 @@
+//! This module exposes the alarm as MPRIS2 media player on Linux so that it can be stopped
+//! with the media keys of the keyboard.
+//!
+//! The player is published as `org.mpris.MediaPlayer2.pomodoro` on the session bus at the object path
+//! `/org/mpris/MediaPlayer2` required by the MPRIS2 specification. `PlayPause`, `Pause`, `Stop` and
+//! `Next` stop the alarm.
+//!
+//! The timer publishes the player while the sounds of an end event are played and stops them
+//! with `sound::with_stop_signal`.
+//!
+//! This module is only available with the `mpris` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use locking_pomodoro_timer::mpris::MprisPlayer;
+//! use locking_pomodoro_timer::sound::play_sound_looping;
+//! use std::sync::atomic::AtomicBool;
+//! use std::sync::Arc;
+//!
+//! let stop = Arc::new(AtomicBool::new(false));
+//! // The player is removed from the session bus when it is dropped
+//! let _player = MprisPlayer::new(stop.clone()).unwrap();
+//! play_sound_looping(&None, stop).unwrap();
+//! ```
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use zbus::blocking::connection::{Builder, Connection};
+use zbus::interface;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+/// The name of the player on the session bus.
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.pomodoro";
+/// The object path of the player, which is fixed by the MPRIS2 specification.
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// The ID of the only track, the alarm.
+const TRACK_ID: &str = "/org/mpris/MediaPlayer2/pomodoro/alarm";
+
+/// The alarm published as MPRIS2 media player on the session bus.
+///
+/// The player is removed from the session bus when it is dropped.
+pub struct MprisPlayer {
+    _connection: Connection,
+}
+
+impl MprisPlayer {
+    /// Publishes the player on the session bus.
+    ///
+    /// # Arguments
+    /// * `stop` - Set to `true` when the user stops the alarm with the media keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no session bus or the name is already taken.
+    pub fn new(stop: Arc<AtomicBool>) -> zbus::Result<Self> {
+        let connection = Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, MediaPlayer)?
+            .serve_at(OBJECT_PATH, Player { stop })?
+            .build()?;
+        Ok(MprisPlayer {
+            _connection: connection,
+        })
+    }
+}
+
+/// The `org.mpris.MediaPlayer2` interface.
+struct MediaPlayer;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl MediaPlayer {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Locking Pomodoro Timer"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The `org.mpris.MediaPlayer2.Player` interface controlling the alarm.
+struct Player {
+    stop: Arc<AtomicBool>,
+}
+
+impl Player {
+    /// Stops the alarm.
+    fn stop_alarm(&self) {
+        log::info!("Alarm stopped with the media keys.");
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.stop_alarm();
+    }
+
+    fn previous(&self) {}
+
+    fn pause(&self) {
+        self.stop_alarm();
+    }
+
+    fn play_pause(&self) {
+        self.stop_alarm();
+    }
+
+    fn stop(&self) {
+        self.stop_alarm();
+    }
+
+    fn play(&self) {}
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        if self.stop.load(Ordering::Relaxed) {
+            "Stopped"
+        } else {
+            "Playing"
+        }
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        if let Ok(track_id) = ObjectPath::try_from(TRACK_ID) {
+            if let Ok(track_id) = OwnedValue::try_from(Value::from(track_id)) {
+                metadata.insert("mpris:trackid".to_string(), track_id);
+            }
+        }
+        if let Ok(title) = OwnedValue::try_from(Value::from("Pomodoro alarm")) {
+            metadata.insert("xesam:title".to_string(), title);
+        }
+        metadata
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_player_stops_alarm() {
+    use crate::sound::with_stop_signal;
+
+    for control in [
+        Player::stop,
+        Player::pause,
+        Player::play_pause,
+        Player::next,
+    ] {
+        let stop = Arc::new(AtomicBool::new(false));
+        let player = Player { stop: stop.clone() };
+        assert_eq!(player.playback_status(), "Playing");
+        // The sounds played by the end events see the signal set by the media keys
+        with_stop_signal(stop.clone(), || control(&player));
+        assert!(stop.load(Ordering::Relaxed));
+        assert_eq!(player.playback_status(), "Stopped");
+    }
+}
@@ -0,0 +1,105 @@
+//! This module sends push notifications via [ntfy](https://ntfy.sh) for the `NtfyPush` end
+//! event, e.g. to notify the phone at the end of a Pomodoro session. The notifications are
+//! received by subscribing to the topic in the ntfy app.
+//!
+//! Sending notifications requires the `native` feature.
+#[cfg(feature = "native")]
+use anyhow::Context;
+use anyhow::Result;
+
+/// The server the notifications are sent to if no server is given.
+pub const DEFAULT_SERVER: &str = "https://ntfy.sh";
+
+/// The title of the notifications.
+#[cfg(feature = "native")]
+const TITLE: &str = "Locking Pomodoro Timer";
+
+/// Sends the message to the topic with `POST <server>/<topic>`.
+///
+/// # Arguments
+/// * `server` - The URL of the ntfy server, e.g. `https://ntfy.example.com`. If `None`,
+///   `https://ntfy.sh` is used.
+/// * `topic` - The topic, e.g. `my-pomodoro`. Everyone knowing the topic of a public server
+///   can subscribe to it, so it should be hard to guess.
+/// * `message` - The message of the notification.
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be reached or rejects the message.
+#[cfg(feature = "native")]
+pub fn push(server: Option<&str>, topic: &str, message: &str) -> Result<()> {
+    let url = topic_url(server.unwrap_or(DEFAULT_SERVER), topic);
+    ureq::post(&url)
+        .header("Title", TITLE)
+        .header("Tags", "tomato")
+        .send(message)
+        .with_context(|| format!("Failed to send the push notification to {}", url))?;
+    Ok(())
+}
+
+/// Fails to send the message like `push`, since notifications can only be sent with the
+/// `native` feature.
+///
+/// # Errors
+///
+/// Always returns an error.
+#[cfg(not(feature = "native"))]
+pub fn push(server: Option<&str>, topic: &str, _message: &str) -> Result<()> {
+    let url = topic_url(server.unwrap_or(DEFAULT_SERVER), topic);
+    anyhow::bail!(
+        "Sending the push notification to {} requires the `native` feature.",
+        url
+    )
+}
+
+/// Builds the URL of the topic on the server, see `push`.
+fn topic_url(server: &str, topic: &str) -> String {
+    format!("{}/{}", server.trim_end_matches('/'), topic)
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_push() {
+    assert_eq!(
+        topic_url(DEFAULT_SERVER, "my-pomodoro"),
+        "https://ntfy.sh/my-pomodoro"
+    );
+    assert_eq!(
+        topic_url("http://ntfy.local:8080/", "desk"),
+        "http://ntfy.local:8080/desk"
+    );
+
+    // A server which accepts one notification
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        use std::io::{Read, Write};
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !String::from_utf8_lossy(&request).ends_with("Break!") {
+            let read = stream.read(&mut buffer).unwrap();
+            assert!(read > 0, "{}", String::from_utf8_lossy(&request));
+            request.extend_from_slice(&buffer[..read]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    let server_url = format!("http://127.0.0.1:{}", port);
+    push(Some(&server_url), "pomodoro", "Break!").unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /pomodoro "), "{}", request);
+    // The names of the headers may be lowercase
+    let headers = request.to_lowercase();
+    assert!(headers.contains("title: locking pomodoro timer"));
+
+    // Nothing listens on the port anymore, so the connection is refused
+    let error = push(Some(&server_url), "pomodoro", "Break!").unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("Failed to send"),
+        "{:#}",
+        error
+    );
+}
@@ -0,0 +1,155 @@
+//! This module plays sounds directly on the default PipeWire sink on Linux, bypassing ALSA and
+//! PulseAudio, for systems on which the ALSA and PulseAudio backends of rodio fail.
+//!
+//! The decoded PCM frames are written to `pw-cat` of the PipeWire tools, which is a native
+//! PipeWire client. `pipewire-rs` is not used, as it needs the libpipewire headers and libclang at
+//! build time. The sound module falls back to rodio if `pw-cat` fails, e.g. if it isn't
+//! installed or no PipeWire daemon is running.
+//!
+//! This module is only available with the `pw-cat` feature.
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// The interval in which the stop signal is checked while `pw-cat` drains its buffer.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The number of frames written at once, i.e. 100 ms at 48 kHz.
+const FRAMES_PER_WRITE: usize = 4800;
+
+/// Plays the interleaved 16 bit samples on the default PipeWire sink and blocks until they were
+/// played or `is_stopped` returns true.
+///
+/// # Errors
+///
+/// Returns an error if `pw-cat` cannot be started or exits with a failure.
+pub(crate) fn play_samples(
+    samples: &[i16],
+    channels: u16,
+    sample_rate: u32,
+    is_stopped: impl Fn() -> bool,
+) -> io::Result<()> {
+    play_samples_with(
+        pw_cat_command(channels, sample_rate),
+        samples,
+        usize::from(channels.max(1)),
+        is_stopped,
+    )
+}
+
+/// Creates the command playing raw samples of stdin on the default PipeWire sink.
+fn pw_cat_command(channels: u16, sample_rate: u32) -> Command {
+    let mut command = Command::new("pw-cat");
+    command
+        .args(["--playback", "--raw", "--format", "s16"])
+        .args(["--rate", &sample_rate.to_string()])
+        .args(["--channels", &channels.to_string()])
+        .arg("-");
+    command
+}
+
+/// Writes the samples to the stdin of the command like `play_samples`.
+fn play_samples_with(
+    mut command: Command,
+    samples: &[i16],
+    channels: usize,
+    is_stopped: impl Fn() -> bool,
+) -> io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    for chunk in samples.chunks(FRAMES_PER_WRITE * channels) {
+        if is_stopped() {
+            return stop(child);
+        }
+        let bytes: Vec<u8> = chunk.iter().flat_map(|s| s.to_le_bytes()).collect();
+        if let Err(e) = stdin.write_all(&bytes) {
+            drop(stdin);
+            return Err(exit_error(child).unwrap_or(e));
+        }
+    }
+    // Closing stdin lets pw-cat exit once it played the remaining samples
+    drop(stdin);
+    loop {
+        if child.try_wait()?.is_some() {
+            return exit_error(child).map_or(Ok(()), Err);
+        }
+        if is_stopped() {
+            return stop(child);
+        }
+        thread::sleep(STOP_CHECK_INTERVAL);
+    }
+}
+
+/// Stops the playback of the child process.
+fn stop(mut child: Child) -> io::Result<()> {
+    child.kill()?;
+    child.wait()?;
+    Ok(())
+}
+
+/// Waits for the child process and creates an error from its stderr if it failed.
+fn exit_error(mut child: Child) -> Option<io::Error> {
+    let status = match child.wait() {
+        Ok(status) => status,
+        Err(e) => return Some(e),
+    };
+    if status.success() {
+        return None;
+    }
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr);
+    }
+    Some(io::Error::other(format!(
+        "pw-cat failed with {}: {}",
+        status,
+        stderr.trim()
+    )))
+}
+
+#[test]
+fn test_pw_cat_command() {
+    let command = pw_cat_command(2, 44100);
+    assert_eq!(command.get_program(), "pw-cat");
+    assert_eq!(
+        command.get_args().collect::<Vec<_>>(),
+        [
+            "--playback",
+            "--raw",
+            "--format",
+            "s16",
+            "--rate",
+            "44100",
+            "--channels",
+            "2",
+            "-"
+        ]
+    );
+}
+
+#[test]
+fn test_play_samples_with() {
+    let samples = vec![0i16; 3 * FRAMES_PER_WRITE];
+    // cat consumes the samples like pw-cat
+    assert!(play_samples_with(Command::new("cat"), &samples, 1, || false).is_ok());
+    let error = play_samples_with(Command::new("false"), &samples, 1, || false).unwrap_err();
+    assert!(error.to_string().contains("pw-cat failed"), "{}", error);
+    let missing = Command::new("locking-pomodoro-timer-missing-pw-cat");
+    assert_eq!(
+        play_samples_with(missing, &samples, 1, || false)
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::NotFound
+    );
+    // A stopped playback kills the process instead of waiting for it
+    let mut sleep = Command::new("sleep");
+    sleep.arg("10");
+    let started = std::time::Instant::now();
+    assert!(play_samples_with(sleep, &[], 1, || true).is_ok());
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
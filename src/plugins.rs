@@ -0,0 +1,303 @@
+//! This module loads end events from plugins for the `Plugin` end event.
+//!
+//! A plugin is a shared library in the directory `pluginDir` of the options with the extension of
+//! the platform, i.e. `.so` on Linux, `.dylib` on macOS and `.dll` on Windows. It exports the
+//! function `pomodoro_end_event_plugins` which returns the end events it provides, described with
+//! the `#[repr(C)]` structs of this module. The plugins are loaded once at startup with
+//! `load_plugins` and their end events are started by name with
+//! `{"plugin": {"name": "...", "config": {...}}}`.
+//!
+//! This module is only available with the `plugins` feature.
+//!
+//! # Examples
+//!
+//! A plugin written in Rust and built as `cdylib`:
+//!
+//! ```no_run
+//! use locking_pomodoro_timer::plugins::{
+//!     EndEventContext, EndEventPlugin, EndEventPluginList, PLUGIN_ABI_VERSION,
+//! };
+//! use std::ffi::CStr;
+//!
+//! extern "C" fn start(context: *const EndEventContext) -> i32 {
+//!     let phase = unsafe { CStr::from_ptr((*context).phase) };
+//!     println!("Hello from the plugin before the {} phase!", phase.to_string_lossy());
+//!     0
+//! }
+//!
+//! struct Plugins([EndEventPlugin; 1]);
+//! // SAFETY: The name points to a static string.
+//! unsafe impl Sync for Plugins {}
+//!
+//! static PLUGINS: Plugins = Plugins([EndEventPlugin {
+//!     name: c"hello".as_ptr(),
+//!     start: Some(start),
+//! }]);
+//!
+//! #[no_mangle]
+//! pub extern "C" fn pomodoro_end_event_plugins() -> EndEventPluginList {
+//!     EndEventPluginList {
+//!         abi_version: PLUGIN_ABI_VERSION,
+//!         plugins: PLUGINS.0.as_ptr(),
+//!         len: PLUGINS.0.len(),
+//!     }
+//! }
+//! ```
+use crate::pomodoro_phase::PomodoroPhase;
+use anyhow::{bail, Context, Result};
+use libloading::{Library, Symbol};
+use std::ffi::{c_char, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The version of the plugin interface. Plugins built for another version are not loaded.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// The name of the function every plugin exports, see `PluginEntry`.
+pub const PLUGIN_ENTRY_SYMBOL: &str = "pomodoro_end_event_plugins";
+
+/// The information passed to a plugin when its end event is started.
+///
+/// The strings are NUL-terminated UTF-8 and only valid during the call.
+#[repr(C)]
+pub struct EndEventContext {
+    /// The `config` of the end event as JSON, e.g. `{"volume":3}`, or `null` if not given.
+    pub config_json: *const c_char,
+    /// The phase the end event is started for as in the history, e.g. `shortBreak`, or an empty
+    /// string if the phase is not known.
+    pub phase: *const c_char,
+    /// The duration of the phase in seconds, or 0 if it is not known.
+    pub duration_secs: u64,
+}
+
+/// An end event provided by a plugin.
+#[repr(C)]
+pub struct EndEventPlugin {
+    /// The name of the end event as NUL-terminated UTF-8, which is used in the options.
+    /// It has to stay valid while the library is loaded.
+    pub name: *const c_char,
+    /// Starts the end event. Returns 0 on success, any other value is reported as failure.
+    /// End events without the function, i.e. a null pointer in C, are not registered.
+    pub start: Option<extern "C" fn(context: *const EndEventContext) -> i32>,
+}
+
+/// The end events provided by a plugin, returned by its `PluginEntry`.
+#[repr(C)]
+pub struct EndEventPluginList {
+    /// The version of the plugin interface the plugin was built for, i.e. `PLUGIN_ABI_VERSION`.
+    pub abi_version: u32,
+    /// A pointer to the first of `len` end events, which have to stay valid while the library is loaded.
+    pub plugins: *const EndEventPlugin,
+    /// The number of end events.
+    pub len: usize,
+}
+
+/// The signature of the function `pomodoro_end_event_plugins` every plugin exports.
+pub type PluginEntry = unsafe extern "C" fn() -> EndEventPluginList;
+
+/// An end event registered by a loaded plugin.
+struct RegisteredEndEvent {
+    name: String,
+    start: extern "C" fn(context: *const EndEventContext) -> i32,
+}
+
+/// The end events of the loaded plugins together with their libraries, which are kept loaded
+/// until the program exits.
+struct PluginRegistry {
+    end_events: Vec<RegisteredEndEvent>,
+    _libraries: Vec<Library>,
+}
+
+/// The plugins loaded by `load_plugins`.
+static REGISTRY: OnceLock<PluginRegistry> = OnceLock::new();
+
+/// Loads the plugins of the directory and registers their end events.
+///
+/// The libraries are loaded in the order of their file names. Libraries which cannot be loaded
+/// are skipped with a warning, as are end events whose name is already registered.
+///
+/// # Returns
+/// The names of the registered end events.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read or the plugins are already loaded.
+pub fn load_plugins(plugin_dir: &Path) -> Result<Vec<String>> {
+    if REGISTRY.get().is_some() {
+        bail!("The plugins are already loaded.");
+    }
+    let mut paths = std::fs::read_dir(plugin_dir)
+        .with_context(|| format!("Failed to read the plugin directory: {:?}", plugin_dir))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_plugin_file(path))
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+
+    let mut registry = PluginRegistry {
+        end_events: Vec::new(),
+        _libraries: Vec::new(),
+    };
+    for path in paths {
+        // SAFETY: Loading a library runs its initialization code, so only trusted libraries
+        // should be put into the plugin directory.
+        let loaded = unsafe { load_library(&path) };
+        let (library, end_events) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                log::warn!("Failed to load the plugin {:?}: {:#}", path, e);
+                continue;
+            }
+        };
+        for end_event in end_events {
+            if registry.end_events.iter().any(|e| e.name == end_event.name) {
+                log::warn!(
+                    "The end event '{}' of the plugin {:?} is already registered.",
+                    end_event.name,
+                    path
+                );
+                continue;
+            }
+            log::info!(
+                "Registered the end event '{}' of the plugin {:?}.",
+                end_event.name,
+                path
+            );
+            registry.end_events.push(end_event);
+        }
+        registry._libraries.push(library);
+    }
+    let names = registry.end_events.iter().map(|e| e.name.clone()).collect();
+    if REGISTRY.set(registry).is_err() {
+        bail!("The plugins are already loaded.");
+    }
+    Ok(names)
+}
+
+/// Checks if the file has the extension of shared libraries on this platform.
+fn is_plugin_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+}
+
+/// Loads the library and reads the end events it provides.
+///
+/// # Safety
+///
+/// The initialization code of the library is run and the function `pomodoro_end_event_plugins`
+/// has to have the signature of `PluginEntry`.
+unsafe fn load_library(path: &Path) -> Result<(Library, Vec<RegisteredEndEvent>)> {
+    let library = Library::new(path)?;
+    let entry: Symbol<PluginEntry> = library.get(PLUGIN_ENTRY_SYMBOL.as_bytes())?;
+    let list = entry();
+    if list.abi_version != PLUGIN_ABI_VERSION {
+        bail!(
+            "The plugin was built for version {} of the plugin interface instead of {}.",
+            list.abi_version,
+            PLUGIN_ABI_VERSION
+        );
+    }
+    let plugins = if list.plugins.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(list.plugins, list.len)
+    };
+    Ok((library, registered_end_events(plugins)))
+}
+
+/// Reads the end events of the plugin, skipping those without a name and those without a
+/// `start` function with a warning.
+///
+/// # Safety
+///
+/// The names have to be null or valid NUL-terminated strings.
+unsafe fn registered_end_events(plugins: &[EndEventPlugin]) -> Vec<RegisteredEndEvent> {
+    plugins
+        .iter()
+        .filter(|plugin| !plugin.name.is_null())
+        .filter_map(|plugin| {
+            let name = CStr::from_ptr(plugin.name).to_string_lossy().into_owned();
+            match plugin.start {
+                Some(start) => Some(RegisteredEndEvent { name, start }),
+                None => {
+                    log::warn!(
+                        "The end event '{}' of the plugin has no start function.",
+                        name
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Starts the end event of a plugin with the given config and prints a warning if no plugin
+/// provides it or it fails.
+///
+/// The phase and its duration are passed to the plugin if they are known.
+pub(crate) fn start_plugin_end_event(
+    name: &str,
+    config: &serde_json::Value,
+    phase: Option<PomodoroPhase>,
+    duration: Option<Duration>,
+) {
+    let Some(end_event) = REGISTRY
+        .get()
+        .and_then(|registry| registry.end_events.iter().find(|e| e.name == name))
+    else {
+        log::warn!("No plugin provides the end event '{}'.", name);
+        return;
+    };
+    let to_c_string = |s: String| CString::new(s).unwrap_or_default();
+    let config_json = to_c_string(config.to_string());
+    let phase = to_c_string(phase.map(|phase| phase.to_string()).unwrap_or_default());
+    let context = EndEventContext {
+        config_json: config_json.as_ptr(),
+        phase: phase.as_ptr(),
+        duration_secs: duration.map_or(0, |duration| duration.as_secs()),
+    };
+    let status = (end_event.start)(&context);
+    if status != 0 {
+        log::warn!(
+            "The end event '{}' of a plugin failed with status {}.",
+            name,
+            status
+        );
+    }
+}
+
+#[test]
+fn test_load_plugins_from_missing_directory() {
+    let error = load_plugins(Path::new("does/not/exist")).unwrap_err();
+    assert!(error.to_string().contains("does/not/exist"));
+    assert!(!is_plugin_file(Path::new("Cargo.toml")));
+}
+
+#[test]
+fn test_registered_end_events() {
+    extern "C" fn start(_context: *const EndEventContext) -> i32 {
+        0
+    }
+
+    let plugins = [
+        EndEventPlugin {
+            name: c"hello".as_ptr(),
+            start: Some(start),
+        },
+        EndEventPlugin {
+            name: c"no_start".as_ptr(),
+            start: None,
+        },
+        EndEventPlugin {
+            name: std::ptr::null(),
+            start: Some(start),
+        },
+    ];
+    // SAFETY: The names are static strings or null.
+    let end_events = unsafe { registered_end_events(&plugins) };
+    assert_eq!(end_events.len(), 1);
+    assert_eq!(end_events[0].name, "hello");
+    assert_eq!((end_events[0].start)(std::ptr::null()), 0);
+}
@@ -28,11 +28,10 @@ impl PomoInfo {
             options.interval_long_break - counter % options.interval_long_break;
         let is_long_break_coming =
             counter % options.interval_long_break == options.interval_long_break - 1;
-        let break_duration: Duration;
-        if is_long_break_coming {
-            break_duration = Duration::from_secs((options.duration_long_break * 60) as u64)
+        let break_duration = if is_long_break_coming {
+            Duration::from_secs((options.duration_long_break * 60) as u64)
         } else {
-            break_duration = Duration::from_secs((options.duration_short_break * 60) as u64)
+            Duration::from_secs((options.duration_short_break * 60) as u64)
         };
         PomoInfo {
             pomodoros_till_long_break,
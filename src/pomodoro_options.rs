@@ -1,16 +1,31 @@
-use crate::end_events::EndEvent;
-use anyhow::{Context, Result};
+//! This module defines the `PomodoroOptions` struct and functions to read and write them as JSON.
+use crate::end_events::{EndEvent, ValidationError};
+use crate::screen_lock::{
+    ContinuousLockConfig, LinuxLocker, DEFAULT_LINUX_LOCK_PRIORITY, MIN_POLL_INTERVAL,
+};
+use crate::theme::ThemeConfig;
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "native")]
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
 #[cfg(test)]
 use project_root::get_project_root;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
+/// The environment variable overriding `durationPomodoro`, in seconds or as `MM:SS`.
+pub const WORK_DURATION_ENV: &str = "POMODORO_WORK_DURATION";
+/// The environment variable overriding `durationShortBreak`, in seconds or as `MM:SS`.
+pub const BREAK_DURATION_ENV: &str = "POMODORO_BREAK_DURATION";
+
 /// Struct representing the options for a Pomodoro timer.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase", default = "PomodoroOptions::default")]
 pub struct PomodoroOptions {
     /// The duration of a single Pomodoro session in minutes.
@@ -27,6 +42,8 @@ pub struct PomodoroOptions {
     pub auto_start_pomodoro: bool,
     /// The interval in number of Pomodoro sessions after which a long break should be taken.
     pub interval_long_break: i32,
+    /// The event to be executed when a Pomodoro session starts.
+    pub event_start_pomodoro: EndEvent,
     /// The end event to be executed after a Pomodoro session ends.
     pub end_event_pomodoro: EndEvent,
     /// The end event to be executed after the additional Pomodoro after a Pomodoro session ends.
@@ -42,25 +59,117 @@ pub struct PomodoroOptions {
     /// If false, the screen will only be locked once at the start of the break.
     #[serde(default = "default_enforce_lock_screen")]
     pub enforce_lock_screen: bool,
+    /// The order in which the screen lockers are tried on Linux. Only the listed lockers are tried.
+    pub linux_lock_priority: Vec<LinuxLocker>,
+    /// The configuration of the screen lock monitoring during enforced breaks, e.g. the grace
+    /// period before the screen is re-locked. The durations are given in milliseconds.
+    #[serde(default)]
+    pub lock_config: ContinuousLockConfig,
+    /// The colors and symbols of the progress bar.
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// The label of the Pomodoro sessions in the history, e.g. the name of a project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_label: Option<String>,
+    /// The label of the breaks in the history.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_label: Option<String>,
+    /// The maximum number of completed Pomodoro sessions per day. No session is started
+    /// once the history contains this many completed sessions of today.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_sessions_per_day: Option<u32>,
+    /// The number of cycles of a Pomodoro session and its break to run before exiting.
+    /// Runs until quit if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u32>,
+    /// The directory the end event plugins are loaded from at startup. Requires the `plugins` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugin_dir: Option<PathBuf>,
 }
 
 fn default_enforce_lock_screen() -> bool {
     true
 }
 
+/// Options overriding some of the `PomodoroOptions`, e.g. from environment variables or
+/// command line arguments. Options which are `None` are not overridden.
+///
+/// See `PomodoroOptions::merge`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PartialPomodoroOptions {
+    /// Overrides `duration_pomodoro`.
+    pub duration_pomodoro: Option<i32>,
+    /// Overrides `additional_duration`.
+    pub additional_duration: Option<i32>,
+    /// Overrides `duration_short_break`.
+    pub duration_short_break: Option<i32>,
+    /// Overrides `duration_long_break`.
+    pub duration_long_break: Option<i32>,
+    /// Overrides `auto_start_break`.
+    pub auto_start_break: Option<bool>,
+    /// Overrides `auto_start_pomodoro`.
+    pub auto_start_pomodoro: Option<bool>,
+    /// Overrides `interval_long_break`.
+    pub interval_long_break: Option<i32>,
+    /// Overrides `event_start_pomodoro`.
+    pub event_start_pomodoro: Option<EndEvent>,
+    /// Overrides `end_event_pomodoro`.
+    pub end_event_pomodoro: Option<EndEvent>,
+    /// Overrides `end_event_additional_pomodoro`.
+    pub end_event_additional_pomodoro: Option<EndEvent>,
+    /// Overrides `interval_reminder_after_break`.
+    pub interval_reminder_after_break: Option<i32>,
+    /// Overrides `event_reminder_after_break`.
+    pub event_reminder_after_break: Option<EndEvent>,
+    /// Overrides `enforce_lock_screen`.
+    pub enforce_lock_screen: Option<bool>,
+    /// Overrides `linux_lock_priority`.
+    pub linux_lock_priority: Option<Vec<LinuxLocker>>,
+    /// Overrides `lock_config`.
+    pub lock_config: Option<ContinuousLockConfig>,
+    /// Overrides `theme`.
+    pub theme: Option<ThemeConfig>,
+    /// Overrides `work_label`.
+    pub work_label: Option<String>,
+    /// Overrides `break_label`.
+    pub break_label: Option<String>,
+    /// Overrides `max_sessions_per_day`.
+    pub max_sessions_per_day: Option<u32>,
+    /// Overrides `cycles`.
+    pub cycles: Option<u32>,
+    /// Overrides `plugin_dir`.
+    pub plugin_dir: Option<PathBuf>,
+}
+
+/// The longest duration of a Pomodoro session or break in minutes, one day.
+pub const MAX_DURATION_MINUTES: i32 = 24 * 60;
+
 /// Error type for verification errors of `PomodoroOptions`.
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum VerificationError {
-    #[error("Pomodoro duration should be at least 1 minute.")]
+    #[error("Pomodoro duration should be between 1 and {MAX_DURATION_MINUTES} minutes.")]
     InvalidDuration,
-    #[error("Additional duration should be at least 0 minute.")]
+    #[error("Additional duration should be between 0 and {MAX_DURATION_MINUTES} minutes.")]
     InvalidAdditionalDuration,
-    #[error("Short break duration should be at least 0 minute.")]
+    #[error("Short break duration should be between 0 and {MAX_DURATION_MINUTES} minutes.")]
     InvalidShortBreakDuration,
-    #[error("Long break duration should be at least 0 minute.")]
+    #[error("Long break duration should be between 0 and {MAX_DURATION_MINUTES} minutes.")]
     InvalidLongBreakDuration,
-    #[error("Sound file does not exist.")]
-    InvalidSoundFile,
+    #[error("Sound file {path:?} of {name} does not exist.")]
+    InvalidSoundFile { name: &'static str, path: PathBuf },
+    #[error("Linux lock priority should contain at least one locker.")]
+    InvalidLinuxLockPriority,
+    #[error("The poll interval of the lock config should be at least {} ms.", MIN_POLL_INTERVAL.as_millis())]
+    InvalidLockPollInterval,
+    #[error("The progress and empty symbols of the theme should be printable and equally wide.")]
+    InvalidThemeSymbols,
+    #[error("{name} is invalid: {source}")]
+    InvalidEndEvent {
+        name: &'static str,
+        source: ValidationError,
+    },
 }
 
 impl Default for PomodoroOptions {
@@ -74,61 +183,430 @@ impl Default for PomodoroOptions {
             auto_start_break: true,
             auto_start_pomodoro: true,
             interval_long_break: 4,
+            event_start_pomodoro: EndEvent::DoNothing,
             end_event_pomodoro: EndEvent::Sound {
                 filepath_sound: None,
+                backend: None,
+                sound_format: None,
+                completion_sound: None,
+                delay_before: None,
+            },
+            end_event_additional_pomodoro: EndEvent::LockScreen {
+                message: None,
+                message_template: None,
+                background_image: None,
+                delay_before: None,
             },
-            end_event_additional_pomodoro: EndEvent::LockScreen,
             interval_reminder_after_break: 5,
             event_reminder_after_break: EndEvent::Sound {
                 filepath_sound: None,
+                backend: None,
+                sound_format: None,
+                completion_sound: None,
+                delay_before: None,
             },
             enforce_lock_screen: true,
+            linux_lock_priority: DEFAULT_LINUX_LOCK_PRIORITY.to_vec(),
+            lock_config: ContinuousLockConfig::default(),
+            theme: ThemeConfig::default(),
+            work_label: None,
+            break_label: None,
+            max_sessions_per_day: None,
+            cycles: None,
+            plugin_dir: None,
         }
     }
 }
 
 impl PomodoroOptions {
+    /// Merges the options of a source with a higher precedence into the base options.
+    ///
+    /// Every option which is set in `override_` replaces the option of `base`.
+    /// Sources are layered by merging them in order of increasing precedence,
+    /// e.g. the options file, then the environment variables and then the command line arguments.
+    ///
+    /// The base options are consumed, so ignoring the merged options is an error:
+    ///
+    /// ```compile_fail
+    /// #![deny(unused_must_use)]
+    /// use locking_pomodoro_timer::pomodoro_options::{PartialPomodoroOptions, PomodoroOptions};
+    ///
+    /// PomodoroOptions::merge(PomodoroOptions::default(), PartialPomodoroOptions::default());
+    /// ```
+    #[must_use]
+    pub fn merge(base: PomodoroOptions, override_: PartialPomodoroOptions) -> PomodoroOptions {
+        PomodoroOptions {
+            duration_pomodoro: override_
+                .duration_pomodoro
+                .unwrap_or(base.duration_pomodoro),
+            additional_duration: override_
+                .additional_duration
+                .unwrap_or(base.additional_duration),
+            duration_short_break: override_
+                .duration_short_break
+                .unwrap_or(base.duration_short_break),
+            duration_long_break: override_
+                .duration_long_break
+                .unwrap_or(base.duration_long_break),
+            auto_start_break: override_.auto_start_break.unwrap_or(base.auto_start_break),
+            auto_start_pomodoro: override_
+                .auto_start_pomodoro
+                .unwrap_or(base.auto_start_pomodoro),
+            interval_long_break: override_
+                .interval_long_break
+                .unwrap_or(base.interval_long_break),
+            event_start_pomodoro: override_
+                .event_start_pomodoro
+                .unwrap_or(base.event_start_pomodoro),
+            end_event_pomodoro: override_
+                .end_event_pomodoro
+                .unwrap_or(base.end_event_pomodoro),
+            end_event_additional_pomodoro: override_
+                .end_event_additional_pomodoro
+                .unwrap_or(base.end_event_additional_pomodoro),
+            interval_reminder_after_break: override_
+                .interval_reminder_after_break
+                .unwrap_or(base.interval_reminder_after_break),
+            event_reminder_after_break: override_
+                .event_reminder_after_break
+                .unwrap_or(base.event_reminder_after_break),
+            enforce_lock_screen: override_
+                .enforce_lock_screen
+                .unwrap_or(base.enforce_lock_screen),
+            linux_lock_priority: override_
+                .linux_lock_priority
+                .unwrap_or(base.linux_lock_priority),
+            lock_config: override_.lock_config.unwrap_or(base.lock_config),
+            theme: override_.theme.unwrap_or(base.theme),
+            work_label: override_.work_label.or(base.work_label),
+            break_label: override_.break_label.or(base.break_label),
+            max_sessions_per_day: override_.max_sessions_per_day.or(base.max_sessions_per_day),
+            cycles: override_.cycles.or(base.cycles),
+            plugin_dir: override_.plugin_dir.or(base.plugin_dir),
+        }
+    }
+
+    /// Gets the differences of the other options to these options, e.g. after the options file
+    /// was reloaded. The options are named as in the options file and their values are
+    /// formatted as JSON.
+    ///
+    /// Optional options which are set in only one of the options are `FieldAdded` or
+    /// `FieldRemoved`, all other differences are `FieldChanged`.
+    #[must_use]
+    pub fn diff(&self, other: &PomodoroOptions) -> Vec<ConfigDiff> {
+        [
+            field_diff(
+                "durationPomodoro",
+                &self.duration_pomodoro,
+                &other.duration_pomodoro,
+            ),
+            field_diff(
+                "additionalDuration",
+                &self.additional_duration,
+                &other.additional_duration,
+            ),
+            field_diff(
+                "durationShortBreak",
+                &self.duration_short_break,
+                &other.duration_short_break,
+            ),
+            field_diff(
+                "durationLongBreak",
+                &self.duration_long_break,
+                &other.duration_long_break,
+            ),
+            field_diff(
+                "autoStartBreak",
+                &self.auto_start_break,
+                &other.auto_start_break,
+            ),
+            field_diff(
+                "autoStartPomodoro",
+                &self.auto_start_pomodoro,
+                &other.auto_start_pomodoro,
+            ),
+            field_diff(
+                "intervalLongBreak",
+                &self.interval_long_break,
+                &other.interval_long_break,
+            ),
+            field_diff(
+                "eventStartPomodoro",
+                &self.event_start_pomodoro,
+                &other.event_start_pomodoro,
+            ),
+            field_diff(
+                "endEventPomodoro",
+                &self.end_event_pomodoro,
+                &other.end_event_pomodoro,
+            ),
+            field_diff(
+                "endEventAdditionalPomodoro",
+                &self.end_event_additional_pomodoro,
+                &other.end_event_additional_pomodoro,
+            ),
+            field_diff(
+                "intervalReminderAfterBreak",
+                &self.interval_reminder_after_break,
+                &other.interval_reminder_after_break,
+            ),
+            field_diff(
+                "eventReminderAfterBreak",
+                &self.event_reminder_after_break,
+                &other.event_reminder_after_break,
+            ),
+            field_diff(
+                "enforceLockScreen",
+                &self.enforce_lock_screen,
+                &other.enforce_lock_screen,
+            ),
+            field_diff(
+                "linuxLockPriority",
+                &self.linux_lock_priority,
+                &other.linux_lock_priority,
+            ),
+            field_diff("lockConfig", &self.lock_config, &other.lock_config),
+            field_diff("theme", &self.theme, &other.theme),
+            optional_field_diff("workLabel", &self.work_label, &other.work_label),
+            optional_field_diff("breakLabel", &self.break_label, &other.break_label),
+            optional_field_diff(
+                "maxSessionsPerDay",
+                &self.max_sessions_per_day,
+                &other.max_sessions_per_day,
+            ),
+            optional_field_diff("cycles", &self.cycles, &other.cycles),
+            optional_field_diff("pluginDir", &self.plugin_dir, &other.plugin_dir),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Logs the differences of the other options to these options at info level.
+    ///
+    /// Changes of end events which contain credentials in either options, see
+    /// `EndEvent::contains_secrets`, are only logged at debug level and without their values.
+    pub(crate) fn log_diff(&self, other: &PomodoroOptions) {
+        for diff in self.diff(other) {
+            if self.is_sensitive_field(diff.field()) || other.is_sensitive_field(diff.field()) {
+                log::debug!("{} changed.", diff.field());
+            } else {
+                log::info!("{}", diff);
+            }
+        }
+    }
+
+    /// Serializes the options as pretty JSON for logging. The end events which contain
+    /// credentials are replaced by `"<redacted>"`, like in `log_diff`.
+    pub(crate) fn to_redacted_json(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        for (name, end_event) in self.end_events() {
+            if let Some(field) = value.get_mut(name).filter(|_| end_event.contains_secrets()) {
+                *field = "<redacted>".into();
+            }
+        }
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    }
+
+    /// Checks whether the option of the name contains credentials.
+    fn is_sensitive_field(&self, field: &str) -> bool {
+        self.end_events()
+            .into_iter()
+            .any(|(name, end_event)| name == field && end_event.contains_secrets())
+    }
+
+    /// Gets all end events together with their names in the options file.
+    pub(crate) fn end_events(&self) -> [(&'static str, &EndEvent); 4] {
+        [
+            ("eventStartPomodoro", &self.event_start_pomodoro),
+            ("endEventPomodoro", &self.end_event_pomodoro),
+            (
+                "endEventAdditionalPomodoro",
+                &self.end_event_additional_pomodoro,
+            ),
+            ("eventReminderAfterBreak", &self.event_reminder_after_break),
+        ]
+    }
+
     /// Verifies the validity of the `PomodoroOptions` instance.
     ///
     /// # Errors
     ///
     /// Returns a `VerificationError` if any of the options are invalid.
-    fn verify(&self) -> Result<(), VerificationError> {
-        if self.duration_pomodoro < 1 {
+    pub(crate) fn verify(&self) -> Result<(), VerificationError> {
+        if !(1..=MAX_DURATION_MINUTES).contains(&self.duration_pomodoro) {
             return Err(VerificationError::InvalidDuration);
         }
-        if self.additional_duration < 0 {
+        if !(0..=MAX_DURATION_MINUTES).contains(&self.additional_duration) {
             return Err(VerificationError::InvalidAdditionalDuration);
         }
-        if self.duration_short_break < 0 {
+        if !(0..=MAX_DURATION_MINUTES).contains(&self.duration_short_break) {
             return Err(VerificationError::InvalidShortBreakDuration);
         }
-        if self.duration_long_break < 0 {
+        if !(0..=MAX_DURATION_MINUTES).contains(&self.duration_long_break) {
             return Err(VerificationError::InvalidLongBreakDuration);
         }
-        // Validate sound files - only check if filepath is provided and not empty
-        if let EndEvent::Sound { filepath_sound } = &self.end_event_pomodoro {
-            if let Some(path) = filepath_sound {
-                if !path.as_os_str().is_empty() && !path.is_file() {
-                    return Err(VerificationError::InvalidSoundFile);
-                }
-            }
+        if self.linux_lock_priority.is_empty() {
+            return Err(VerificationError::InvalidLinuxLockPriority);
+        }
+        if self.lock_config.poll_interval < MIN_POLL_INTERVAL {
+            return Err(VerificationError::InvalidLockPollInterval);
+        }
+        if !self.theme.has_valid_symbols() {
+            return Err(VerificationError::InvalidThemeSymbols);
         }
-        if let EndEvent::Sound { filepath_sound } = &self.end_event_additional_pomodoro {
-            if let Some(path) = filepath_sound {
-                if !path.as_os_str().is_empty() && !path.is_file() {
-                    return Err(VerificationError::InvalidSoundFile);
+        // A missing sound file is reported last as it is replaced by the internal sound
+        let mut sound_file_not_found = None;
+        for (name, end_event) in self.end_events() {
+            match end_event.validate() {
+                Ok(()) => (),
+                Err(ValidationError::SoundFileNotFound(path)) => {
+                    sound_file_not_found.get_or_insert((name, path));
                 }
+                Err(source) => return Err(VerificationError::InvalidEndEvent { name, source }),
             }
         }
+        if let Some((name, path)) = sound_file_not_found {
+            return Err(VerificationError::InvalidSoundFile { name, path });
+        }
 
         Ok(())
     }
 }
 
+/// A difference between two options, see `PomodoroOptions::diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigDiff {
+    /// The option has another value.
+    FieldChanged {
+        /// The name of the option in the options file.
+        field: &'static str,
+        /// The previous value as JSON.
+        old: String,
+        /// The new value as JSON.
+        new: String,
+    },
+    /// The optional option was not set before.
+    FieldAdded {
+        /// The name of the option in the options file.
+        field: &'static str,
+    },
+    /// The optional option is not set anymore.
+    FieldRemoved {
+        /// The name of the option in the options file.
+        field: &'static str,
+    },
+}
+
+impl ConfigDiff {
+    /// Gets the name of the changed option in the options file.
+    #[must_use]
+    pub fn field(&self) -> &'static str {
+        match self {
+            ConfigDiff::FieldChanged { field, .. }
+            | ConfigDiff::FieldAdded { field }
+            | ConfigDiff::FieldRemoved { field } => field,
+        }
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    /// Formats the difference as sentence, e.g. `durationPomodoro changed from 25 to 50.`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigDiff::FieldChanged { field, old, new } => {
+                write!(f, "{} changed from {} to {}.", field, old, new)
+            }
+            ConfigDiff::FieldAdded { field } => write!(f, "{} was added.", field),
+            ConfigDiff::FieldRemoved { field } => write!(f, "{} was removed.", field),
+        }
+    }
+}
+
+/// Gets the difference of the values of the option, or `None` if they are equal as JSON.
+fn field_diff<T: Serialize>(field: &'static str, old: &T, new: &T) -> Option<ConfigDiff> {
+    let old = serde_json::to_string(old).unwrap_or_default();
+    let new = serde_json::to_string(new).unwrap_or_default();
+    (old != new).then_some(ConfigDiff::FieldChanged { field, old, new })
+}
+
+/// Gets the difference of the values of the optional option like `field_diff`.
+fn optional_field_diff<T: Serialize>(
+    field: &'static str,
+    old: &Option<T>,
+    new: &Option<T>,
+) -> Option<ConfigDiff> {
+    match (old, new) {
+        (None, None) => None,
+        (None, Some(_)) => Some(ConfigDiff::FieldAdded { field }),
+        (Some(_), None) => Some(ConfigDiff::FieldRemoved { field }),
+        (Some(old), Some(new)) => field_diff(field, old, new),
+    }
+}
+
+/// Reads the options overridden by the environment variables `POMODORO_WORK_DURATION`
+/// and `POMODORO_BREAK_DURATION`.
+///
+/// The values are given in seconds, e.g. `1500`, or as `MM:SS`, e.g. `25:00`.
+/// As the options have a resolution of one minute, the values must be whole minutes.
+///
+/// # Errors
+///
+/// Returns an error if a variable is set to an invalid duration.
+pub fn options_from_env() -> Result<PartialPomodoroOptions> {
+    let duration_pomodoro = duration_minutes_from_env(WORK_DURATION_ENV)?;
+    if duration_pomodoro.is_some_and(|minutes| minutes < 1) {
+        bail!("{} must be at least one minute.", WORK_DURATION_ENV);
+    }
+    Ok(PartialPomodoroOptions {
+        duration_pomodoro,
+        duration_short_break: duration_minutes_from_env(BREAK_DURATION_ENV)?,
+        ..Default::default()
+    })
+}
+
+/// Reads a duration in whole minutes from the environment variable.
+///
+/// Returns `None` if the variable is not set.
+fn duration_minutes_from_env(name: &str) -> Result<Option<i32>> {
+    match env::var(name) {
+        Ok(value) => parse_duration_minutes(&value)
+            .map(Some)
+            .with_context(|| format!("Invalid value '{}' of {}", value, name)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Invalid value of {}", name)),
+    }
+}
+
+/// Parses a duration in seconds, e.g. `1500`, or as `MM:SS`, e.g. `25:00`, to whole minutes.
+fn parse_duration_minutes(value: &str) -> Result<i32> {
+    let value = value.trim();
+    let seconds: u64 = match value.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: u64 = minutes.parse().context("expected the minutes before ':'")?;
+            let seconds: u64 = seconds.parse().context("expected the seconds after ':'")?;
+            if seconds >= 60 {
+                bail!("the seconds must be below 60");
+            }
+            minutes
+                .checked_mul(60)
+                .and_then(|minutes| minutes.checked_add(seconds))
+                .context("the duration is too long")?
+        }
+        None => value
+            .parse()
+            .context("expected the duration in seconds or as MM:SS")?,
+    };
+    if !seconds.is_multiple_of(60) {
+        bail!("the duration must be whole minutes");
+    }
+    i32::try_from(seconds / 60).context("the duration is too long")
+}
+
 /// Error type for `PomodoroOptions` related errors.
 #[derive(Error, Debug)]
-pub(crate) enum PomodoroOptionsError {
+pub enum PomodoroOptionsError {
+    /// The options file does not exist at the given path.
     #[error("Failed to read options from JSON file at path: {:?}", _0)]
     OptionFileNotFound(PathBuf),
 }
@@ -142,6 +620,67 @@ pub(crate) enum PomodoroOptionsError {
 ///
 /// Returns a `PomodoroOptionsError` if the options file is not found or if there are any other errors during the process.
 pub fn read_options_from_json(filepath_json: Option<PathBuf>) -> Result<PomodoroOptions> {
+    let mut data = parse_options_from_json(filepath_json)?;
+    match data.verify() {
+        Ok(_) => (),
+        Err(VerificationError::InvalidSoundFile { .. }) => {
+            for end_event in [
+                &mut data.event_start_pomodoro,
+                &mut data.end_event_pomodoro,
+                &mut data.end_event_additional_pomodoro,
+                &mut data.event_reminder_after_break,
+            ] {
+                use_internal_sound_if_missing(end_event);
+            }
+            // Report the other problems of the sound events, e.g. an unsupported backend
+            data.verify()?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(data)
+}
+
+/// Replaces a sound file which does not exist by the internal sound and removes a completion
+/// sound which does not exist, also in the steps of sequences.
+fn use_internal_sound_if_missing(end_event: &mut EndEvent) {
+    match end_event {
+        EndEvent::Sound {
+            filepath_sound,
+            completion_sound,
+            ..
+        } => {
+            if let Some(path) =
+                filepath_sound.take_if(|path| !path.as_os_str().is_empty() && !path.is_file())
+            {
+                log::warn!(
+                    "Sound file {:?} does not exist. Using internal default sound.",
+                    path
+                );
+            }
+            if let Some(path) = completion_sound.take_if(|path| !path.is_file()) {
+                log::warn!(
+                    "Completion sound {:?} does not exist. Playing no completion sound.",
+                    path
+                );
+            }
+        }
+        EndEvent::Sequence { events, .. } => {
+            for event in events {
+                use_internal_sound_if_missing(event);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Parses the `PomodoroOptions` from a JSON file without verifying them.
+///
+/// The file is found like in `read_options_from_json`.
+///
+/// # Errors
+///
+/// Returns a `PomodoroOptionsError` if the options file is not found or an error if it cannot be read or parsed.
+pub(crate) fn parse_options_from_json(filepath_json: Option<PathBuf>) -> Result<PomodoroOptions> {
     let file_path = match filepath_json {
         Some(path) => path,
         None => get_filepath_options_next_to_executable()?,
@@ -155,22 +694,135 @@ pub fn read_options_from_json(filepath_json: Option<PathBuf>) -> Result<Pomodoro
     file.read_to_string(&mut contents)
         .with_context(|| format!("Failed to read file: {:?}", file_path))?;
 
-    let mut data: PomodoroOptions = serde_json::from_str(&contents)
+    let data: PomodoroOptions = serde_json::from_str(&contents)
         .with_context(|| format!("Failed to parse JSON file: {:?}", file_path))?;
-    match data.verify() {
-        Ok(_) => (),
-        Err(VerificationError::InvalidSoundFile) => {
-            println!("Sound file does not exist. Using internal default sound.");
-            if let EndEvent::Sound { filepath_sound } = &mut data.end_event_pomodoro {
-                *filepath_sound = None;
+    Ok(data)
+}
+
+/// A function applying the options which take precedence over the options file, e.g. the
+/// environment variables and the command line arguments.
+type OverrideFn = dyn Fn(PomodoroOptions) -> PomodoroOptions + Send + Sync;
+
+/// Watches the options file and reloads the `PomodoroOptions` whenever the file changes.
+///
+/// The watching stops when the `OptionsWatcher` is dropped.
+pub struct OptionsWatcher {
+    latest: Arc<Mutex<Option<PomodoroOptions>>>,
+    overrides: Box<OverrideFn>,
+    #[cfg(feature = "native")]
+    _watcher: RecommendedWatcher,
+}
+
+impl OptionsWatcher {
+    /// Sets the function applying the options which take precedence over the options file to
+    /// the reloaded options, e.g. by merging the environment variables and the command line
+    /// arguments with `PomodoroOptions::merge`. By default, the reloaded options are used as
+    /// they are.
+    #[must_use]
+    pub fn with_overrides(
+        mut self,
+        overrides: impl Fn(PomodoroOptions) -> PomodoroOptions + Send + Sync + 'static,
+    ) -> Self {
+        self.overrides = Box::new(overrides);
+        self
+    }
+
+    /// Gets the options most recently reloaded from the file with the overrides applied.
+    ///
+    /// Returns `None` if the file has not changed since the watching started or the options
+    /// were taken with `take`.
+    #[must_use]
+    pub fn latest(&self) -> Option<PomodoroOptions> {
+        self.latest.lock().unwrap().clone().map(&self.overrides)
+    }
+
+    /// Takes the options most recently reloaded from the file with the overrides applied, so
+    /// that every reload is only applied once.
+    ///
+    /// Returns `None` if the file has not changed since the watching started or the last call.
+    #[must_use]
+    pub fn take(&self) -> Option<PomodoroOptions> {
+        self.latest.lock().unwrap().take().map(&self.overrides)
+    }
+}
+
+/// Starts watching the options file for changes.
+///
+/// If `filepath_json` is `Some`, the specified file is watched.
+/// If `filepath_json` is `None`, the options file next to the executable is watched.
+/// The folder of the file is watched since many editors replace the file on saving.
+/// If the changed file cannot be read, a warning is logged and the previous options are kept.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be watched.
+#[cfg(feature = "native")]
+pub fn watch_options(filepath_json: Option<PathBuf>) -> Result<OptionsWatcher> {
+    let file_path = match filepath_json {
+        Some(path) => path,
+        None => get_filepath_options_next_to_executable()?,
+    };
+    let folder_path = match file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let latest = Arc::new(Mutex::new(None));
+    let latest_storage = latest.clone();
+    let watched_file_path = file_path.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                log::warn!("Failed to watch options file: {}", e);
+                return;
             }
-            if let EndEvent::Sound { filepath_sound } = &mut data.end_event_additional_pomodoro {
-                *filepath_sound = None;
+        };
+        let is_options_file = event
+            .paths
+            .iter()
+            .any(|path| path.file_name() == watched_file_path.file_name());
+        if !is_options_file || !(event.kind.is_modify() || event.kind.is_create()) {
+            return;
+        }
+        match read_options_from_json(Some(watched_file_path.clone())) {
+            Ok(options) => {
+                log::info!("Reloaded options from {:?}.", watched_file_path);
+                *latest_storage.lock().unwrap() = Some(options);
             }
+            Err(e) => log::warn!("Failed to reload options: {:#}", e),
         }
-        Err(e) => return Err(e.into()),
-    }
-    Ok(data)
+    })
+    .context("Failed to create the watcher of the options file.")?;
+    watcher
+        .watch(&folder_path, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch options file: {:?}", file_path))?;
+    Ok(OptionsWatcher {
+        latest,
+        overrides: Box::new(|options| options),
+        _watcher: watcher,
+    })
+}
+
+/// Fails to watch the options file like `watch_options`, since the file can only be watched
+/// with the `native` feature.
+///
+/// # Errors
+///
+/// Always returns an error.
+#[cfg(not(feature = "native"))]
+pub fn watch_options(filepath_json: Option<PathBuf>) -> Result<OptionsWatcher> {
+    bail!(
+        "Watching the options file {:?} requires the `native` feature.",
+        filepath_json
+    )
+}
+
+/// Generates the JSON Schema of the options file.
+///
+/// The schema contains the descriptions of the doc comments and can be used by IDEs for autocompletion and validation.
+#[must_use]
+pub fn options_json_schema() -> serde_json::Value {
+    schemars::schema_for!(PomodoroOptions).to_value()
 }
 
 /// Writes the default `PomodoroOptions` to a JSON file next to the executable.
@@ -178,7 +830,7 @@ pub fn read_options_from_json(filepath_json: Option<PathBuf>) -> Result<Pomodoro
 /// # Errors
 ///
 /// Returns an error if there are any errors during the process of writing the options to the file.
-pub(crate) fn write_default_options_to_json_next_to_executable() -> Result<()> {
+pub fn write_default_options_to_json_next_to_executable() -> Result<()> {
     let file_path = get_filepath_options_next_to_executable()?;
     let options = PomodoroOptions::default();
     write_options_to_json(&file_path, &options)
@@ -195,7 +847,7 @@ pub(crate) fn write_default_options_to_json_next_to_executable() -> Result<()> {
 ///
 /// Returns an error if there are any errors during the process of writing the options to the file.
 pub(crate) fn write_options_to_json(file_path: &PathBuf, options: &PomodoroOptions) -> Result<()> {
-    let file = File::create(&file_path)
+    let file = File::create(file_path)
         .with_context(|| format!("Failed to create file: {:?}", file_path))?;
     serde_json::to_writer_pretty(file, options)
         .with_context(|| format!("Failed to write to file: {:?}", file_path))?;
@@ -243,3 +895,251 @@ fn test_read_options_from_json() {
     assert_eq!(options.duration_short_break, 5);
     assert_eq!(options.duration_long_break, 15);
 }
+
+#[cfg(feature = "native")]
+#[test]
+fn test_watch_options() {
+    let folder_path = env::temp_dir().join("locking_pomodoro_timer_test_watch_options");
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let file_path = folder_path.join("pomodoro_options.json");
+    write_options_to_json(&file_path, &PomodoroOptions::default()).unwrap();
+
+    let watcher = watch_options(Some(file_path.clone()))
+        .unwrap()
+        .with_overrides(|options| PomodoroOptions {
+            cycles: Some(1),
+            ..options
+        });
+    assert!(watcher.latest().is_none());
+
+    let options = PomodoroOptions {
+        duration_pomodoro: 50,
+        ..PomodoroOptions::default()
+    };
+    write_options_to_json(&file_path, &options).unwrap();
+    let mut latest = None;
+    for _ in 0..50 {
+        latest = watcher
+            .latest()
+            .filter(|options| options.duration_pomodoro == 50);
+        if latest.is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    std::fs::remove_dir_all(&folder_path).unwrap();
+    assert_eq!(latest.unwrap().cycles, Some(1));
+    // A reload is only taken once
+    assert_eq!(watcher.take().unwrap().duration_pomodoro, 50);
+    assert!(watcher.take().is_none());
+    assert!(watcher.latest().is_none());
+}
+
+#[test]
+fn test_options_json_schema() {
+    let schema = options_json_schema();
+    assert!(schema["properties"]["endEventPomodoro"].is_object());
+    assert!(schema["$defs"]["EndEvent"]["oneOf"].is_array());
+}
+
+#[test]
+fn test_parse_duration_minutes() {
+    assert_eq!(parse_duration_minutes("1500").unwrap(), 25);
+    assert_eq!(parse_duration_minutes("25:00").unwrap(), 25);
+    assert_eq!(parse_duration_minutes(" 05:00 ").unwrap(), 5);
+    assert!(parse_duration_minutes("90").is_err());
+    assert!(parse_duration_minutes("25:30").is_err());
+    assert!(parse_duration_minutes("25:60").is_err());
+    assert!(parse_duration_minutes("25min").is_err());
+    assert!(parse_duration_minutes(&format!("{}:00", u64::MAX)).is_err());
+    assert!(parse_duration_minutes(&format!("{}:00", u64::MAX / 60)).is_err());
+}
+
+#[test]
+fn test_merge() {
+    let base = PomodoroOptions {
+        work_label: Some("Project A".to_string()),
+        ..Default::default()
+    };
+    let override_ = PartialPomodoroOptions {
+        duration_pomodoro: Some(50),
+        break_label: Some("Walk".to_string()),
+        ..Default::default()
+    };
+    let options = PomodoroOptions::merge(base, override_);
+    assert_eq!(options.duration_pomodoro, 50);
+    assert_eq!(options.duration_short_break, 5);
+    assert_eq!(options.work_label.as_deref(), Some("Project A"));
+    assert_eq!(options.break_label.as_deref(), Some("Walk"));
+    assert!(options.verify().is_ok());
+
+    // Merged durations out of range are rejected, e.g. from the remote control
+    for duration in [-5, MAX_DURATION_MINUTES + 1, i32::MAX] {
+        let override_ = PartialPomodoroOptions {
+            duration_short_break: Some(duration),
+            ..Default::default()
+        };
+        assert!(matches!(
+            PomodoroOptions::merge(options.clone(), override_).verify(),
+            Err(VerificationError::InvalidShortBreakDuration)
+        ));
+    }
+}
+
+#[test]
+fn test_diff() {
+    let options = PomodoroOptions {
+        cycles: Some(4),
+        ..Default::default()
+    };
+    assert!(options.diff(&options.clone()).is_empty());
+    let other = PomodoroOptions {
+        duration_pomodoro: 50,
+        work_label: Some("Project A".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        options.diff(&other),
+        vec![
+            ConfigDiff::FieldChanged {
+                field: "durationPomodoro",
+                old: "25".to_string(),
+                new: "50".to_string(),
+            },
+            ConfigDiff::FieldAdded { field: "workLabel" },
+            ConfigDiff::FieldRemoved { field: "cycles" },
+        ]
+    );
+    assert_eq!(
+        options.diff(&other)[0].to_string(),
+        "durationPomodoro changed from 25 to 50."
+    );
+
+    let with_token = PomodoroOptions {
+        end_event_pomodoro: serde_json::from_str(
+            r#"{"triggerHomeAssistant": {"baseUrl": "http://homeassistant.local:8123",
+                "token": "secret", "entityId": "light.desk", "service": "turn_off"}}"#,
+        )
+        .unwrap(),
+        ..Default::default()
+    };
+    assert!(with_token.is_sensitive_field("endEventPomodoro"));
+    assert!(!with_token.is_sensitive_field("durationPomodoro"));
+    assert!(!options.is_sensitive_field("endEventPomodoro"));
+}
+
+#[test]
+fn test_deserialize_lock_config() {
+    let options = serde_json::from_str::<PomodoroOptions>(r#"{"durationPomodoro": 50}"#).unwrap();
+    assert_eq!(
+        options.lock_config.poll_interval,
+        ContinuousLockConfig::default().poll_interval
+    );
+    let options = serde_json::from_str::<PomodoroOptions>(
+        r#"{"lockConfig": {"gracePeriodMillis": 30000, "retryConfig": {"maxRetries": 5}}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        options.lock_config.grace_period,
+        std::time::Duration::from_secs(30)
+    );
+    assert_eq!(options.lock_config.retry_config.max_retries, 5);
+    assert_eq!(
+        options.lock_config.linux_lock_priority,
+        DEFAULT_LINUX_LOCK_PRIORITY
+    );
+    assert!(options.verify().is_ok());
+    let options =
+        serde_json::from_str::<PomodoroOptions>(r#"{"lockConfig": {"pollIntervalMillis": 10}}"#)
+            .unwrap();
+    assert!(matches!(
+        options.verify(),
+        Err(VerificationError::InvalidLockPollInterval)
+    ));
+}
+
+#[test]
+fn test_to_redacted_json() {
+    let options = PomodoroOptions {
+        end_event_pomodoro: EndEvent::TriggerHomeAssistant {
+            base_url: "http://homeassistant.local:8123".to_string(),
+            token: "secret-token".to_string(),
+            entity_id: "light.desk".to_string(),
+            service: "turn_off".to_string(),
+            delay_before: None,
+        },
+        ..PomodoroOptions::default()
+    };
+    let json = options.to_redacted_json();
+    assert!(!json.contains("secret-token"));
+    assert!(json.contains(r#""endEventPomodoro": "<redacted>""#));
+    assert!(json.contains(r#""durationPomodoro": 25"#));
+}
+
+#[test]
+fn test_read_options_with_missing_sounds() {
+    let folder_path = env::temp_dir().join("locking_pomodoro_timer_test_missing_sounds");
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let file_path = folder_path.join("pomodoro_options.json");
+    let missing = || Some(PathBuf::from("does/not/exist.wav"));
+    let options = PomodoroOptions {
+        end_event_pomodoro: EndEvent::Sound {
+            filepath_sound: missing(),
+            backend: None,
+            sound_format: None,
+            completion_sound: missing(),
+            delay_before: None,
+        },
+        ..PomodoroOptions::default()
+    };
+    write_options_to_json(&file_path, &options).unwrap();
+    let result = read_options_from_json(Some(file_path));
+    std::fs::remove_dir_all(&folder_path).unwrap();
+
+    assert_eq!(
+        result.unwrap().end_event_pomodoro,
+        EndEvent::Sound {
+            filepath_sound: None,
+            backend: None,
+            sound_format: None,
+            completion_sound: None,
+            delay_before: None,
+        }
+    );
+}
+
+#[test]
+fn test_export_options() {
+    // The options printed by `--export-config` can be read again
+    let options = PomodoroOptions {
+        work_label: Some("Project A".to_string()),
+        ..PomodoroOptions::default()
+    };
+    let json = serde_json::to_value(&options).unwrap();
+    let exported = toml::to_string_pretty(&options).unwrap();
+    let imported: PomodoroOptions = toml::from_str(&exported).unwrap();
+    assert_eq!(serde_json::to_value(&imported).unwrap(), json);
+    let exported = serde_json::to_string_pretty(&options).unwrap();
+    let imported: PomodoroOptions = serde_json::from_str(&exported).unwrap();
+    assert_eq!(serde_json::to_value(&imported).unwrap(), json);
+}
+
+#[test]
+fn test_deserialize_linux_lock_priority() {
+    let options: PomodoroOptions = serde_json::from_str("{}").unwrap();
+    assert_eq!(options.linux_lock_priority, DEFAULT_LINUX_LOCK_PRIORITY);
+    let options: PomodoroOptions =
+        serde_json::from_str(r#"{"linuxLockPriority": ["swaylock", "i3lock", "xdgScreensaver"]}"#)
+            .unwrap();
+    assert_eq!(
+        options.linux_lock_priority,
+        [
+            LinuxLocker::Swaylock,
+            LinuxLocker::I3lock,
+            LinuxLocker::XdgScreensaver
+        ]
+    );
+    assert!(
+        serde_json::from_str::<PomodoroOptions>(r#"{"linuxLockPriority": ["xlock"]}"#).is_err()
+    );
+}
@@ -0,0 +1,182 @@
+//! This module defines the phases the Pomodoro timer alternates between.
+use crate::pomodoro_options::PomodoroOptions;
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// A phase of the Pomodoro timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PomodoroPhase {
+    /// A Pomodoro session to work in.
+    Work,
+    /// A short break between two Pomodoro sessions.
+    ShortBreak,
+    /// A long break after several Pomodoro sessions.
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    /// Checks if the phase is a short or long break.
+    #[must_use]
+    pub fn is_break(self) -> bool {
+        matches!(self, PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak)
+    }
+
+    /// Gets the name of the phase in plain words, e.g. `short break`.
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            PomodoroPhase::Work => "work",
+            PomodoroPhase::ShortBreak => "short break",
+            PomodoroPhase::LongBreak => "long break",
+        }
+    }
+}
+
+/// The phases in the order of their names in the error message of `UnknownPhaseError`.
+const PHASES: [PomodoroPhase; 3] = [
+    PomodoroPhase::Work,
+    PomodoroPhase::ShortBreak,
+    PomodoroPhase::LongBreak,
+];
+
+/// Error type for strings which are not the name of a `PomodoroPhase`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Unknown phase '{0}'. Expected one of: 'work', 'shortBreak', 'longBreak'.")]
+pub struct UnknownPhaseError(pub String);
+
+impl From<PomodoroPhase> for &'static str {
+    /// Gets the name of the phase in JSON and the history file, e.g. `shortBreak`.
+    fn from(phase: PomodoroPhase) -> Self {
+        match phase {
+            PomodoroPhase::Work => "work",
+            PomodoroPhase::ShortBreak => "shortBreak",
+            PomodoroPhase::LongBreak => "longBreak",
+        }
+    }
+}
+
+impl TryFrom<&str> for PomodoroPhase {
+    type Error = UnknownPhaseError;
+
+    /// Parses the phase from its name in JSON, e.g. `shortBreak`. The name is case-sensitive.
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        PHASES
+            .into_iter()
+            .find(|phase| <&str>::from(*phase) == name)
+            .ok_or_else(|| UnknownPhaseError(name.to_string()))
+    }
+}
+
+impl fmt::Display for PomodoroPhase {
+    /// Formats the phase as its name in the history file, e.g. `shortBreak`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str((*self).into())
+    }
+}
+
+/// A phase of the planned sequence of phases, see `plan_phases`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedPhase {
+    /// The phase.
+    pub phase: PomodoroPhase,
+    /// The number of the phase among the phases of the same kind, starting at 1.
+    pub number: u32,
+    /// The projected start of the phase.
+    pub start: DateTime<Local>,
+    /// The duration of the phase in seconds.
+    pub duration_secs: u64,
+}
+
+/// Plans the phases of the given number of cycles starting at `start`.
+///
+/// A cycle is a Pomodoro session and the following break, which is a long break after every
+/// `interval_long_break` sessions. The phases are assumed to start right after each other.
+#[must_use]
+pub fn plan_phases(
+    options: &PomodoroOptions,
+    cycles: u32,
+    start: DateTime<Local>,
+) -> Vec<PlannedPhase> {
+    let interval_long_break = options.interval_long_break.max(1) as u32;
+    let mut phases = Vec::new();
+    let mut start = start;
+    let mut push = |phase, number, minutes: i32| {
+        let duration_secs = u64::from(minutes.max(0) as u32) * 60;
+        phases.push(PlannedPhase {
+            phase,
+            number,
+            start,
+            duration_secs,
+        });
+        start += TimeDelta::seconds(duration_secs as i64);
+    };
+    let mut long_breaks = 0;
+    for cycle in 1..=cycles {
+        push(PomodoroPhase::Work, cycle, options.duration_pomodoro);
+        if cycle % interval_long_break == 0 {
+            long_breaks += 1;
+            push(
+                PomodoroPhase::LongBreak,
+                long_breaks,
+                options.duration_long_break,
+            );
+        } else {
+            push(
+                PomodoroPhase::ShortBreak,
+                cycle - long_breaks,
+                options.duration_short_break,
+            );
+        }
+    }
+    phases
+}
+
+#[test]
+fn test_phase_names() {
+    for phase in PHASES {
+        let name: &str = phase.into();
+        assert_eq!(PomodoroPhase::try_from(name), Ok(phase));
+        assert_eq!(
+            serde_json::to_string(&phase).unwrap(),
+            format!("\"{}\"", name)
+        );
+        assert_eq!(phase.to_string(), name);
+    }
+    assert_eq!(<&str>::from(PomodoroPhase::ShortBreak), "shortBreak");
+    let error = PomodoroPhase::try_from("short break").unwrap_err();
+    assert_eq!(error, UnknownPhaseError("short break".to_string()));
+    assert_eq!(
+        error.to_string(),
+        "Unknown phase 'short break'. Expected one of: 'work', 'shortBreak', 'longBreak'."
+    );
+}
+
+#[test]
+fn test_plan_phases() {
+    let options = PomodoroOptions {
+        interval_long_break: 2,
+        ..Default::default()
+    };
+    let start = Local::now();
+    let phases = plan_phases(&options, 3, start);
+    let kinds: Vec<_> = phases.iter().map(|p| (p.phase, p.number)).collect();
+    assert_eq!(
+        kinds,
+        [
+            (PomodoroPhase::Work, 1),
+            (PomodoroPhase::ShortBreak, 1),
+            (PomodoroPhase::Work, 2),
+            (PomodoroPhase::LongBreak, 1),
+            (PomodoroPhase::Work, 3),
+            (PomodoroPhase::ShortBreak, 2),
+        ]
+    );
+    assert_eq!(phases[0].start, start);
+    assert_eq!(phases[0].duration_secs, 25 * 60);
+    assert_eq!(phases[2].start, start + TimeDelta::minutes(30));
+    assert_eq!(phases[4].start, start + TimeDelta::minutes(70));
+}
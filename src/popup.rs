@@ -0,0 +1,117 @@
+//! This module shows the popup windows of the `PopupWindow` end event, which are harder to miss
+//! than notifications.
+//!
+//! On Linux, the popup window is shown with `zenity` on X11 and Wayland. On macOS, it is shown
+//! with `display dialog` of AppleScript and on Windows with `WScript.Shell.Popup` of PowerShell.
+//! If no popup window can be shown, e.g. on a headless system, the message is printed instead.
+use std::process::Command;
+
+/// The exit code of `zenity` if the popup window was closed after the timeout.
+const ZENITY_TIMEOUT_EXIT_CODE: i32 = 5;
+
+/// Shows a popup window with the title and message and waits until it is closed or the timeout
+/// expired.
+///
+/// Prints the message to the terminal if no popup window can be shown.
+///
+/// # Arguments
+/// * `title` - The title of the popup window.
+/// * `message` - The message of the popup window.
+/// * `timeout_secs` - The seconds after which the popup window is closed automatically, or
+///   `None` to wait until it is closed by the user.
+pub fn show_popup(title: &str, message: &str, timeout_secs: Option<u32>) {
+    let shown = if cfg!(target_os = "linux") {
+        has_display() && show_with_zenity(title, message, timeout_secs)
+    } else if cfg!(target_os = "macos") {
+        show_with_osascript(title, message, timeout_secs)
+    } else if cfg!(target_os = "windows") {
+        show_with_powershell(title, message, timeout_secs)
+    } else {
+        false
+    };
+    if !shown {
+        log::debug!("Failed to show the popup window, printing its message instead.");
+        println!("{}: {}", title, message);
+    }
+}
+
+/// Checks whether a graphical session is running, i.e. `DISPLAY` or `WAYLAND_DISPLAY` is set.
+fn has_display() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .into_iter()
+        .any(|variable| std::env::var_os(variable).is_some_and(|value| !value.is_empty()))
+}
+
+/// Shows the popup window with `zenity`. The message is shown as plain text.
+fn show_with_zenity(title: &str, message: &str, timeout_secs: Option<u32>) -> bool {
+    let result = Command::new("zenity")
+        .args(zenity_args(title, message, timeout_secs))
+        .output();
+    // The exit code is 1 if the window was closed without pressing OK
+    match result {
+        Ok(output) => matches!(output.status.code(), Some(0 | 1 | ZENITY_TIMEOUT_EXIT_CODE)),
+        Err(_) => false,
+    }
+}
+
+/// Gets the arguments of `zenity` for the popup window.
+fn zenity_args(title: &str, message: &str, timeout_secs: Option<u32>) -> Vec<String> {
+    let mut args = vec![
+        "--info".to_string(),
+        "--no-markup".to_string(),
+        format!("--title={}", title),
+        format!("--text={}", message),
+    ];
+    if let Some(timeout_secs) = timeout_secs {
+        args.push(format!("--timeout={}", timeout_secs));
+    }
+    args
+}
+
+/// Shows the popup window with `osascript`. The title and message are passed as environment
+/// variables to prevent them from being interpreted as code.
+fn show_with_osascript(title: &str, message: &str, timeout_secs: Option<u32>) -> bool {
+    let mut script = "display dialog (system attribute \"POMODORO_POPUP_MESSAGE\") \
+        with title (system attribute \"POMODORO_POPUP_TITLE\") buttons {\"OK\"} default button \"OK\""
+        .to_string();
+    if let Some(timeout_secs) = timeout_secs {
+        script.push_str(&format!(" giving up after {}", timeout_secs));
+    }
+    let result = Command::new("osascript")
+        .args(["-e", &script])
+        .env("POMODORO_POPUP_TITLE", title)
+        .env("POMODORO_POPUP_MESSAGE", message)
+        .output();
+    matches!(result, Ok(output) if output.status.success())
+}
+
+/// Shows the popup window with PowerShell. The title and message are passed as environment
+/// variables to prevent them from being interpreted as code.
+fn show_with_powershell(title: &str, message: &str, timeout_secs: Option<u32>) -> bool {
+    // A timeout of 0 waits until the window is closed, 64 shows the information icon
+    let script = format!(
+        "(New-Object -ComObject WScript.Shell).Popup($env:POMODORO_POPUP_MESSAGE, {}, $env:POMODORO_POPUP_TITLE, 64)",
+        timeout_secs.unwrap_or(0)
+    );
+    let result = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .env("POMODORO_POPUP_TITLE", title)
+        .env("POMODORO_POPUP_MESSAGE", message)
+        .output();
+    matches!(result, Ok(output) if output.status.success())
+}
+
+#[test]
+fn test_zenity_args() {
+    assert_eq!(
+        zenity_args("Break", "Time for a <break>!", Some(30)),
+        vec![
+            "--info",
+            "--no-markup",
+            "--title=Break",
+            "--text=Time for a <break>!",
+            "--timeout=30",
+        ]
+    );
+    assert_eq!(zenity_args("Break", "", None).len(), 4);
+}
@@ -0,0 +1,180 @@
+//! This module contains the handle to control a running Pomodoro timer from another thread,
+//! e.g. by the JSON-RPC server of the `server` feature or a GUI.
+//!
+//! The commands have the same effect as the keys in the terminal, e.g. `Pause` like `p`.
+use crate::pomodoro_options::PartialPomodoroOptions;
+use crate::pomodoro_phase::PomodoroPhase;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// A command to control the timer remotely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteCommand {
+    /// Start the next Pomodoro session or break the timer waits for, like the enter key.
+    Start,
+    /// Stop the current Pomodoro session or break without its end event, like `q`.
+    Stop,
+    /// Pause the current Pomodoro session or break, like `p`.
+    Pause,
+    /// Resume the current Pomodoro session or break, like `r`.
+    Resume,
+    /// Skip to the end of the current Pomodoro session or break and start its end event, like
+    /// `e`. The phase is recorded as skipped and not completed.
+    SkipToEnd,
+}
+
+impl RemoteCommand {
+    /// Gets the input of the key with the same effect.
+    fn as_input(self) -> &'static str {
+        match self {
+            RemoteCommand::Start => "\n",
+            RemoteCommand::Stop => "q",
+            RemoteCommand::Pause => "p",
+            RemoteCommand::Resume => "r",
+            RemoteCommand::SkipToEnd => "e",
+        }
+    }
+}
+
+/// The state of the timer as reported by `RemoteControl::status`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerStatus {
+    /// The running phase, or `None` if the timer waits for the next phase or isn't started.
+    pub phase: Option<PomodoroPhase>,
+    /// The duration of the running phase in seconds.
+    pub duration_secs: u64,
+    /// The remaining time of the running phase in seconds.
+    pub remaining_secs: u64,
+    /// Whether the running phase is paused.
+    pub paused: bool,
+    /// The number of finished cycles of a Pomodoro session and its break.
+    pub completed_cycles: u32,
+}
+
+/// The state shared between the timer and the `RemoteControl` handles.
+#[derive(Debug, Default)]
+struct RemoteState {
+    inputs: Option<Sender<String>>,
+    status: TimerStatus,
+    options: Vec<PartialPomodoroOptions>,
+}
+
+/// A handle to control the timer started with `start_pomodoro_with_remote_control` from
+/// another thread. Clones control the same timer.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteControl {
+    state: Arc<Mutex<RemoteState>>,
+}
+
+impl RemoteControl {
+    /// Creates a new handle which is not connected to a timer yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the command to the timer.
+    ///
+    /// # Returns
+    /// `false` if the timer isn't started or has finished.
+    pub fn send(&self, command: RemoteCommand) -> bool {
+        log::debug!("Received remote command: {:?}", command);
+        self.lock()
+            .inputs
+            .as_ref()
+            .is_some_and(|inputs| inputs.send(command.as_input().to_string()).is_ok())
+    }
+
+    /// Gets the current state of the timer.
+    #[must_use]
+    pub fn status(&self) -> TimerStatus {
+        self.lock().status.clone()
+    }
+
+    /// Changes the options, which take effect at the start of the next Pomodoro session,
+    /// additional Pomodoro or break like the options reloaded with `--watch-config`.
+    /// Options which are `None` are not changed.
+    pub fn configure(&self, options: PartialPomodoroOptions) {
+        self.lock().options.push(options);
+    }
+
+    /// Connects the handle to the input channel of the timer.
+    pub(crate) fn connect(&self, inputs: Sender<String>) {
+        self.lock().inputs = Some(inputs);
+    }
+
+    /// Disconnects the handle from the timer when it finished.
+    pub(crate) fn disconnect(&self) {
+        let mut state = self.lock();
+        state.inputs = None;
+        state.status.phase = None;
+    }
+
+    /// Takes the options changed with `configure` since the last call in the order they were given.
+    pub(crate) fn take_options(&self) -> Vec<PartialPomodoroOptions> {
+        std::mem::take(&mut self.lock().options)
+    }
+
+    /// Reports that the phase with the duration started.
+    pub(crate) fn start_phase(&self, phase: PomodoroPhase, duration: Duration) {
+        let status = &mut self.lock().status;
+        status.phase = Some(phase);
+        status.duration_secs = duration.as_secs();
+        status.remaining_secs = duration.as_secs();
+        status.paused = false;
+    }
+
+    /// Reports the remaining time of the running phase and whether it is paused.
+    pub(crate) fn update_phase(&self, remaining: Duration, paused: bool) {
+        let status = &mut self.lock().status;
+        status.remaining_secs = remaining.as_secs();
+        status.paused = paused;
+    }
+
+    /// Reports that the running phase ended and the timer waits for the next one.
+    pub(crate) fn end_phase(&self) {
+        let status = &mut self.lock().status;
+        status.phase = None;
+        status.remaining_secs = 0;
+        status.paused = false;
+    }
+
+    /// Reports the number of finished cycles.
+    pub(crate) fn set_completed_cycles(&self, completed_cycles: u32) {
+        self.lock().status.completed_cycles = completed_cycles;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RemoteState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[test]
+fn test_remote_control() {
+    let remote = RemoteControl::new();
+    assert!(!remote.send(RemoteCommand::Pause));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    remote.connect(sender);
+    assert!(remote.clone().send(RemoteCommand::Pause));
+    assert_eq!(receiver.try_recv().unwrap(), "p");
+    assert!(remote.send(RemoteCommand::SkipToEnd));
+    assert_eq!(receiver.try_recv().unwrap(), "e");
+
+    remote.start_phase(PomodoroPhase::ShortBreak, Duration::from_secs(300));
+    remote.update_phase(Duration::from_secs(200), true);
+    let status = remote.status();
+    assert_eq!(status.phase, Some(PomodoroPhase::ShortBreak));
+    assert_eq!((status.remaining_secs, status.paused), (200, true));
+
+    remote.configure(PartialPomodoroOptions {
+        cycles: Some(2),
+        ..Default::default()
+    });
+    assert_eq!(remote.take_options()[0].cycles, Some(2));
+    assert!(remote.take_options().is_empty());
+    remote.disconnect();
+    assert!(!remote.send(RemoteCommand::Start));
+}
@@ -0,0 +1,2159 @@
+//! This module contains the functions to lock the screen and to keep it locked during a break.
+//!
+//! The screen is locked with the commands available on the respective platform.
+//! On Linux, several commands are tried in order until one of them succeeds.
+//! The order can be configured with a list of `LinuxLocker`s.
+use crate::clock::Instant;
+use crate::clock::{current_clock, Clock};
+use crate::sound::play_sound;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Error type for failures to lock the screen.
+#[derive(Error, Debug)]
+#[error(
+    "Failed to lock the screen on {platform}. Attempted commands: {}.",
+    attempted_commands.join(", ")
+)]
+pub struct LockError {
+    /// The platform on which locking the screen failed.
+    pub platform: &'static str,
+    /// The commands which were attempted to lock the screen.
+    pub attempted_commands: Vec<String>,
+    /// The error of the last attempted command.
+    #[source]
+    pub last_error: io::Error,
+}
+
+/// Configuration of the retries if locking the screen fails.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LockRetryConfig {
+    /// The number of times the lock commands are retried after all of them failed.
+    pub max_retries: u32,
+    /// The time to wait between two attempts, in milliseconds in the options file.
+    #[serde(rename = "retryDelayMillis", with = "duration_millis")]
+    #[schemars(with = "u64")]
+    pub retry_delay: Duration,
+}
+
+impl Default for LockRetryConfig {
+    /// Creates a new `LockRetryConfig` instance with two retries one second apart.
+    fn default() -> Self {
+        LockRetryConfig {
+            max_retries: 2,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The screen lockers available on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LinuxLocker {
+    /// `loginctl lock-session`, works on most modern Linux distributions with systemd.
+    Loginctl,
+    /// `gnome-screensaver-command -l`.
+    GnomeScreensaver,
+    /// `cinnamon-screensaver-command -l`, for Cinnamon. Only tried when running on Cinnamon
+    /// or if the command is installed.
+    Cinnamon,
+    /// `mate-screensaver-command --lock`, for MATE. Only tried when running on MATE or if the
+    /// command is installed.
+    Mate,
+    /// `xfce4-screensaver-command -l`, for Xfce. Only tried when running on Xfce or if the
+    /// command is installed.
+    Xfce,
+    /// `dm-tool lock`, for the LightDM display manager. Only tried in a session of LightDM.
+    #[serde(rename = "lightdm")]
+    LightDm,
+    /// The `org.gnome.ScreenSaver.Lock` method via `dbus-send`, works for GNOME and KDE.
+    #[serde(rename = "dbus")]
+    DBus,
+    /// `qdbus6 org.freedesktop.ScreenSaver /ScreenSaver Lock`, for KDE Plasma 6.
+    Qdbus6,
+    /// `qdbus org.freedesktop.ScreenSaver /ScreenSaver Lock`, for KDE Plasma 5.
+    Qdbus,
+    /// `swaylock -f`. Shows the background image of the lock screen if given. Only tried in a
+    /// Wayland session.
+    Swaylock,
+    /// `i3lock`. Shows the background image of the lock screen if given. Only tried in an X11
+    /// session.
+    I3lock,
+    /// `xdg-screensaver lock`.
+    XdgScreensaver,
+    /// `hyprctl dispatch dpms off`. Only tried when running on Hyprland.
+    Hyprland,
+    /// `niri msg action power-off-monitors`. Only tried when running on niri.
+    Niri,
+    /// `loginctl lock-session`, which the session lock of COSMIC listens to.
+    /// Only tried when running on COSMIC.
+    Cosmic,
+}
+
+/// The default order in which the lockers are tried on Linux.
+pub const DEFAULT_LINUX_LOCK_PRIORITY: &[LinuxLocker] = &[
+    LinuxLocker::Hyprland,
+    LinuxLocker::Niri,
+    LinuxLocker::Cosmic,
+    LinuxLocker::Loginctl,
+    LinuxLocker::GnomeScreensaver,
+    LinuxLocker::Cinnamon,
+    LinuxLocker::Mate,
+    LinuxLocker::Xfce,
+    LinuxLocker::LightDm,
+    LinuxLocker::Qdbus6,
+    LinuxLocker::Qdbus,
+    LinuxLocker::Swaylock,
+    LinuxLocker::I3lock,
+    LinuxLocker::DBus,
+];
+
+impl LinuxLocker {
+    /// Checks if the locker can be used in the current session.
+    ///
+    /// The compositor and desktop lockers are only available when running on the respective compositor or desktop.
+    fn is_available(self) -> bool {
+        self.is_available_with(|name| std::env::var_os(name).is_some())
+    }
+
+    /// Checks if the locker can be used like `is_available` with the function checking whether
+    /// an environment variable is set.
+    fn is_available_with(self, is_set: impl Fn(&str) -> bool) -> bool {
+        match self {
+            LinuxLocker::Hyprland => is_set("HYPRLAND_INSTANCE_SIGNATURE"),
+            LinuxLocker::Niri => is_set("NIRI_SOCKET"),
+            LinuxLocker::Cosmic => is_set("COSMIC_SESSION_SOCK"),
+            LinuxLocker::Cinnamon | LinuxLocker::Mate | LinuxLocker::Xfce => {
+                self.runs_on_desktop(&current_desktop()) || is_program_in_path(self.command().0)
+            }
+            LinuxLocker::LightDm => is_set("XDG_SEAT_PATH"),
+            LinuxLocker::Swaylock => is_set("WAYLAND_DISPLAY"),
+            // XWayland sets `DISPLAY` as well, but i3lock cannot lock a Wayland session
+            LinuxLocker::I3lock => is_set("DISPLAY") && !is_set("WAYLAND_DISPLAY"),
+            _ => true,
+        }
+    }
+
+    /// Gets the names of the desktops in `XDG_CURRENT_DESKTOP` the locker belongs to.
+    fn desktop_names(self) -> &'static [&'static str] {
+        match self {
+            LinuxLocker::Cinnamon => &["Cinnamon", "X-Cinnamon"],
+            LinuxLocker::Mate => &["MATE"],
+            LinuxLocker::Xfce => &["XFCE"],
+            _ => &[],
+        }
+    }
+
+    /// Checks if the locker belongs to one of the desktops of `XDG_CURRENT_DESKTOP`, a
+    /// colon-separated list of desktop names, e.g. `X-Cinnamon`.
+    fn runs_on_desktop(self, xdg_current_desktop: &str) -> bool {
+        xdg_current_desktop
+            .split(':')
+            .any(|desktop| self.desktop_names().contains(&desktop))
+    }
+
+    /// Checks if the locker can show a custom background image with `--image`.
+    fn supports_background_image(self) -> bool {
+        matches!(self, LinuxLocker::Swaylock | LinuxLocker::I3lock)
+    }
+
+    /// Gets the option with which the locker shows a message on the lock screen, i.e.
+    /// `swaylock --message` and `i3lock -t`, or `None` if it cannot show one.
+    fn message_option(self) -> Option<&'static str> {
+        match self {
+            LinuxLocker::Swaylock => Some("--message"),
+            LinuxLocker::I3lock => Some("-t"),
+            _ => None,
+        }
+    }
+
+    /// Gets the program and the arguments to lock the screen.
+    fn command(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            LinuxLocker::Loginctl => ("loginctl", &["lock-session"]),
+            LinuxLocker::GnomeScreensaver => ("gnome-screensaver-command", &["-l"]),
+            LinuxLocker::Cinnamon => ("cinnamon-screensaver-command", &["-l"]),
+            LinuxLocker::Mate => ("mate-screensaver-command", &["--lock"]),
+            LinuxLocker::Xfce => ("xfce4-screensaver-command", &["-l"]),
+            LinuxLocker::LightDm => ("dm-tool", &["lock"]),
+            LinuxLocker::DBus => (
+                "dbus-send",
+                &[
+                    "--type=method_call",
+                    "--dest=org.gnome.ScreenSaver",
+                    "/org/gnome/ScreenSaver",
+                    "org.gnome.ScreenSaver.Lock",
+                ],
+            ),
+            LinuxLocker::Qdbus6 => (
+                "qdbus6",
+                &["org.freedesktop.ScreenSaver", "/ScreenSaver", "Lock"],
+            ),
+            LinuxLocker::Qdbus => (
+                "qdbus",
+                &["org.freedesktop.ScreenSaver", "/ScreenSaver", "Lock"],
+            ),
+            LinuxLocker::Swaylock => ("swaylock", &["-f"]),
+            LinuxLocker::I3lock => ("i3lock", &[]),
+            LinuxLocker::XdgScreensaver => ("xdg-screensaver", &["lock"]),
+            LinuxLocker::Hyprland => ("hyprctl", &["dispatch", "dpms", "off"]),
+            LinuxLocker::Niri => ("niri", &["msg", "action", "power-off-monitors"]),
+            LinuxLocker::Cosmic => ("loginctl", &["lock-session"]),
+        }
+    }
+}
+
+/// Locks the screen.
+///
+/// A browser cannot lock the screen, so with the `wasm` feature on `wasm32` this only logs that
+/// the screen stays unlocked and succeeds.
+///
+/// # Errors
+///
+/// Returns a `LockError` if the screen could not be locked.
+pub fn lock_screen() -> Result<(), LockError> {
+    lock_screen_with_retries(&LockRetryConfig::default())
+}
+
+/// Locks the screen and retries according to the given configuration if locking fails.
+///
+/// # Errors
+///
+/// Returns a `LockError` if all attempts failed.
+pub fn lock_screen_with_retries(retry_config: &LockRetryConfig) -> Result<(), LockError> {
+    lock_screen_with_priority(retry_config, DEFAULT_LINUX_LOCK_PRIORITY)
+}
+
+/// Locks the screen and retries according to the given configuration if locking fails.
+///
+/// On Linux, the lockers are tried in the order of `linux_lock_priority`.
+///
+/// # Errors
+///
+/// Returns a `LockError` if all attempts failed or screen locking is not implemented for this platform.
+pub fn lock_screen_with_priority(
+    retry_config: &LockRetryConfig,
+    linux_lock_priority: &[LinuxLocker],
+) -> Result<(), LockError> {
+    lock_screen_with_background_image(retry_config, linux_lock_priority, None)
+}
+
+/// Locks the screen like `lock_screen_with_priority` and shows the background image on the lock screen.
+///
+/// Only `swaylock` and `i3lock` support a background image, which is passed to them with `--image`.
+/// If another locker is used or the platform is not Linux, a warning is printed and the default
+/// lock screen is shown.
+///
+/// # Errors
+///
+/// Returns a `LockError` if all attempts failed or screen locking is not implemented for this platform.
+pub fn lock_screen_with_background_image(
+    retry_config: &LockRetryConfig,
+    linux_lock_priority: &[LinuxLocker],
+    background_image: Option<&Path>,
+) -> Result<(), LockError> {
+    lock_screen_with_message(retry_config, linux_lock_priority, background_image, None)
+}
+
+/// Locks the screen like `lock_screen_with_background_image` and shows the message on the lock
+/// screen.
+///
+/// The message is passed to `swaylock --message` and `i3lock -t`. If the locker fails with the
+/// message, e.g. because the installed build has no such option, it is run again without it.
+/// For the other lockers and platforms, the message is shown with `show_lock_message`.
+///
+/// # Errors
+///
+/// Returns a `LockError` if all attempts failed or screen locking is not implemented for this platform.
+pub fn lock_screen_with_message(
+    retry_config: &LockRetryConfig,
+    linux_lock_priority: &[LinuxLocker],
+    background_image: Option<&Path>,
+    message: Option<&str>,
+) -> Result<(), LockError> {
+    if cfg!(all(feature = "wasm", target_arch = "wasm32")) {
+        log::info!("The screen is not locked as it cannot be locked in the browser.");
+        return Ok(());
+    }
+    if background_image.is_some() && !cfg!(target_os = "linux") {
+        log::warn!(
+            "Custom lock screen images are not supported on this platform. Showing the default lock screen."
+        );
+    } else if background_image.is_some()
+        && !linux_lock_priority
+            .iter()
+            .any(|locker| locker.supports_background_image())
+    {
+        log::warn!(
+            "None of the lockers of `linuxLockPriority` supports a custom lock screen image. Add `swaylock` or `i3lock` to show it."
+        );
+    }
+    if let Some(message) = message.filter(|_| !cfg!(target_os = "linux")) {
+        show_lock_message(message);
+    }
+    if cfg!(windows) {
+        retry_lock(retry_config, lock_screen_on_windows)
+    } else if cfg!(target_os = "linux") {
+        retry_lock(retry_config, || {
+            try_lock_screen_on_linux(linux_lock_priority, background_image, message)
+        })
+    } else if cfg!(target_os = "macos") {
+        retry_lock(retry_config, lock_screen_on_macos)
+    } else {
+        Err(unsupported_platform_error())
+    }
+}
+
+/// Does a dry run of locking the screen like `lock_screen_with_priority`.
+///
+/// The lock commands are chosen and tried in the same order, but instead of running them, it is
+/// only checked whether their programs are in the `PATH`. Nothing is locked and no retries are
+/// done.
+///
+/// Returns the command the screen would be locked with.
+///
+/// # Errors
+///
+/// Returns a `LockError` with the attempted commands if none of their programs is found, or if
+/// screen locking is not implemented for this platform.
+pub fn lock_screen_dry_run(linux_lock_priority: &[LinuxLocker]) -> Result<String, LockError> {
+    let runner = DryRunCommandRunner::default();
+    if cfg!(windows) {
+        lock_screen_on_windows_with(&runner)?;
+    } else if cfg!(target_os = "linux") {
+        try_lock_screen_on_linux_with(&runner, linux_lock_priority, None, None)?;
+    } else if cfg!(target_os = "macos") {
+        lock_screen_on_macos_with(&runner)?;
+    } else {
+        return Err(unsupported_platform_error());
+    }
+    Ok(runner.command.take().unwrap_or_default())
+}
+
+/// Creates the error returned on platforms on which screen locking is not implemented.
+fn unsupported_platform_error() -> LockError {
+    LockError {
+        platform: std::env::consts::OS,
+        attempted_commands: Vec::new(),
+        last_error: io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Screen locking is not implemented for this platform.",
+        ),
+    }
+}
+
+/// Locks the screen like `lock_screen_with_message` and prints a warning if it fails.
+pub(crate) fn lock_screen_or_warn(
+    retry_config: &LockRetryConfig,
+    linux_lock_priority: &[LinuxLocker],
+    background_image: Option<&Path>,
+    message: Option<&str>,
+) {
+    if let Err(e) =
+        lock_screen_with_message(retry_config, linux_lock_priority, background_image, message)
+    {
+        log::warn!("{}", lock_failure_warning(&e));
+    }
+}
+
+/// Creates the warning printed if the screen could not be locked.
+pub(crate) fn lock_failure_warning(error: &LockError) -> String {
+    format!("{} Last error: {}", error, error.last_error)
+}
+
+/// Runs the commands to lock the screen.
+///
+/// The lock functions run their commands through this trait so that tests can check which commands
+/// are run in which order without locking the screen.
+pub(crate) trait CommandRunner {
+    /// Runs the program with the arguments and waits until it finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program cannot be started or exits with a failure.
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<()>;
+
+    /// Checks if the locker can be used in the current session, see `LinuxLocker::is_available`.
+    fn is_locker_available(&self, locker: LinuxLocker) -> bool {
+        locker.is_available()
+    }
+}
+
+/// Runs the commands as processes of the operating system.
+struct SystemCommandRunner;
+
+/// Records the command which succeeds first instead of running it, see `lock_screen_dry_run`.
+///
+/// A command succeeds if its program is in the `PATH`.
+#[derive(Default)]
+struct DryRunCommandRunner {
+    /// The first command whose program was found.
+    command: RefCell<Option<String>>,
+}
+
+impl CommandRunner for DryRunCommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<()> {
+        if !is_program_in_path(program) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("`{}` is not in the PATH", program),
+            ));
+        }
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.command.borrow_mut().get_or_insert(command);
+        Ok(())
+    }
+}
+
+impl CommandRunner for SystemCommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<()> {
+        let output = std::process::Command::new(program).args(args).output()?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Err(io::Error::other(format!(
+            "`{}` failed with {}",
+            command, output.status
+        )))
+    }
+}
+
+/// Calls the lock function until it succeeds or `max_retries` retries failed, waiting `retry_delay` in between.
+///
+/// # Errors
+///
+/// Returns the `LockError` of the last attempt if all attempts failed.
+fn retry_lock(
+    retry_config: &LockRetryConfig,
+    lock: impl Fn() -> Result<(), LockError>,
+) -> Result<(), LockError> {
+    let mut retries = 0;
+    loop {
+        match lock() {
+            Ok(()) => return Ok(()),
+            Err(e) if retries >= retry_config.max_retries => return Err(e),
+            Err(e) => {
+                retries += 1;
+                log::debug!("{} Retrying ({}/{}).", e, retries, retry_config.max_retries);
+                thread::sleep(retry_config.retry_delay);
+            }
+        }
+    }
+}
+
+/// Finds the command which would be used to lock the screen without running it, see
+/// `lock_screen_dry_run`.
+///
+/// On Linux, the first available locker of `linux_lock_priority` whose program is found
+/// in the `PATH` is returned.
+///
+/// Returns `None` if no lock command is available.
+#[must_use]
+pub fn find_lock_command(linux_lock_priority: &[LinuxLocker]) -> Option<String> {
+    lock_screen_dry_run(linux_lock_priority).ok()
+}
+
+/// Gets the value of `XDG_CURRENT_DESKTOP`, or an empty string if it is not set.
+fn current_desktop() -> String {
+    std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default()
+}
+
+/// Orders the lockers so that the lockers of the current desktop are tried first, e.g. MATE's
+/// screensaver on MATE. The other lockers keep the order of `linux_lock_priority`.
+fn lockers_for_desktop(
+    linux_lock_priority: &[LinuxLocker],
+    xdg_current_desktop: &str,
+) -> Vec<LinuxLocker> {
+    let (desktop, generic): (Vec<_>, Vec<_>) = linux_lock_priority
+        .iter()
+        .copied()
+        .partition(|locker| locker.runs_on_desktop(xdg_current_desktop));
+    desktop.into_iter().chain(generic).collect()
+}
+
+/// Checks if the program is found in one of the directories of the `PATH`.
+fn is_program_in_path(program: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|directory| {
+        let path = directory.join(program);
+        path.is_file() || (cfg!(windows) && path.with_extension("exe").is_file())
+    })
+}
+
+/// The program and the arguments to lock the screen on Windows.
+const WINDOWS_LOCK_COMMAND: (&str, &[&str]) =
+    ("cmd", &["/C", "rundll32", "user32.dll,LockWorkStation"]);
+
+/// The program and the arguments to lock the screen on macOS.
+const MACOS_LOCK_COMMAND: (&str, &[&str]) = ("pmset", &["displaysleepnow"]);
+
+/// Locks the screen on Windows.
+///
+/// # Errors
+///
+/// Returns a `LockError` if the lock command failed.
+pub fn lock_screen_on_windows() -> Result<(), LockError> {
+    lock_screen_on_windows_with(&SystemCommandRunner)
+}
+
+/// Locks the screen on Windows with the commands run by the runner.
+fn lock_screen_on_windows_with(runner: &dyn CommandRunner) -> Result<(), LockError> {
+    // Turn on the screen saver for windows and lock the screen.
+    let mut attempts = LockAttempts::new(runner);
+    let (program, args) = WINDOWS_LOCK_COMMAND;
+    if attempts.run(program, args) {
+        return Ok(());
+    }
+    Err(attempts.into_error("Windows"))
+}
+
+/// Locks the screen on Linux.
+///
+/// Tries the lockers in the order of `linux_lock_priority` until one of them succeeds. If all of them fail,
+/// the whole chain is retried up to `max_retries` times, waiting `retry_delay` in between.
+///
+/// # Errors
+///
+/// Returns a `LockError` if all attempts failed.
+pub fn lock_screen_on_linux(
+    retry_config: &LockRetryConfig,
+    linux_lock_priority: &[LinuxLocker],
+) -> Result<(), LockError> {
+    retry_lock(retry_config, || {
+        try_lock_screen_on_linux(linux_lock_priority, None, None)
+    })
+}
+
+/// Tries the lockers on Linux once in the given order until one of them succeeds.
+///
+/// The background image and the message are passed to the lockers which support them. The
+/// message is shown with `show_lock_message` if the locker which succeeded doesn't support it.
+///
+/// # Errors
+///
+/// Returns a `LockError` if all lockers failed.
+fn try_lock_screen_on_linux(
+    linux_lock_priority: &[LinuxLocker],
+    background_image: Option<&Path>,
+    message: Option<&str>,
+) -> Result<(), LockError> {
+    try_lock_screen_on_linux_with(
+        &SystemCommandRunner,
+        linux_lock_priority,
+        background_image,
+        message,
+    )
+}
+
+/// Tries the lockers on Linux like `try_lock_screen_on_linux` with the commands run by the runner.
+fn try_lock_screen_on_linux_with(
+    runner: &dyn CommandRunner,
+    linux_lock_priority: &[LinuxLocker],
+    background_image: Option<&Path>,
+    message: Option<&str>,
+) -> Result<(), LockError> {
+    let mut attempts = LockAttempts::new(runner);
+    for locker in lockers_for_desktop(linux_lock_priority, &current_desktop()) {
+        if !runner.is_locker_available(locker) {
+            continue;
+        }
+        let (program, args) = locker.command();
+        let image = background_image
+            .filter(|_| locker.supports_background_image())
+            .map(Path::to_string_lossy);
+        let mut args = args.to_vec();
+        if let Some(image) = &image {
+            args.extend(["--image", image]);
+        }
+        if let Some((message, option)) = message.zip(locker.message_option()) {
+            let mut message_args = args.clone();
+            message_args.extend([option, message]);
+            if attempts.run(program, &message_args) {
+                return Ok(());
+            }
+        }
+        if attempts.run(program, &args) {
+            if background_image.is_some() && image.is_none() {
+                log::warn!(
+                    "`{}` does not support a custom lock screen image. Showing the default lock screen.",
+                    program
+                );
+            }
+            if let Some(message) = message {
+                show_lock_message_with(runner, message);
+            }
+            return Ok(());
+        }
+    }
+    Err(attempts.into_error("Linux"))
+}
+
+/// Records the commands attempted to lock the screen and the error of the last one.
+struct LockAttempts<'a> {
+    runner: &'a dyn CommandRunner,
+    attempted_commands: Vec<String>,
+    last_error: Option<io::Error>,
+}
+
+impl Default for LockAttempts<'_> {
+    /// Creates a new `LockAttempts` instance which runs the commands as processes.
+    fn default() -> Self {
+        LockAttempts::new(&SystemCommandRunner)
+    }
+}
+
+impl<'a> LockAttempts<'a> {
+    /// Creates a new `LockAttempts` instance which runs the commands with the runner.
+    fn new(runner: &'a dyn CommandRunner) -> Self {
+        LockAttempts {
+            runner,
+            attempted_commands: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    /// Runs the lock command and records it.
+    ///
+    /// Returns `true` if the command succeeded.
+    fn run(&mut self, program: &str, args: &[&str]) -> bool {
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let Err(error) = self.runner.run(program, args) else {
+            return true;
+        };
+        log::debug!("Lock command `{}` failed: {}", command, error);
+        self.attempted_commands.push(command);
+        self.last_error = Some(error);
+        false
+    }
+
+    /// Converts the recorded attempts into a `LockError` for the given platform.
+    fn into_error(self, platform: &'static str) -> LockError {
+        LockError {
+            platform,
+            attempted_commands: self.attempted_commands,
+            last_error: self
+                .last_error
+                .unwrap_or_else(|| io::Error::other("No lock command was attempted.")),
+        }
+    }
+}
+
+/// Locks the screen on macOS.
+///
+/// Running `caffeinate` processes can prevent the display from going to sleep, so they should
+/// be stopped with `SuspendedCaffeinate::suspend` for the break.
+///
+/// # Errors
+///
+/// Returns a `LockError` if the lock command failed.
+pub fn lock_screen_on_macos() -> Result<(), LockError> {
+    lock_screen_on_macos_with(&SystemCommandRunner)
+}
+
+/// The `caffeinate` processes of the current user stopped for a break on macOS, which are
+/// restarted with the same command lines when the value is dropped.
+#[must_use = "the processes are restarted when the value is dropped"]
+#[derive(Debug, Default)]
+pub struct SuspendedCaffeinate {
+    commands: Vec<Vec<String>>,
+}
+
+impl SuspendedCaffeinate {
+    /// Stops the `caffeinate` processes of the current user with `pkill -u <uid> caffeinate`
+    /// on macOS, so that the display can go to sleep when the screen is locked. Does nothing
+    /// on other platforms.
+    pub fn suspend() -> Self {
+        if !cfg!(target_os = "macos") {
+            return Self::default();
+        }
+        let Some(uid) = command_stdout("id", &["-u"]) else {
+            log::debug!("Failed to determine the user id to stop 'caffeinate'.");
+            return Self::default();
+        };
+        let uid = uid.trim();
+        let Some(processes) = command_stdout("pgrep", &["-u", uid, "-l", "-f", "caffeinate"])
+        else {
+            return Self::default();
+        };
+        let commands = parse_caffeinate_processes(&processes);
+        if commands.is_empty() {
+            return Self::default();
+        }
+        match std::process::Command::new("pkill")
+            .args(["-u", uid, "caffeinate"])
+            .status()
+        {
+            Ok(status) if status.success() => {
+                log::info!(
+                    "Stopped {} 'caffeinate' processes to lock the screen.",
+                    commands.len()
+                );
+                SuspendedCaffeinate { commands }
+            }
+            _ => {
+                log::warn!("Failed to stop 'caffeinate', the display might not go to sleep.");
+                Self::default()
+            }
+        }
+    }
+}
+
+impl Drop for SuspendedCaffeinate {
+    /// Restarts the stopped processes and prints a warning if one cannot be restarted.
+    fn drop(&mut self) {
+        for command in self.commands.drain(..) {
+            let Some((program, args)) = command.split_first() else {
+                continue;
+            };
+            match std::process::Command::new(program).args(args).spawn() {
+                Ok(_) => log::info!("Restarted '{}'.", command.join(" ")),
+                Err(e) => log::warn!("Failed to restart '{}': {}", command.join(" "), e),
+            }
+        }
+    }
+}
+
+/// Runs the command and returns its standard output if it succeeded.
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the output of `pgrep -l -f caffeinate`, i.e. the process id and the command line per
+/// line, into the command lines of the `caffeinate` processes.
+///
+/// The arguments are split at whitespace, so arguments containing spaces are not restored exactly.
+fn parse_caffeinate_processes(output: &str) -> Vec<Vec<String>> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let command = line
+                .split_whitespace()
+                .skip(1)
+                .map(str::to_string)
+                .collect::<Vec<String>>();
+            let program = Path::new(command.first()?).file_name()?;
+            (program == "caffeinate").then_some(command)
+        })
+        .collect()
+}
+
+/// Locks the screen on macOS with the commands run by the runner.
+fn lock_screen_on_macos_with(runner: &dyn CommandRunner) -> Result<(), LockError> {
+    let mut attempts = LockAttempts::new(runner);
+    let (program, args) = MACOS_LOCK_COMMAND;
+    if attempts.run(program, args) {
+        return Ok(());
+    }
+    Err(attempts.into_error("macOS"))
+}
+
+/// Checks if the screen is currently locked on Linux.
+fn is_screen_locked_linux() -> bool {
+    // Try freedesktop.org standard ScreenSaver interface (works with KDE, GNOME, etc.)
+    if let Ok(active_output) = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.ScreenSaver",
+            "--object-path",
+            "/ScreenSaver",
+            "--method",
+            "org.freedesktop.ScreenSaver.GetActive",
+        ])
+        .output()
+    {
+        if active_output.status.success() {
+            if let Ok(result) = String::from_utf8(active_output.stdout) {
+                // Result will be "(true,)" if locked, "(false,)" if unlocked
+                return result.contains("true");
+            }
+        }
+    }
+
+    // Fallback: Try GNOME-specific interface
+    if let Ok(active_output) = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.ScreenSaver",
+            "--object-path",
+            "/org/gnome/ScreenSaver",
+            "--method",
+            "org.gnome.ScreenSaver.GetActive",
+        ])
+        .output()
+    {
+        if active_output.status.success() {
+            if let Ok(result) = String::from_utf8(active_output.stdout) {
+                return result.contains("true");
+            }
+        }
+    }
+
+    // Fallback: assume unlocked if we can't determine
+    false
+}
+
+/// Checks if the screen is currently locked on Windows.
+fn is_screen_locked_windows() -> bool {
+    // On Windows, we'll use a simple heuristic: if we just locked it, assume it's locked
+    // A more robust solution would require Win32 API calls
+    false
+}
+
+/// Checks if the screen is currently locked on macOS.
+///
+/// The screen counts as locked if the main display is asleep, which is the case after the lock
+/// command `pmset displaysleepnow`. As fallback, the screen saver process is looked for, which
+/// is not found reliably on macOS 13 and later.
+fn is_screen_locked_macos() -> bool {
+    if is_main_display_asleep_macos() {
+        return true;
+    }
+    // Check if the screen saver is running
+    if let Ok(output) = std::process::Command::new("pgrep")
+        .arg("ScreenSaverEngine")
+        .output()
+    {
+        return output.status.success();
+    }
+    false
+}
+
+/// Checks if the main display is asleep with `CGDisplayIsAsleep` of CoreGraphics on macOS.
+#[cfg(target_os = "macos")]
+fn is_main_display_asleep_macos() -> bool {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGMainDisplayID() -> u32;
+        fn CGDisplayIsAsleep(display: u32) -> i32;
+    }
+    // SAFETY: Both functions have no preconditions and only read the state of the displays.
+    unsafe { CGDisplayIsAsleep(CGMainDisplayID()) != 0 }
+}
+
+/// Checks if the main display is asleep. Always `false` on platforms other than macOS.
+#[cfg(not(target_os = "macos"))]
+fn is_main_display_asleep_macos() -> bool {
+    false
+}
+
+/// Checks if the screen is currently locked.
+fn is_screen_locked() -> bool {
+    if cfg!(target_os = "linux") {
+        is_screen_locked_linux()
+    } else if cfg!(windows) {
+        is_screen_locked_windows()
+    } else if cfg!(target_os = "macos") {
+        is_screen_locked_macos()
+    } else {
+        false
+    }
+}
+
+/// The shortest interval in which `continuously_lock_screen` checks whether the screen is
+/// locked, to prevent busy-waiting.
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Configuration of the screen lock monitoring in `continuously_lock_screen`.
+///
+/// It is configured with `lockConfig` in the options file, where the durations are given in
+/// milliseconds. The message, the lockers and the background image are not part of it, they are
+/// taken from the `lockScreen` end event and `linuxLockPriority`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContinuousLockConfig {
+    /// The time the user may stay unlocked before the screen is locked again.
+    /// A zero grace period re-locks the screen as soon as an unlock is detected.
+    #[serde(rename = "gracePeriodMillis", with = "duration_millis")]
+    #[schemars(with = "u64")]
+    pub grace_period: Duration,
+    /// The interval in which the lock status is checked. Intervals below `MIN_POLL_INTERVAL`
+    /// are rejected in the options file and raised to it otherwise.
+    #[serde(rename = "pollIntervalMillis", with = "duration_millis")]
+    #[schemars(with = "u64")]
+    pub poll_interval: Duration,
+    /// The CPU core the monitoring thread is pinned to. If `None`, the operating system schedules the thread.
+    ///
+    /// This is an advanced performance option, e.g. to reduce CPU wake-ups of other cores on battery-powered laptops.
+    /// If pinning fails, a warning is printed and the thread is scheduled by the operating system.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_affinity_core: Option<usize>,
+    /// The configuration of the retries if locking the screen fails.
+    pub retry_config: LockRetryConfig,
+    /// The message shown whenever the screen is locked. The placeholder `{remaining}` is replaced
+    /// by the remaining duration. See `lock_screen_with_message`.
+    #[serde(skip)]
+    pub message: Option<String>,
+    /// The order in which the lockers are tried on Linux.
+    #[serde(skip, default = "default_linux_lock_priority")]
+    pub linux_lock_priority: Vec<LinuxLocker>,
+    /// The image shown as background of the lock screen. Only supported by `swaylock` and `i3lock`.
+    #[serde(skip)]
+    pub background_image: Option<PathBuf>,
+    /// The sound played right before the screen is re-locked after an unlock, to warn the user.
+    /// If `None`, the screen is re-locked silently.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relock_sound: Option<PathBuf>,
+    /// The command executed when the duration expired to unlock the screen again,
+    /// e.g. `["loginctl", "unlock-session"]` on Linux. If `None`, the screen stays locked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unlock_command: Option<Vec<String>>,
+    /// The signal to stop the monitoring before the duration expired, e.g. when the break is
+    /// quit or the timer aborted. If `None`, the screen is locked for the whole duration.
+    #[serde(skip)]
+    pub stop_signal: Option<Arc<AtomicBool>>,
+}
+
+fn default_linux_lock_priority() -> Vec<LinuxLocker> {
+    DEFAULT_LINUX_LOCK_PRIORITY.to_vec()
+}
+
+/// (De)serializes a `Duration` as whole milliseconds.
+mod duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+impl Default for ContinuousLockConfig {
+    /// Creates a new `ContinuousLockConfig` instance which re-locks immediately with the default lockers.
+    fn default() -> Self {
+        ContinuousLockConfig {
+            grace_period: Duration::ZERO,
+            poll_interval: Duration::from_millis(500),
+            cpu_affinity_core: None,
+            retry_config: LockRetryConfig::default(),
+            message: None,
+            linux_lock_priority: default_linux_lock_priority(),
+            background_image: None,
+            relock_sound: None,
+            unlock_command: None,
+            stop_signal: None,
+        }
+    }
+}
+
+impl ContinuousLockConfig {
+    /// Sets the grace period during which an unlocked screen is tolerated.
+    ///
+    /// This allows brief unlock windows during a break, e.g. to answer a phone call.
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets the interval in which the lock status is checked, at least `MIN_POLL_INTERVAL`.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the CPU core the monitoring thread is pinned to.
+    pub fn with_cpu_affinity_core(mut self, core: usize) -> Self {
+        self.cpu_affinity_core = Some(core);
+        self
+    }
+
+    /// Sets the message shown whenever the screen is locked.
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Sets the order in which the lockers are tried on Linux.
+    pub fn with_linux_lock_priority(mut self, linux_lock_priority: Vec<LinuxLocker>) -> Self {
+        self.linux_lock_priority = linux_lock_priority;
+        self
+    }
+
+    /// Sets the image shown as background of the lock screen.
+    pub fn with_background_image(mut self, background_image: impl Into<PathBuf>) -> Self {
+        self.background_image = Some(background_image.into());
+        self
+    }
+
+    /// Sets the sound played right before the screen is re-locked.
+    pub fn with_relock_sound(mut self, relock_sound: impl Into<PathBuf>) -> Self {
+        self.relock_sound = Some(relock_sound.into());
+        self
+    }
+
+    /// Sets the command executed to unlock the screen when the duration expired.
+    ///
+    /// The first element is the program, the others are its arguments.
+    pub fn with_unlock_command(mut self, unlock_command: Vec<String>) -> Self {
+        self.unlock_command = Some(unlock_command);
+        self
+    }
+
+    /// Sets the signal which stops the monitoring when it is set to `true`.
+    pub fn with_stop_signal(mut self, stop_signal: Arc<AtomicBool>) -> Self {
+        self.stop_signal = Some(stop_signal);
+        self
+    }
+}
+
+/// Replaces the placeholder `{remaining}` in the message by the remaining duration, e.g. `5 min`,
+/// and `{remaining_secs}` by the remaining duration in seconds.
+///
+/// The duration of `{remaining}` is rounded up to full minutes. Durations below one minute are
+/// given in seconds.
+#[must_use]
+pub fn fill_remaining_placeholder(message: &str, remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    let remaining = if seconds < 60 {
+        format!("{} s", seconds)
+    } else {
+        format!("{} min", seconds.div_ceil(60))
+    };
+    message
+        .replace("{remaining_secs}", &seconds.to_string())
+        .replace("{remaining}", &remaining)
+}
+
+/// Shows a message on the lock screen of lockers which cannot show one themselves.
+///
+/// On Linux, the message is sent as a notification with `notify-send` which is shown on the lock
+/// screen of e.g. GNOME and KDE. On other platforms, and if `notify-send` fails, the message is
+/// written to the system log. See `lock_screen_with_message` for the lockers showing the message.
+pub fn show_lock_message(message: &str) {
+    show_lock_message_with(&SystemCommandRunner, message);
+}
+
+/// Shows the message like `show_lock_message` with the commands run by the runner.
+fn show_lock_message_with(runner: &dyn CommandRunner, message: &str) {
+    if cfg!(target_os = "linux") {
+        match runner.run(
+            "notify-send",
+            &["--urgency=critical", "Locking Pomodoro Timer", message],
+        ) {
+            Ok(()) => return,
+            Err(_) => log::debug!("Failed to show the lock screen message with 'notify-send'."),
+        }
+    }
+    log_lock_message_with(runner, message);
+}
+
+/// Writes the message of the lock screen to the system log with `logger` on Unix.
+///
+/// On other platforms, and if `logger` fails, the message is logged as info instead.
+fn log_lock_message_with(runner: &dyn CommandRunner, message: &str) {
+    if cfg!(unix) {
+        if runner
+            .run("logger", &["-t", "locking-pomodoro-timer", message])
+            .is_ok()
+        {
+            return;
+        }
+        log::debug!("Failed to write the lock screen message to the system log with 'logger'.");
+    }
+    log::info!("Lock screen message: {}", message);
+}
+
+/// Runs the command to unlock the screen. The first element is the program, the others are its arguments.
+///
+/// Prints a warning if the command is empty, cannot be started or fails.
+fn run_unlock_command(command: &[String]) {
+    let Some((program, args)) = command.split_first() else {
+        log::warn!("The unlock command is empty.");
+        return;
+    };
+    match std::process::Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => log::info!("Screen unlocked with '{}'.", program),
+        Ok(output) => log::warn!(
+            "Failed to unlock the screen with '{}': {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => log::warn!("Failed to run the unlock command '{}': {}", program, e),
+    }
+}
+
+/// Plays the sound in a separate thread without waiting for it to finish.
+///
+/// Prints a warning if the sound cannot be played.
+fn play_sound_in_background(filepath_sound: Option<PathBuf>) {
+    thread::spawn(move || {
+        if let Err(e) = play_sound(&filepath_sound) {
+            log::warn!("Failed to play the re-lock sound: {}", e);
+        }
+    });
+}
+
+/// Pins the current thread to the CPU core with the given index.
+///
+/// Prints a warning and returns `false` if the core doesn't exist or pinning is not supported
+/// on this platform.
+fn pin_current_thread_to_core(core: usize) -> bool {
+    let core_id = core_affinity::get_core_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|core_id| core_id.id == core);
+    match core_id {
+        Some(core_id) if core_affinity::set_for_current(core_id) => {
+            log::debug!("Pinned monitoring thread to CPU core {}.", core);
+            true
+        }
+        Some(_) => {
+            log::warn!("Failed to pin monitoring thread to CPU core {}.", core);
+            false
+        }
+        None => {
+            log::warn!("CPU core {} does not exist.", core);
+            false
+        }
+    }
+}
+
+/// An event of the screen lock monitoring in `continuously_lock_screen`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockEvent {
+    /// Locking the screen is attempted.
+    LockAttempted,
+    /// The attempt to lock the screen succeeded.
+    LockSucceeded,
+    /// The attempt to lock the screen failed with all lockers and retries.
+    LockFailed,
+    /// The screen was locked at the start of the monitoring. Not sent if the initial lock failed.
+    Locked,
+    /// The screen was detected to be unlocked before the duration expired.
+    UnlockDetected,
+    /// The screen was locked again after an unlock.
+    Relocked,
+    /// The duration expired or the stop signal was set and the monitoring stopped.
+    MonitoringEnded,
+}
+
+/// The result of the screen lock monitoring in `continuously_lock_screen`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LockReport {
+    /// The number of times the screen was re-locked after an unlock.
+    pub relocks: u32,
+    /// How long the screen was actually locked, i.e. the duration without the time
+    /// the screen was unlocked before it was re-locked.
+    pub locked_duration: Duration,
+    /// Whether the monitoring was stopped with the stop signal before the duration expired.
+    pub stopped: bool,
+}
+
+/// Sends the event if a sender is given. A disconnected receiver is ignored.
+fn send_lock_event(events: &Option<Sender<LockEvent>>, event: LockEvent) {
+    if let Some(events) = events {
+        let _ = events.send(event);
+    }
+}
+
+/// Locks the screen like `lock_screen_or_warn` with the lock function of the hooks and sends
+/// `LockAttempted` before and `LockSucceeded` or `LockFailed` after the attempt.
+///
+/// # Returns
+/// `true` if the screen was locked.
+fn lock_screen_and_send_events(
+    hooks: &ScreenLockHooks,
+    events: &Option<Sender<LockEvent>>,
+    retry_config: &LockRetryConfig,
+    linux_lock_priority: &[LinuxLocker],
+    background_image: Option<&Path>,
+    message: Option<&str>,
+) -> bool {
+    send_lock_event(events, LockEvent::LockAttempted);
+    match (hooks.lock_screen)(retry_config, linux_lock_priority, background_image, message) {
+        Ok(()) => {
+            send_lock_event(events, LockEvent::LockSucceeded);
+            true
+        }
+        Err(e) => {
+            log::warn!("{}", lock_failure_warning(&e));
+            send_lock_event(events, LockEvent::LockFailed);
+            false
+        }
+    }
+}
+
+/// A function locking the screen like `lock_screen_with_message`.
+type LockScreenFn = dyn Fn(&LockRetryConfig, &[LinuxLocker], Option<&Path>, Option<&str>) -> Result<(), LockError>
+    + Send
+    + Sync;
+
+/// A function running an unlock command like `run_unlock_command`.
+type UnlockCommandFn = dyn Fn(&[String]) + Send + Sync;
+
+/// The functions `continuously_lock_screen` uses to lock the screen, check the lock, unlock the
+/// screen, play the re-lock sound and wait, which are replaced in the tests.
+#[derive(Clone)]
+struct ScreenLockHooks {
+    /// Locks the screen.
+    lock_screen: Arc<LockScreenFn>,
+    /// Checks if the screen is currently locked.
+    is_screen_locked: Arc<dyn Fn() -> bool + Send + Sync>,
+    /// The clock all waiting and time measurement is done with, so that the tests don't have to
+    /// wait in real time.
+    clock: Arc<dyn Clock>,
+    /// The time the monitoring waits for the initial lock to take effect before it checks the lock.
+    initial_monitor_delay: Duration,
+    /// Runs the unlock command of the configuration when the duration expired.
+    run_unlock_command: Arc<UnlockCommandFn>,
+    /// Plays the re-lock sound of the configuration without waiting for it to finish.
+    play_relock_sound: Arc<dyn Fn(&Path) + Send + Sync>,
+}
+
+impl Default for ScreenLockHooks {
+    /// Creates the hooks which lock the screen with the commands of the platform with the clock
+    /// of the timer, see `current_clock`.
+    fn default() -> Self {
+        ScreenLockHooks {
+            lock_screen: Arc::new(lock_screen_with_message),
+            is_screen_locked: Arc::new(is_screen_locked),
+            clock: current_clock(),
+            initial_monitor_delay: Duration::from_secs(3),
+            run_unlock_command: Arc::new(run_unlock_command),
+            play_relock_sound: Arc::new(|sound| play_sound_in_background(Some(sound.into()))),
+        }
+    }
+}
+
+/// Continuously locks the screen for the specified duration.
+///
+/// This function locks the screen and monitors it, re-locking whenever
+/// the user tries to unlock it before the duration expires.
+/// If the configuration contains a grace period, the screen is only re-locked
+/// after the user has been unlocked for longer than the grace period.
+/// If the monitoring thread panics, it is restarted for the remaining duration.
+/// If the configuration contains an unlock command, it is executed when the duration expired
+/// to unlock the screen without user interaction.
+/// If the configuration contains a stop signal, the monitoring ends as soon as it is set.
+///
+/// # Arguments
+/// * `duration` - How long to keep the screen locked
+/// * `config` - The configuration of the lock monitoring
+/// * `events` - If given, a `LockEvent` is sent whenever locking the screen is attempted and
+///   succeeded or failed, the screen is locked, an unlock is detected, the screen is re-locked
+///   and when the monitoring ends
+///
+/// # Returns
+/// A `LockReport` with the number of times the screen was re-locked after an unlock, how
+/// long the screen was actually locked and whether the monitoring was stopped.
+pub fn continuously_lock_screen(
+    duration: Duration,
+    config: &ContinuousLockConfig,
+    events: Option<Sender<LockEvent>>,
+) -> LockReport {
+    continuously_lock_screen_with(duration, config, events, &ScreenLockHooks::default())
+}
+
+/// Continuously locks the screen like `continuously_lock_screen` with the functions of the hooks.
+///
+/// Only the monitoring thread waits with the clock of the hooks, so a clock which advances
+/// whenever it is slept with makes the monitoring deterministic.
+fn continuously_lock_screen_with(
+    duration: Duration,
+    config: &ContinuousLockConfig,
+    events: Option<Sender<LockEvent>>,
+    hooks: &ScreenLockHooks,
+) -> LockReport {
+    let clock = hooks.clock.clone();
+    let relock_count = Arc::new(AtomicU32::new(0));
+    // The total time the screen was unlocked, tracked by the monitoring thread
+    let unlocked_millis = Arc::new(AtomicU64::new(0));
+    let grace_period = config.grace_period;
+    let poll_interval = config.poll_interval.max(MIN_POLL_INTERVAL);
+    let cpu_affinity_core = config.cpu_affinity_core;
+    let retry_config = config.retry_config.clone();
+    let linux_lock_priority = config.linux_lock_priority.clone();
+    let background_image = config.background_image.clone();
+    let relock_sound = config.relock_sound.clone();
+    let message = config.message.clone();
+    let stop_signal = config.stop_signal.clone();
+    let is_stopped = move || {
+        stop_signal
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Relaxed))
+    };
+    let start_time = clock.now();
+    let end_time = start_time + duration;
+    let message_clock = clock.clone();
+    let render_message = move || {
+        message.as_ref().map(|message| {
+            let elapsed = message_clock.now().saturating_duration_since(start_time);
+            let remaining = duration.saturating_sub(elapsed);
+            fill_remaining_placeholder(message, remaining)
+        })
+    };
+
+    // Keep the display from being woken up by `caffeinate` until the break ends
+    let caffeinate = SuspendedCaffeinate::suspend();
+
+    // Lock the screen immediately
+    log::info!("Initial screen lock...");
+    if lock_screen_and_send_events(
+        hooks,
+        &events,
+        &retry_config,
+        &linux_lock_priority,
+        background_image.as_deref(),
+        render_message().as_deref(),
+    ) {
+        send_lock_event(&events, LockEvent::Locked);
+    }
+
+    // Spawns a monitoring thread which starts checking after the delay and runs until the
+    // duration expired
+    let spawn_monitor = |delay: Duration| {
+        let relock_count = relock_count.clone();
+        let unlocked_millis = unlocked_millis.clone();
+        let retry_config = retry_config.clone();
+        let linux_lock_priority = linux_lock_priority.clone();
+        let background_image = background_image.clone();
+        let relock_sound = relock_sound.clone();
+        let render_message = render_message.clone();
+        let monitor_events = events.clone();
+        let is_stopped = is_stopped.clone();
+        let hooks = hooks.clone();
+        thread::spawn(move || {
+            let clock = &hooks.clock;
+            let remaining = || end_time.saturating_duration_since(clock.now());
+            // All waiting ends at the end of the duration or when the monitoring is stopped
+            let wait = |duration: Duration| {
+                let wake_time = clock.now() + duration.min(remaining());
+                while !is_stopped() {
+                    let left = wake_time.saturating_duration_since(clock.now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    clock.sleep(left.min(MIN_POLL_INTERVAL));
+                }
+            };
+            if let Some(core) = cpu_affinity_core {
+                pin_current_thread_to_core(core);
+            }
+            wait(delay);
+            log::debug!(
+                "Monitoring thread started. Checking lock status every {} ms...",
+                poll_interval.as_millis()
+            );
+
+            let mut check_count = 0;
+            let mut unlocked_since: Option<Instant> = None;
+            let mut was_locked = None;
+            let unlocked_for =
+                |unlocked_at: Instant| clock.now().saturating_duration_since(unlocked_at);
+            let end_unlock_window = |unlocked_at: Instant| {
+                unlocked_millis.fetch_add(
+                    unlocked_for(unlocked_at).as_millis() as u64,
+                    Ordering::Relaxed,
+                );
+            };
+            while !remaining().is_zero() && !is_stopped() {
+                check_count += 1;
+                let is_locked = (hooks.is_screen_locked)();
+                let status = if is_locked { "LOCKED" } else { "UNLOCKED" };
+                log::debug!("Lock status check #{}: Screen is {}", check_count, status);
+                if was_locked != Some(is_locked) {
+                    log::info!("Lock status changed: Screen is {}", status);
+                    was_locked = Some(is_locked);
+                }
+
+                // Check if screen is unlocked
+                if is_locked {
+                    if let Some(unlocked_at) = unlocked_since.take() {
+                        end_unlock_window(unlocked_at);
+                    }
+                } else {
+                    let unlocked_at = *unlocked_since.get_or_insert_with(|| {
+                        send_lock_event(&monitor_events, LockEvent::UnlockDetected);
+                        if !grace_period.is_zero() {
+                            log::info!(
+                                "Screen unlocked. Re-locking in {} seconds unless locked again.",
+                                grace_period.as_secs()
+                            );
+                        }
+                        clock.now()
+                    });
+                    if unlocked_for(unlocked_at) >= grace_period {
+                        log::info!("Screen unlocked detected! Re-locking in 1 second...");
+                        wait(Duration::from_secs(1));
+                        if let Some(relock_sound) = &relock_sound {
+                            (hooks.play_relock_sound)(relock_sound);
+                        }
+                        // If locking failed, the screen stays unlocked and is re-locked at the next check
+                        if lock_screen_and_send_events(
+                            &hooks,
+                            &monitor_events,
+                            &retry_config,
+                            &linux_lock_priority,
+                            background_image.as_deref(),
+                            render_message().as_deref(),
+                        ) {
+                            log::info!("Screen re-locked.");
+                            send_lock_event(&monitor_events, LockEvent::Relocked);
+                            relock_count.fetch_add(1, Ordering::Relaxed);
+                            end_unlock_window(unlocked_at);
+                            unlocked_since = None;
+                            // Wait a bit after locking
+                            wait(Duration::from_secs(2));
+                        }
+                    }
+                }
+
+                wait(poll_interval);
+            }
+            if let Some(unlocked_at) = unlocked_since {
+                end_unlock_window(unlocked_at);
+            }
+            log::debug!("Monitoring thread stopped.");
+        })
+    };
+
+    // Wait a bit for the initial lock to take effect before monitoring
+    let mut monitor_thread = spawn_monitor(hooks.initial_monitor_delay);
+
+    // Act as watchdog of the monitoring thread, which only fails to join if it panicked
+    while monitor_thread.join().is_err() {
+        log::warn!(
+            "The lock monitoring thread panicked. Restarting it for the remaining {} seconds.",
+            end_time.saturating_duration_since(clock.now()).as_secs()
+        );
+        monitor_thread = spawn_monitor(Duration::ZERO);
+    }
+    let stopped = is_stopped() && !end_time.saturating_duration_since(clock.now()).is_zero();
+    if stopped {
+        log::info!("Lock monitoring stopped before the end of the break.");
+    } else {
+        log::info!("Break duration completed. Lock monitoring stopped.");
+    }
+
+    let elapsed = clock
+        .now()
+        .saturating_duration_since(start_time)
+        .min(duration);
+    let report = LockReport {
+        relocks: relock_count.load(Ordering::Relaxed),
+        locked_duration: elapsed.saturating_sub(Duration::from_millis(
+            unlocked_millis.load(Ordering::Relaxed),
+        )),
+        stopped,
+    };
+    if let Some(unlock_command) = &config.unlock_command {
+        (hooks.run_unlock_command)(unlock_command);
+    }
+    drop(caffeinate);
+    send_lock_event(&events, LockEvent::MonitoringEnded);
+    report
+}
+
+#[test]
+fn test_fill_remaining_placeholder() {
+    let message = "Pomodoro break: {remaining} remaining";
+    assert_eq!(
+        fill_remaining_placeholder(message, Duration::from_secs(5 * 60)),
+        "Pomodoro break: 5 min remaining"
+    );
+    assert_eq!(
+        fill_remaining_placeholder(message, Duration::from_secs(4 * 60 + 1)),
+        "Pomodoro break: 5 min remaining"
+    );
+    assert_eq!(
+        fill_remaining_placeholder(message, Duration::from_secs(30)),
+        "Pomodoro break: 30 s remaining"
+    );
+    assert_eq!(
+        fill_remaining_placeholder("{remaining_secs} seconds left", Duration::from_secs(90)),
+        "90 seconds left"
+    );
+}
+
+#[test]
+fn test_lock_attempts_into_error() {
+    let mut attempts = LockAttempts::default();
+    assert!(!attempts.run("locking-pomodoro-timer-missing-command", &["--lock"]));
+
+    let error = attempts.into_error("Linux");
+    assert_eq!(error.platform, "Linux");
+    assert_eq!(
+        error.attempted_commands,
+        vec!["locking-pomodoro-timer-missing-command --lock".to_string()]
+    );
+    assert_eq!(error.last_error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_lock_error() {
+    let error = LockError {
+        platform: "Linux",
+        attempted_commands: vec!["swaylock -f".to_string(), "i3lock".to_string()],
+        last_error: io::Error::other("i3lock failed"),
+    };
+    assert_eq!(
+        error.to_string(),
+        "Failed to lock the screen on Linux. Attempted commands: swaylock -f, i3lock."
+    );
+    assert_eq!(
+        std::error::Error::source(&error).unwrap().to_string(),
+        "i3lock failed"
+    );
+    assert_eq!(
+        lock_failure_warning(&error),
+        "Failed to lock the screen on Linux. Attempted commands: swaylock -f, i3lock. \
+        Last error: i3lock failed"
+    );
+
+    let error = unsupported_platform_error();
+    assert_eq!(error.platform, std::env::consts::OS);
+    assert!(error.attempted_commands.is_empty());
+    assert_eq!(error.last_error.kind(), io::ErrorKind::Unsupported);
+}
+
+/// A `CommandRunner` which records the commands instead of running them.
+#[cfg(test)]
+struct FakeCommandRunner {
+    /// The programs which fail when run.
+    failing_programs: Vec<&'static str>,
+    /// The commands run so far.
+    calls: std::cell::RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl CommandRunner for FakeCommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<()> {
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.calls.borrow_mut().push(command);
+        if self.failing_programs.contains(&program) {
+            return Err(io::Error::other(format!("{} failed", program)));
+        }
+        Ok(())
+    }
+
+    /// Makes all lockers available, so that the tests don't depend on the session they run in.
+    fn is_locker_available(&self, _locker: LinuxLocker) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_lock_commands_with_fake_runner() {
+    type LockFunction = fn(&dyn CommandRunner) -> Result<(), LockError>;
+    let linux: LockFunction = |runner| {
+        try_lock_screen_on_linux_with(
+            runner,
+            &[LinuxLocker::Loginctl, LinuxLocker::GnomeScreensaver],
+            None,
+            None,
+        )
+    };
+    let cases: [(&str, LockFunction, &[&str]); 3] = [
+        (
+            "Windows",
+            lock_screen_on_windows_with,
+            &["cmd /C rundll32 user32.dll,LockWorkStation"],
+        ),
+        (
+            "Linux",
+            linux,
+            &["loginctl lock-session", "gnome-screensaver-command -l"],
+        ),
+        (
+            "macOS",
+            lock_screen_on_macos_with,
+            &["pmset displaysleepnow"],
+        ),
+    ];
+    for (platform, lock, commands) in cases {
+        // The first command is attempted first and no other command if it succeeds
+        let runner = FakeCommandRunner {
+            failing_programs: Vec::new(),
+            calls: Default::default(),
+        };
+        assert!(lock(&runner).is_ok(), "{}", platform);
+        assert_eq!(*runner.calls.borrow(), commands[..1], "{}", platform);
+
+        // If the first command fails, the next one is attempted
+        let first_program = commands[0].split(' ').next().unwrap();
+        let runner = FakeCommandRunner {
+            failing_programs: vec![first_program],
+            calls: Default::default(),
+        };
+        assert_eq!(lock(&runner).is_ok(), commands.len() > 1, "{}", platform);
+        assert_eq!(*runner.calls.borrow(), commands, "{}", platform);
+
+        // If all commands fail, the warning lists all attempted commands
+        let runner = FakeCommandRunner {
+            failing_programs: commands
+                .iter()
+                .map(|command| command.split(' ').next().unwrap())
+                .collect(),
+            calls: Default::default(),
+        };
+        let error = lock(&runner).unwrap_err();
+        assert_eq!(error.platform, platform);
+        let last_program = commands[commands.len() - 1].split(' ').next().unwrap();
+        assert_eq!(
+            lock_failure_warning(&error),
+            format!(
+                "Failed to lock the screen on {}. Attempted commands: {}. Last error: {} failed",
+                platform,
+                commands.join(", "),
+                last_program
+            )
+        );
+    }
+}
+
+#[test]
+fn test_dry_run_command_runner() {
+    let runner = DryRunCommandRunner::default();
+    let error = runner
+        .run("locking-pomodoro-timer-missing", &["--lock"])
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert_eq!(*runner.command.borrow(), None);
+    if cfg!(unix) {
+        // Only the first command which succeeds is recorded, nothing is run
+        runner.run("sh", &["-c", "exit 1"]).unwrap();
+        runner.run("sh", &["-c", "exit 2"]).unwrap();
+        assert_eq!(runner.command.borrow().as_deref(), Some("sh -c exit 1"));
+    }
+}
+
+#[test]
+fn test_compositor_lockers() {
+    let only = |variable: &'static str| move |name: &str| name == variable;
+    assert!(LinuxLocker::Hyprland.is_available_with(only("HYPRLAND_INSTANCE_SIGNATURE")));
+    assert!(!LinuxLocker::Hyprland.is_available_with(only("NIRI_SOCKET")));
+    assert!(LinuxLocker::Niri.is_available_with(only("NIRI_SOCKET")));
+    assert!(!LinuxLocker::Niri.is_available_with(|_| false));
+    assert!(LinuxLocker::Loginctl.is_available_with(|_| false));
+    assert!(LinuxLocker::Swaylock.is_available_with(only("WAYLAND_DISPLAY")));
+    assert!(!LinuxLocker::Swaylock.is_available_with(only("DISPLAY")));
+    assert!(LinuxLocker::I3lock.is_available_with(only("DISPLAY")));
+    assert!(!LinuxLocker::I3lock.is_available_with(|name| name.ends_with("DISPLAY")));
+    assert!(!LinuxLocker::I3lock.is_available_with(|_| false));
+    assert_eq!(
+        LinuxLocker::Hyprland.command(),
+        ("hyprctl", &["dispatch", "dpms", "off"][..])
+    );
+    assert_eq!(
+        LinuxLocker::Niri.command(),
+        ("niri", &["msg", "action", "power-off-monitors"][..])
+    );
+    // The compositors are tried before the generic lockers
+    assert_eq!(
+        DEFAULT_LINUX_LOCK_PRIORITY[..2],
+        [LinuxLocker::Hyprland, LinuxLocker::Niri]
+    );
+    // The lockers supporting a background image are tried before the generic D-Bus locker
+    assert_eq!(
+        DEFAULT_LINUX_LOCK_PRIORITY[DEFAULT_LINUX_LOCK_PRIORITY.len() - 3..],
+        [
+            LinuxLocker::Swaylock,
+            LinuxLocker::I3lock,
+            LinuxLocker::DBus
+        ]
+    );
+}
+
+#[test]
+fn test_kde_lockers() {
+    let lockers: Vec<LinuxLocker> = serde_json::from_str(r#"["qdbus6", "qdbus"]"#).unwrap();
+    assert_eq!(lockers, [LinuxLocker::Qdbus6, LinuxLocker::Qdbus]);
+    let arguments = &["org.freedesktop.ScreenSaver", "/ScreenSaver", "Lock"][..];
+    assert_eq!(LinuxLocker::Qdbus6.command(), ("qdbus6", arguments));
+    assert_eq!(LinuxLocker::Qdbus.command(), ("qdbus", arguments));
+    assert!(LinuxLocker::Qdbus6.is_available_with(|_| false));
+
+    // Plasma 6 is tried before Plasma 5
+    let runner = FakeCommandRunner {
+        failing_programs: vec!["qdbus6"],
+        calls: Default::default(),
+    };
+    try_lock_screen_on_linux_with(&runner, &lockers, None, None).unwrap();
+    assert_eq!(
+        *runner.calls.borrow(),
+        [
+            "qdbus6 org.freedesktop.ScreenSaver /ScreenSaver Lock",
+            "qdbus org.freedesktop.ScreenSaver /ScreenSaver Lock"
+        ]
+    );
+}
+
+#[test]
+fn test_cosmic_locker() {
+    let locker: LinuxLocker = serde_json::from_str(r#""cosmic""#).unwrap();
+    assert_eq!(locker, LinuxLocker::Cosmic);
+    assert!(locker.is_available_with(|name| name == "COSMIC_SESSION_SOCK"));
+    assert!(!locker.is_available_with(|name| name == "HYPRLAND_INSTANCE_SIGNATURE"));
+    assert_eq!(locker.command(), LinuxLocker::Loginctl.command());
+}
+
+#[test]
+fn test_pin_current_thread_to_core() {
+    let config = ContinuousLockConfig::default().with_cpu_affinity_core(0);
+    assert_eq!(config.cpu_affinity_core, Some(0));
+    // The thread is pinned in a new thread, so the test thread can still run on every core
+    let pinned = thread::spawn(|| {
+        let first_core = core_affinity::get_core_ids()
+            .and_then(|core_ids| core_ids.first().map(|core_id| core_id.id));
+        let pinned = first_core.map(pin_current_thread_to_core);
+        (pinned, pin_current_thread_to_core(usize::MAX))
+    })
+    .join()
+    .unwrap();
+    if cfg!(target_os = "linux") {
+        assert_eq!(pinned, (Some(true), false));
+    } else {
+        assert!(!pinned.1);
+    }
+}
+
+#[test]
+fn test_is_cinnamon() {
+    assert!(LinuxLocker::Cinnamon.runs_on_desktop("X-Cinnamon"));
+    assert!(LinuxLocker::Cinnamon.runs_on_desktop("GNOME:Cinnamon"));
+    assert!(!LinuxLocker::Cinnamon.runs_on_desktop("GNOME"));
+    assert!(!LinuxLocker::Cinnamon.runs_on_desktop(""));
+}
+
+#[test]
+fn test_lockers_for_desktop() {
+    let lockers = [
+        LinuxLocker::Loginctl,
+        LinuxLocker::Cinnamon,
+        LinuxLocker::Mate,
+        LinuxLocker::Xfce,
+        LinuxLocker::LightDm,
+    ];
+    assert_eq!(lockers_for_desktop(&lockers, ""), lockers);
+    assert_eq!(
+        lockers_for_desktop(&lockers, "MATE"),
+        [
+            LinuxLocker::Mate,
+            LinuxLocker::Loginctl,
+            LinuxLocker::Cinnamon,
+            LinuxLocker::Xfce,
+            LinuxLocker::LightDm,
+        ]
+    );
+    assert_eq!(lockers_for_desktop(&lockers, "XFCE")[0], LinuxLocker::Xfce);
+    // Only the listed lockers are tried, even on their desktop
+    assert_eq!(
+        lockers_for_desktop(&[LinuxLocker::Loginctl], "XFCE"),
+        [LinuxLocker::Loginctl]
+    );
+}
+
+#[test]
+fn test_only_listed_lockers_are_tried() {
+    let lockers = [
+        LinuxLocker::Swaylock,
+        LinuxLocker::I3lock,
+        LinuxLocker::XdgScreensaver,
+    ];
+    let runner = FakeCommandRunner {
+        failing_programs: vec!["swaylock", "i3lock", "xdg-screensaver"],
+        calls: Default::default(),
+    };
+    assert!(try_lock_screen_on_linux_with(&runner, &lockers, None, None).is_err());
+    assert_eq!(
+        *runner.calls.borrow(),
+        ["swaylock -f", "i3lock", "xdg-screensaver lock"]
+    );
+    let runner = FakeCommandRunner {
+        failing_programs: Vec::new(),
+        calls: Default::default(),
+    };
+    assert!(try_lock_screen_on_linux_with(&runner, &[], None, None).is_err());
+    assert!(runner.calls.borrow().is_empty());
+}
+
+#[test]
+fn test_retry_lock_with_fake_runner() {
+    let runner = FakeCommandRunner {
+        failing_programs: vec!["pmset"],
+        calls: Default::default(),
+    };
+    let retry_config = LockRetryConfig {
+        max_retries: 2,
+        retry_delay: Duration::ZERO,
+    };
+    assert!(retry_lock(&retry_config, || lock_screen_on_macos_with(&runner)).is_err());
+    assert_eq!(runner.calls.borrow().len(), 3);
+}
+
+#[test]
+fn test_lock_with_background_image() {
+    let runner = FakeCommandRunner {
+        failing_programs: vec!["swaylock"],
+        calls: Default::default(),
+    };
+    let lockers = [
+        LinuxLocker::Swaylock,
+        LinuxLocker::I3lock,
+        LinuxLocker::Loginctl,
+    ];
+    let image = Path::new("/tmp/break.png");
+    assert!(try_lock_screen_on_linux_with(&runner, &lockers, Some(image), None).is_ok());
+    assert_eq!(
+        *runner.calls.borrow(),
+        [
+            "swaylock -f --image /tmp/break.png",
+            "i3lock --image /tmp/break.png"
+        ]
+    );
+
+    // Lockers without support for images are run without it
+    let runner = FakeCommandRunner {
+        failing_programs: Vec::new(),
+        calls: Default::default(),
+    };
+    assert!(try_lock_screen_on_linux_with(&runner, &lockers[2..], Some(image), None).is_ok());
+    assert_eq!(*runner.calls.borrow(), ["loginctl lock-session"]);
+}
+
+#[test]
+fn test_lock_with_message() {
+    let message = "Break: 5 min remaining";
+    let runner = FakeCommandRunner {
+        failing_programs: Vec::new(),
+        calls: Default::default(),
+    };
+    let image = Path::new("/tmp/break.png");
+    let lockers = [LinuxLocker::Swaylock];
+    assert!(try_lock_screen_on_linux_with(&runner, &lockers, Some(image), Some(message)).is_ok());
+    assert_eq!(
+        *runner.calls.borrow(),
+        ["swaylock -f --image /tmp/break.png --message Break: 5 min remaining"]
+    );
+    let runner = FakeCommandRunner {
+        failing_programs: Vec::new(),
+        calls: Default::default(),
+    };
+    assert!(
+        try_lock_screen_on_linux_with(&runner, &[LinuxLocker::I3lock], None, Some(message)).is_ok()
+    );
+    assert_eq!(*runner.calls.borrow(), ["i3lock -t Break: 5 min remaining"]);
+
+    // Lockers without support for messages show it as notification
+    let runner = FakeCommandRunner {
+        failing_programs: Vec::new(),
+        calls: Default::default(),
+    };
+    assert!(
+        try_lock_screen_on_linux_with(&runner, &[LinuxLocker::Loginctl], None, Some(message))
+            .is_ok()
+    );
+    assert_eq!(
+        *runner.calls.borrow(),
+        [
+            "loginctl lock-session",
+            "notify-send --urgency=critical Locking Pomodoro Timer Break: 5 min remaining"
+        ]
+    );
+
+    // The message is written to the system log if the notification fails
+    let runner = FakeCommandRunner {
+        failing_programs: vec!["notify-send"],
+        calls: Default::default(),
+    };
+    show_lock_message_with(&runner, message);
+    assert_eq!(
+        runner.calls.borrow()[1],
+        "logger -t locking-pomodoro-timer Break: 5 min remaining"
+    );
+}
+
+#[test]
+fn test_parse_caffeinate_processes() {
+    let output = "412 caffeinate -d -w 398\n977 /usr/bin/caffeinate -i\n1203 pgrep -u 501 -l -f caffeinate\n";
+    assert_eq!(
+        parse_caffeinate_processes(output),
+        vec![
+            vec!["caffeinate", "-d", "-w", "398"],
+            vec!["/usr/bin/caffeinate", "-i"],
+        ]
+    );
+    assert!(parse_caffeinate_processes("").is_empty());
+}
+
+#[test]
+fn test_continuously_lock_screen_with_fake_hooks() {
+    let lock_calls = Arc::new(AtomicU32::new(0));
+    let lock_checks = Arc::new(AtomicU32::new(0));
+    let hooks = {
+        let lock_calls = lock_calls.clone();
+        let lock_checks = lock_checks.clone();
+        ScreenLockHooks {
+            lock_screen: Arc::new(move |_, _, _, _| {
+                lock_calls.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }),
+            // The user unlocks the screen right after the initial lock and the first re-lock
+            is_screen_locked: Arc::new(move || lock_checks.fetch_add(1, Ordering::Relaxed) >= 2),
+            clock: Arc::new(crate::clock::ManualClock::new()),
+            initial_monitor_delay: Duration::ZERO,
+            ..ScreenLockHooks::default()
+        }
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let report = continuously_lock_screen_with(
+        Duration::from_secs(5),
+        &ContinuousLockConfig::default(),
+        Some(sender),
+        &hooks,
+    );
+    assert_eq!(report.relocks, 2);
+    // The screen is unlocked for a second before each re-lock
+    assert_eq!(report.locked_duration, Duration::from_secs(3));
+    assert_eq!(lock_calls.load(Ordering::Relaxed), 3);
+    let events = receiver.iter().collect::<Vec<_>>();
+    let count = |event| events.iter().filter(|e| **e == event).count();
+    assert_eq!(count(LockEvent::UnlockDetected), 2);
+    assert_eq!(count(LockEvent::Relocked), 2);
+    assert_eq!(events.last(), Some(&LockEvent::MonitoringEnded));
+}
+
+#[test]
+fn test_continuously_lock_screen_with_failing_lock() {
+    let lock_calls = Arc::new(AtomicU32::new(0));
+    let hooks = {
+        let lock_calls = lock_calls.clone();
+        ScreenLockHooks {
+            lock_screen: Arc::new(move |_, _, _, _| {
+                lock_calls.fetch_add(1, Ordering::Relaxed);
+                Err(LockError {
+                    platform: "Linux",
+                    attempted_commands: vec!["loginctl lock-session".to_string()],
+                    last_error: io::Error::other("loginctl failed"),
+                })
+            }),
+            is_screen_locked: Arc::new(|| false),
+            clock: Arc::new(crate::clock::ManualClock::new()),
+            initial_monitor_delay: Duration::ZERO,
+            ..ScreenLockHooks::default()
+        }
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let report = continuously_lock_screen_with(
+        Duration::from_secs(5),
+        &ContinuousLockConfig::default(),
+        Some(sender),
+        &hooks,
+    );
+    assert_eq!(report.relocks, 0);
+    assert_eq!(report.locked_duration, Duration::ZERO);
+    // The initial lock and a re-lock at every check, i.e. after 1, 2.5, 4 and 5 seconds
+    assert_eq!(lock_calls.load(Ordering::Relaxed), 5);
+    let events = receiver.iter().collect::<Vec<_>>();
+    assert!(!events.contains(&LockEvent::Locked));
+    assert!(!events.contains(&LockEvent::Relocked));
+    // The screen stays unlocked, which is detected only once
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| **e == LockEvent::UnlockDetected)
+            .count(),
+        1
+    );
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| **e == LockEvent::LockFailed)
+            .count() as u32,
+        lock_calls.load(Ordering::Relaxed)
+    );
+}
+
+#[test]
+fn test_continuously_lock_screen_restarts_panicked_monitor() {
+    let lock_checks = Arc::new(AtomicU32::new(0));
+    let hooks = {
+        let lock_checks = lock_checks.clone();
+        ScreenLockHooks {
+            lock_screen: Arc::new(|_, _, _, _| Ok(())),
+            // The first check panics, the restarted monitoring thread finds the screen locked
+            is_screen_locked: Arc::new(move || {
+                if lock_checks.fetch_add(1, Ordering::Relaxed) == 0 {
+                    panic!("Failed to check the lock status");
+                }
+                true
+            }),
+            clock: Arc::new(crate::clock::ManualClock::new()),
+            initial_monitor_delay: Duration::ZERO,
+            ..ScreenLockHooks::default()
+        }
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let report = continuously_lock_screen_with(
+        Duration::from_secs(5),
+        &ContinuousLockConfig::default(),
+        Some(sender),
+        &hooks,
+    );
+    assert_eq!(report.relocks, 0);
+    assert_eq!(report.locked_duration, Duration::from_secs(5));
+    // One panicking check and a check every 500 ms of the restarted thread
+    assert_eq!(lock_checks.load(Ordering::Relaxed), 11);
+    let events = receiver.iter().collect::<Vec<_>>();
+    assert_eq!(events.last(), Some(&LockEvent::MonitoringEnded));
+}
+
+#[test]
+fn test_continuously_lock_screen_runs_unlock_command() {
+    let unlock_commands = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let hooks = {
+        let unlock_commands = unlock_commands.clone();
+        ScreenLockHooks {
+            lock_screen: Arc::new(|_, _, _, _| Ok(())),
+            is_screen_locked: Arc::new(|| true),
+            clock: Arc::new(crate::clock::ManualClock::new()),
+            initial_monitor_delay: Duration::ZERO,
+            run_unlock_command: Arc::new(move |command| {
+                unlock_commands.lock().unwrap().push(command.to_vec());
+            }),
+            ..ScreenLockHooks::default()
+        }
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    continuously_lock_screen_with(
+        Duration::from_secs(5),
+        &ContinuousLockConfig::default(),
+        None,
+        &hooks,
+    );
+    assert!(unlock_commands.lock().unwrap().is_empty());
+
+    let unlock_command = vec!["loginctl".to_string(), "unlock-session".to_string()];
+    let config = ContinuousLockConfig::default().with_unlock_command(unlock_command.clone());
+    let report =
+        continuously_lock_screen_with(Duration::from_secs(5), &config, Some(sender), &hooks);
+    assert_eq!(report.locked_duration, Duration::from_secs(5));
+    // The screen is unlocked once when the duration expired
+    assert_eq!(*unlock_commands.lock().unwrap(), [unlock_command]);
+    let events = receiver.iter().collect::<Vec<_>>();
+    assert_eq!(events.last(), Some(&LockEvent::MonitoringEnded));
+}
+
+#[test]
+fn test_continuously_lock_screen_plays_relock_sound() {
+    let played_sounds = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let lock_checks = Arc::new(AtomicU32::new(0));
+    let hooks = {
+        let played_sounds = played_sounds.clone();
+        let lock_checks = lock_checks.clone();
+        ScreenLockHooks {
+            lock_screen: Arc::new(|_, _, _, _| Ok(())),
+            // The user unlocks the screen once right after the initial lock
+            is_screen_locked: Arc::new(move || lock_checks.fetch_add(1, Ordering::Relaxed) >= 1),
+            clock: Arc::new(crate::clock::ManualClock::new()),
+            initial_monitor_delay: Duration::ZERO,
+            play_relock_sound: Arc::new(move |sound| {
+                played_sounds.lock().unwrap().push(sound.to_path_buf());
+            }),
+            ..ScreenLockHooks::default()
+        }
+    };
+    let report = continuously_lock_screen_with(
+        Duration::from_secs(5),
+        &ContinuousLockConfig::default(),
+        None,
+        &hooks,
+    );
+    assert_eq!(report.relocks, 1);
+    // Without a re-lock sound, the screen is re-locked silently
+    assert!(played_sounds.lock().unwrap().is_empty());
+
+    lock_checks.store(0, Ordering::Relaxed);
+    let config = ContinuousLockConfig::default().with_relock_sound("/tmp/relock.wav");
+    let report = continuously_lock_screen_with(Duration::from_secs(5), &config, None, &hooks);
+    assert_eq!(report.relocks, 1);
+    assert_eq!(
+        *played_sounds.lock().unwrap(),
+        [PathBuf::from("/tmp/relock.wav")]
+    );
+}
+
+#[test]
+fn test_continuously_lock_screen_with_stop_signal() {
+    let stop_signal = Arc::new(AtomicBool::new(false));
+    let hooks = {
+        let stop_signal = stop_signal.clone();
+        let lock_checks = AtomicU32::new(0);
+        ScreenLockHooks {
+            lock_screen: Arc::new(|_, _, _, _| Ok(())),
+            // The break is quit at the third check after 1 second
+            is_screen_locked: Arc::new(move || {
+                if lock_checks.fetch_add(1, Ordering::Relaxed) == 2 {
+                    stop_signal.store(true, Ordering::Relaxed);
+                }
+                true
+            }),
+            clock: Arc::new(crate::clock::ManualClock::new()),
+            initial_monitor_delay: Duration::ZERO,
+            ..ScreenLockHooks::default()
+        }
+    };
+    let config = ContinuousLockConfig::default().with_stop_signal(stop_signal);
+    let report = continuously_lock_screen_with(Duration::from_secs(5), &config, None, &hooks);
+    assert!(report.stopped);
+    assert_eq!(report.locked_duration, Duration::from_secs(1));
+
+    let report = continuously_lock_screen_with(
+        Duration::from_secs(5),
+        &ContinuousLockConfig::default(),
+        None,
+        &hooks,
+    );
+    assert!(!report.stopped);
+    assert_eq!(report.locked_duration, Duration::from_secs(5));
+}
+
+#[test]
+fn test_suspended_caffeinate() {
+    if !cfg!(target_os = "macos") {
+        assert!(SuspendedCaffeinate::suspend().commands.is_empty());
+    }
+    // Restarting skips empty command lines
+    drop(SuspendedCaffeinate {
+        commands: vec![Vec::new(), vec!["true".to_string()]],
+    });
+}
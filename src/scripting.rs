@@ -0,0 +1,146 @@
+//! This module evaluates the Lua scripts of the `Script` end event, e.g. to only lock the screen
+//! in the evening:
+//!
+//! ```lua
+//! if tonumber(os.date("%H")) >= 18 then
+//!     lock_screen()
+//! else
+//!     send_notification("Break", session_stats().completed_sessions .. " sessions today")
+//! end
+//! ```
+//!
+//! Besides the standard library of Lua 5.4, the scripts can call these functions:
+//! * `lock_screen()` - Locks the screen once with the lockers of `linuxLockPriority`.
+//! * `play_sound(path)` - Plays the sound file, or the default sound if the path is empty.
+//! * `send_notification(title, body)` - Shows a desktop notification.
+//! * `session_stats()` - Gets the statistics of today as table with the fields
+//!   `completed_sessions`, `focused_secs`, `average_session_secs`, `longest_streak` and `labels`.
+//!
+//! This module is only available with the `scripting` feature.
+use crate::end_events::{play_sound_or_warn, today_summary};
+use crate::screen_lock::{lock_screen_or_warn, LinuxLocker, LockRetryConfig};
+use mlua::{Lua, Table};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The name of the scripts in the error messages of Lua.
+const SCRIPT_NAME: &str = "script end event";
+
+/// Evaluates the script with the functions of this module.
+///
+/// # Arguments
+/// * `source` - The Lua source code.
+/// * `linux_lock_priority` - The lockers used by `lock_screen()` on Linux.
+///
+/// # Errors
+///
+/// Returns an error if the script has a syntax error or raises an error.
+pub fn run_script(source: &str, linux_lock_priority: &[LinuxLocker]) -> mlua::Result<()> {
+    let lua = Lua::new();
+    register_functions(&lua, linux_lock_priority)?;
+    lua.load(source).set_name(SCRIPT_NAME).exec()
+}
+
+/// Checks that the script can be compiled without running it.
+///
+/// # Errors
+///
+/// Returns an error if the script has a syntax error.
+pub fn check_script(source: &str) -> mlua::Result<()> {
+    Lua::new()
+        .load(source)
+        .set_name(SCRIPT_NAME)
+        .into_function()
+        .map(|_| ())
+}
+
+/// Registers the functions of the scripts as globals of the Lua state.
+fn register_functions(lua: &Lua, linux_lock_priority: &[LinuxLocker]) -> mlua::Result<()> {
+    let globals = lua.globals();
+    let lockers = linux_lock_priority.to_vec();
+    globals.set(
+        "lock_screen",
+        lua.create_function(move |_, ()| {
+            lock_screen_or_warn(&LockRetryConfig::default(), &lockers, None, None);
+            Ok(())
+        })?,
+    )?;
+    globals.set(
+        "play_sound",
+        lua.create_function(|_, path: String| {
+            let path = (!path.is_empty()).then(|| PathBuf::from(path));
+            play_sound_or_warn(&path, None, None);
+            Ok(())
+        })?,
+    )?;
+    globals.set(
+        "send_notification",
+        lua.create_function(|_, (title, body): (String, String)| {
+            send_notification(&title, &body);
+            Ok(())
+        })?,
+    )?;
+    globals.set(
+        "session_stats",
+        lua.create_function(|lua, ()| session_stats(lua))?,
+    )?;
+    Ok(())
+}
+
+/// Creates the table of the statistics of today.
+fn session_stats(lua: &Lua) -> mlua::Result<Table> {
+    let summary = today_summary();
+    let stats = lua.create_table()?;
+    stats.set("completed_sessions", summary.completed_sessions)?;
+    stats.set("focused_secs", summary.focused_secs)?;
+    stats.set("average_session_secs", summary.average_session_secs)?;
+    stats.set("longest_streak", summary.longest_streak)?;
+    stats.set("labels", lua.create_sequence_from(summary.labels)?)?;
+    Ok(stats)
+}
+
+/// Shows a desktop notification with `notify-send` on Linux and `osascript` on macOS.
+/// On other platforms or if it fails, the notification is logged instead.
+///
+/// On macOS, the title and body are passed as environment variables to prevent them from being
+/// interpreted as code.
+fn send_notification(title: &str, body: &str) {
+    let result = if cfg!(target_os = "linux") {
+        Command::new("notify-send").args([title, body]).output()
+    } else if cfg!(target_os = "macos") {
+        let script = "display notification (system attribute \"POMODORO_NOTIFICATION_BODY\") \
+            with title (system attribute \"POMODORO_NOTIFICATION_TITLE\")";
+        Command::new("osascript")
+            .args(["-e", script])
+            .env("POMODORO_NOTIFICATION_TITLE", title)
+            .env("POMODORO_NOTIFICATION_BODY", body)
+            .output()
+    } else {
+        log::info!("{}: {}", title, body);
+        return;
+    };
+    match result {
+        Ok(output) if output.status.success() => (),
+        _ => log::warn!("Failed to show the notification '{}: {}'.", title, body),
+    }
+}
+
+#[test]
+fn test_run_script() {
+    assert!(check_script("if then end").is_err());
+    assert!(check_script("error('not run')").is_ok());
+    run_script(
+        r#"
+        local stats = session_stats()
+        assert(math.type(stats.completed_sessions) == "integer")
+        assert(type(stats.labels) == "table")
+        assert(type(lock_screen) == "function")
+        assert(type(play_sound) == "function")
+        assert(type(send_notification) == "function")
+        "#,
+        &[],
+    )
+    .unwrap();
+    let error = run_script("error('failed')", &[]).unwrap_err();
+    assert!(error.to_string().contains("failed"), "{}", error);
+}
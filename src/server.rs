@@ -0,0 +1,416 @@
+//! This module contains the JSON-RPC 2.0 server started with `--server`, which controls the
+//! timer remotely, e.g. from a status bar or a script.
+//!
+//! The server listens on `127.0.0.1` for requests over HTTP and WebSocket with these methods:
+//! * `start` - Starts the next Pomodoro session or break the timer waits for.
+//! * `stop` - Stops the current Pomodoro session or break without its end event.
+//! * `pause` and `resume` - Pause and resume the current Pomodoro session or break.
+//! * `skipToEnd` - Skips to the end of the current Pomodoro session or break.
+//! * `status` - Gets the `TimerStatus`.
+//! * `configure` - Changes the durations and labels given by name, e.g.
+//!   `{"durationPomodoro": 50}`, which take effect at the start of the next phase, see
+//!   `RemoteOptions`. Other options, e.g. end events, cannot be changed remotely.
+//!
+//! The methods except `status` return `true` if they were applied, e.g. `false` if the timer
+//! isn't started yet.
+//!
+//! Every request must contain the token of the server in the header
+//! `Authorization: Bearer <token>`, see `load_or_create_token`. Requests from web pages of
+//! other hosts than `localhost`, i.e. with another `Origin` header, are rejected.
+use crate::pomodoro_options::PartialPomodoroOptions;
+use crate::remote::{RemoteCommand, RemoteControl, TimerStatus};
+use anyhow::{Context, Result};
+use http::{header, StatusCode};
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::middleware::http::HostFilterLayer;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse, RpcModule, Server};
+use jsonrpsee::types::ErrorObjectOwned;
+use serde::Deserialize;
+use std::fs;
+use std::future::Future;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::thread;
+use tower::{Layer, Service, ServiceBuilder};
+
+/// The port of the server if `--server` is given without a port.
+pub const DEFAULT_PORT: u16 = 7177;
+
+/// The environment variable with the token of the server. If it is not set, a random token is
+/// created, see `load_or_create_token`.
+pub const TOKEN_ENV: &str = "POMODORO_SERVER_TOKEN";
+
+/// The name of the file with the token in the runtime directory.
+const TOKEN_FILE_NAME: &str = "pomodoro-server.token";
+
+/// The options which can be changed with the method `configure`.
+///
+/// Only the durations and labels can be changed, so that a client of the server cannot start
+/// arbitrary programs with end events. Unknown options are rejected.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct RemoteOptions {
+    /// Overrides `duration_pomodoro`.
+    pub duration_pomodoro: Option<i32>,
+    /// Overrides `additional_duration`.
+    pub additional_duration: Option<i32>,
+    /// Overrides `duration_short_break`.
+    pub duration_short_break: Option<i32>,
+    /// Overrides `duration_long_break`.
+    pub duration_long_break: Option<i32>,
+    /// Overrides `work_label`.
+    pub work_label: Option<String>,
+    /// Overrides `break_label`.
+    pub break_label: Option<String>,
+}
+
+impl From<RemoteOptions> for PartialPomodoroOptions {
+    fn from(options: RemoteOptions) -> Self {
+        PartialPomodoroOptions {
+            duration_pomodoro: options.duration_pomodoro,
+            additional_duration: options.additional_duration,
+            duration_short_break: options.duration_short_break,
+            duration_long_break: options.duration_long_break,
+            work_label: options.work_label,
+            break_label: options.break_label,
+            ..Default::default()
+        }
+    }
+}
+
+/// Gets the path of the file the token of the server is written to,
+/// `$XDG_RUNTIME_DIR/pomodoro-server.token`.
+///
+/// If the runtime directory is unknown, e.g. on macOS, the file is in the temporary directory.
+#[must_use]
+pub fn token_file_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(TOKEN_FILE_NAME)
+}
+
+/// Gets the token of the server from `POMODORO_SERVER_TOKEN`, or else creates a random token
+/// and writes it to `token_file_path`, which only the current user can read on Unix.
+///
+/// # Errors
+///
+/// Returns an error if the variable is empty or the token cannot be created or written.
+pub fn load_or_create_token() -> Result<String> {
+    if let Ok(token) = std::env::var(TOKEN_ENV) {
+        if token.is_empty() {
+            anyhow::bail!("{} must not be empty.", TOKEN_ENV);
+        }
+        return Ok(token);
+    }
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).context("Failed to create the token of the JSON-RPC server")?;
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let path = token_file_path();
+    write_token_file(&path, &token)
+        .with_context(|| format!("Failed to write the token of the server to {:?}", path))?;
+    Ok(token)
+}
+
+/// Reads the token of a running server from `POMODORO_SERVER_TOKEN`, or else from
+/// `token_file_path`, e.g. to send requests to it.
+///
+/// # Errors
+///
+/// Returns an error if the variable is not set and the file cannot be read.
+pub fn read_token() -> Result<String> {
+    if let Ok(token) = std::env::var(TOKEN_ENV) {
+        return Ok(token);
+    }
+    let path = token_file_path();
+    fs::read_to_string(&path)
+        .map(|token| token.trim().to_string())
+        .with_context(|| format!("Failed to read the token of the server from {:?}", path))
+}
+
+/// Gets the status of the timer from the server on the port with the method `status`.
+///
+/// # Errors
+///
+/// Returns an error if no server listens on the port, it rejects the token or its response
+/// is invalid.
+pub fn query_status(port: u16, token: &str) -> Result<TimerStatus> {
+    let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "status" });
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(std::time::Duration::from_secs(5)))
+        .build()
+        .new_agent();
+    let response = agent
+        .post(&format!("http://127.0.0.1:{}", port))
+        .header("Authorization", &format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .send(&request.to_string())
+        .with_context(|| format!("Failed to query the JSON-RPC server on port {}", port))?
+        .into_body()
+        .read_to_string()?;
+    let mut response: serde_json::Value = serde_json::from_str(&response)?;
+    let status = response
+        .get_mut("result")
+        .map(serde_json::Value::take)
+        .with_context(|| format!("The JSON-RPC server returned an error: {}", response))?;
+    Ok(serde_json::from_value(status)?)
+}
+
+/// Writes the token to the file, which is replaced so that it is created with permissions
+/// only for the current user on Unix.
+fn write_token_file(path: &PathBuf, token: &str) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(token.as_bytes())
+}
+
+/// Starts the JSON-RPC server on the port in a background thread, which controls the timer
+/// with the remote control until the program exits.
+///
+/// # Arguments
+/// * `port` - The port on `127.0.0.1`, or 0 to use any free port.
+/// * `remote` - The remote control of the timer, e.g. passed to `start_pomodoro_with_remote_control`.
+/// * `token` - The token every request must contain, e.g. from `load_or_create_token`.
+///
+/// # Returns
+/// The address the server listens on.
+///
+/// # Errors
+///
+/// Returns an error if the port cannot be bound.
+pub fn start_server(port: u16, remote: RemoteControl, token: &str) -> Result<SocketAddr> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .context("Failed to start the runtime of the JSON-RPC server")?;
+    // Reject requests to other hosts than the loopback address, e.g. by DNS rebinding
+    let host_filter = HostFilterLayer::new(["localhost:*", "127.0.0.1:*"])
+        .context("Failed to create the host filter of the JSON-RPC server")?;
+    let middleware = ServiceBuilder::new().layer(host_filter).layer(AuthLayer {
+        token: token.into(),
+    });
+    let server = runtime
+        .block_on(
+            Server::builder()
+                .set_http_middleware(middleware)
+                .build(("127.0.0.1", port)),
+        )
+        .with_context(|| format!("Failed to bind the JSON-RPC server to port {}", port))?;
+    let address = server.local_addr()?;
+    let module = rpc_module(remote)?;
+    let handle = runtime.block_on(async { server.start(module) });
+    thread::Builder::new()
+        .name("json_rpc_server".to_string())
+        .spawn(move || runtime.block_on(handle.stopped()))
+        .context("Failed to spawn the thread of the JSON-RPC server")?;
+    log::info!("The JSON-RPC server listens on {}.", address);
+    Ok(address)
+}
+
+/// Creates the module with the methods of the server.
+fn rpc_module(remote: RemoteControl) -> Result<RpcModule<RemoteControl>> {
+    let mut module = RpcModule::new(remote);
+    for (name, command) in [
+        ("start", RemoteCommand::Start),
+        ("stop", RemoteCommand::Stop),
+        ("pause", RemoteCommand::Pause),
+        ("resume", RemoteCommand::Resume),
+        ("skipToEnd", RemoteCommand::SkipToEnd),
+    ] {
+        module.register_method(name, move |_, remote, _| remote.send(command))?;
+    }
+    module.register_method("status", |_, remote, _| {
+        Ok::<_, ErrorObjectOwned>(remote.status())
+    })?;
+    module.register_method("configure", |params, remote, _| {
+        let options = params.parse::<RemoteOptions>()?;
+        remote.configure(options.into());
+        Ok::<_, ErrorObjectOwned>(true)
+    })?;
+    Ok(module)
+}
+
+/// The HTTP middleware rejecting requests without the token of the server or from web pages of
+/// other hosts than `localhost`.
+#[derive(Debug, Clone)]
+struct AuthLayer {
+    token: Arc<str>,
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = Auth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Auth {
+            inner,
+            token: self.token.clone(),
+        }
+    }
+}
+
+/// The service of `AuthLayer`.
+#[derive(Debug, Clone)]
+struct Auth<S> {
+    inner: S,
+    token: Arc<str>,
+}
+
+impl<S, B> Service<HttpRequest<B>> for Auth<S>
+where
+    S: Service<HttpRequest<B>, Response = HttpResponse>,
+    S::Error: Into<BoxError> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<HttpResponse, BoxError>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: HttpRequest<B>) -> Self::Future {
+        let header_value = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let status = if header_value(header::ORIGIN).is_some_and(|origin| !is_local_origin(origin))
+        {
+            Some(StatusCode::FORBIDDEN)
+        } else if !header_value(header::AUTHORIZATION)
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| tokens_equal(token, &self.token))
+        {
+            Some(StatusCode::UNAUTHORIZED)
+        } else {
+            None
+        };
+        if let Some(status) = status {
+            log::warn!("Rejected a request to the JSON-RPC server: {}", status);
+            let mut response = HttpResponse::new(HttpBody::from(status.to_string()));
+            *response.status_mut() = status;
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+        let response = self.inner.call(request);
+        Box::pin(async move { response.await.map_err(Into::into) })
+    }
+}
+
+/// Checks if the origin of a web page, e.g. `http://localhost:8080`, is on the local machine.
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Compares the tokens in constant time, so that the time of a rejection doesn't reveal how
+/// many characters of the token were right.
+fn tokens_equal(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[test]
+fn test_server() {
+    let remote = RemoteControl::new();
+    let address = start_server(0, remote.clone(), "secret").unwrap();
+    let request = |method: &str, params: serde_json::Value| {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        ureq::post(&format!("http://{}", address))
+            .header("Content-Type", "application/json")
+            .send(&request.to_string())
+    };
+    let call = |method: &str, params: serde_json::Value| {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response = ureq::post(&format!("http://{}", address))
+            .header("Content-Type", "application/json")
+            .header("Authorization", "Bearer secret")
+            .send(&request.to_string())
+            .unwrap()
+            .into_body()
+            .read_to_string()
+            .unwrap();
+        serde_json::from_str::<serde_json::Value>(&response).unwrap()
+    };
+    assert_eq!(call("pause", serde_json::json!([]))["result"], false);
+    assert_eq!(
+        query_status(address.port(), "secret").unwrap(),
+        TimerStatus::default()
+    );
+    assert!(query_status(address.port(), "wrong").is_err());
+    assert_eq!(
+        call("status", serde_json::json!([]))["result"]["phase"],
+        serde_json::Value::Null
+    );
+    assert_eq!(
+        call("configure", serde_json::json!({ "durationPomodoro": 50 }))["result"],
+        true
+    );
+    assert_eq!(remote.take_options()[0].duration_pomodoro, Some(50));
+    assert!(
+        call("configure", serde_json::json!({ "durationPomodoro": "50" }))["error"].is_object()
+    );
+    // End events cannot be changed remotely
+    let response = call(
+        "configure",
+        serde_json::json!({ "endEventPomodoro": { "script": { "source": "os.exit()" } } }),
+    );
+    assert!(response["error"].is_object());
+    assert!(remote.take_options().is_empty());
+
+    // Requests without the token or from web pages of other hosts are rejected
+    assert!(matches!(
+        request("status", serde_json::json!([])),
+        Err(ureq::Error::StatusCode(401))
+    ));
+    let response = ureq::post(&format!("http://{}", address))
+        .header("Content-Type", "application/json")
+        .header("Authorization", "Bearer secret")
+        .header("Origin", "https://example.com")
+        .send(r#"{"jsonrpc": "2.0", "id": 1, "method": "status"}"#);
+    assert!(matches!(response, Err(ureq::Error::StatusCode(403))));
+}
+
+#[test]
+fn test_is_local_origin() {
+    assert!(is_local_origin("http://localhost:8080"));
+    assert!(is_local_origin("http://127.0.0.1"));
+    assert!(is_local_origin("http://[::1]:7177"));
+    assert!(!is_local_origin("https://example.com"));
+    assert!(!is_local_origin("http://localhost.example.com"));
+    assert!(!is_local_origin("null"));
+    assert!(tokens_equal("secret", "secret"));
+    assert!(!tokens_equal("secreT", "secret"));
+    assert!(!tokens_equal("secret2", "secret"));
+}
@@ -0,0 +1,325 @@
+//! This module contains a builder for sessions with custom phases, for library users who need
+//! more flexibility than the fixed alternation of Pomodoro sessions and breaks.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use locking_pomodoro_timer::end_events::EndEvent;
+//! use locking_pomodoro_timer::pomodoro_phase::PomodoroPhase;
+//! use locking_pomodoro_timer::session::SessionBuilder;
+//! use std::time::Duration;
+//!
+//! let session = SessionBuilder::new()
+//!     .add_phase(PomodoroPhase::Work, Duration::from_secs(50 * 60), EndEvent::DoNothing)
+//!     .add_phase(
+//!         PomodoroPhase::LongBreak,
+//!         Duration::from_secs(20 * 60),
+//!         EndEvent::PlayTts {
+//!             message: "The {phase} is over.".to_string(),
+//!             delay_before: None,
+//!         },
+//!     )
+//!     .build();
+//! session.run();
+//! ```
+//!
+//! To integrate the timer with a logging, UI or statistics of the application, run the session
+//! with `SessionCallbacks`:
+//!
+//! ```no_run
+//! # use locking_pomodoro_timer::session::{SessionBuilder, SessionCallbacks};
+//! let callbacks = SessionCallbacks {
+//!     on_phase_start: Some(Box::new(|phase| println!("The {} started.", phase.description()))),
+//!     on_session_complete: Some(Box::new(|summary| println!("{}", summary))),
+//!     ..SessionCallbacks::default()
+//! };
+//! # let session = SessionBuilder::new().build();
+//! session.run_with_callbacks(&callbacks);
+//! ```
+use crate::clock::current_clock;
+use crate::end_events::{start_end_event_for_phase, EndEvent};
+use crate::pomodoro_phase::PomodoroPhase;
+use crate::screen_lock::{LinuxLocker, DEFAULT_LINUX_LOCK_PRIORITY};
+use crate::statistics::{HistoryRecord, SessionSummary, Statistics};
+use crate::timer::Timer;
+use chrono::Local;
+use std::fmt;
+use std::time::Duration;
+
+/// A callback getting a phase.
+pub type PhaseCallback = Box<dyn Fn(PomodoroPhase) + Send>;
+
+/// A callback getting the record of a phase.
+pub type RecordCallback = Box<dyn Fn(&HistoryRecord) + Send>;
+
+/// A callback getting the summary of the Pomodoro sessions.
+pub type SummaryCallback = Box<dyn Fn(&SessionSummary) + Send>;
+
+/// Callbacks of the lifecycle of the timer for applications embedding it, e.g. to update their
+/// UI. The callbacks are called on the thread running the timer, so they should return quickly.
+///
+/// Used by `Session::run_with_callbacks` and `cli_utilities::start_pomodoro_with_callbacks`.
+#[derive(Default)]
+pub struct SessionCallbacks {
+    /// Called when a phase starts.
+    pub on_phase_start: Option<PhaseCallback>,
+    /// Called when a phase ended, whether it ran to the end, was quit early or aborted, after
+    /// its end event was started.
+    pub on_phase_end: Option<PhaseCallback>,
+    /// Called with the record of a phase when it ended, before `on_phase_end`, e.g. to update
+    /// the statistics of today. `cli_utilities` has written the record to the history file at
+    /// this point, a `Session` doesn't write the history file.
+    pub on_phase_recorded: Option<RecordCallback>,
+    /// Called when the timer finished with the summary of the Pomodoro sessions it ran. Not
+    /// called if no phase was started.
+    pub on_session_complete: Option<SummaryCallback>,
+}
+
+impl fmt::Debug for SessionCallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionCallbacks")
+            .field("on_phase_start", &self.on_phase_start.is_some())
+            .field("on_phase_end", &self.on_phase_end.is_some())
+            .field("on_phase_recorded", &self.on_phase_recorded.is_some())
+            .field("on_session_complete", &self.on_session_complete.is_some())
+            .finish()
+    }
+}
+
+impl SessionCallbacks {
+    /// Calls `on_phase_start` with the phase, if it is set.
+    pub(crate) fn phase_started(&self, phase: PomodoroPhase) {
+        if let Some(callback) = &self.on_phase_start {
+            callback(phase);
+        }
+    }
+
+    /// Calls `on_phase_end` with the phase, if it is set.
+    pub(crate) fn phase_ended(&self, phase: PomodoroPhase) {
+        if let Some(callback) = &self.on_phase_end {
+            callback(phase);
+        }
+    }
+
+    /// Calls `on_phase_recorded` with the record, if it is set.
+    pub(crate) fn phase_recorded(&self, record: &HistoryRecord) {
+        if let Some(callback) = &self.on_phase_recorded {
+            callback(record);
+        }
+    }
+
+    /// Calls `on_session_complete` with the summary of the records from the day of the first
+    /// record until today, if it is set and there are records.
+    pub(crate) fn session_completed(&self, records: Vec<HistoryRecord>) {
+        let (Some(callback), Some(first)) = (&self.on_session_complete, records.first()) else {
+            return;
+        };
+        let from = first.started_at.date_naive();
+        callback(&Statistics::new(records).summary(from, Local::now().date_naive()));
+    }
+}
+
+/// A phase of a `Session` with the end event started when it ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionPhase {
+    /// The phase.
+    pub phase: PomodoroPhase,
+    /// The duration of the phase.
+    pub duration: Duration,
+    /// The end event started when the phase ends.
+    pub end_event: EndEvent,
+}
+
+/// Builds a `Session` from phases in the order they are added.
+#[derive(Debug, Clone)]
+pub struct SessionBuilder {
+    phases: Vec<SessionPhase>,
+    linux_lock_priority: Vec<LinuxLocker>,
+}
+
+impl Default for SessionBuilder {
+    fn default() -> Self {
+        Self {
+            phases: Vec::new(),
+            linux_lock_priority: DEFAULT_LINUX_LOCK_PRIORITY.to_vec(),
+        }
+    }
+}
+
+impl SessionBuilder {
+    /// Creates a builder without phases.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a phase with its duration and an end event started when it ends.
+    #[must_use]
+    pub fn add_phase(
+        mut self,
+        phase: PomodoroPhase,
+        duration: Duration,
+        end_event: EndEvent,
+    ) -> Self {
+        self.phases.push(SessionPhase {
+            phase,
+            duration,
+            end_event,
+        });
+        self
+    }
+
+    /// Sets the order in which the lockers are tried on Linux by `lockScreen` end events.
+    #[must_use]
+    pub fn with_linux_lock_priority(mut self, linux_lock_priority: Vec<LinuxLocker>) -> Self {
+        self.linux_lock_priority = linux_lock_priority;
+        self
+    }
+
+    /// Builds the session.
+    #[must_use]
+    pub fn build(self) -> Session {
+        Session {
+            phases: self.phases,
+            linux_lock_priority: self.linux_lock_priority,
+        }
+    }
+}
+
+/// A sequence of phases, built with `SessionBuilder`.
+#[derive(Debug, Clone)]
+pub struct Session {
+    phases: Vec<SessionPhase>,
+    linux_lock_priority: Vec<LinuxLocker>,
+}
+
+impl Session {
+    /// Gets the phases in the order they are run.
+    #[must_use]
+    pub fn phases(&self) -> &[SessionPhase] {
+        &self.phases
+    }
+
+    /// Runs the phases in the order they were added and blocks until the last one ended.
+    ///
+    /// When a phase ends, its end event is started like in `start_end_event_for_phase` with the
+    /// phase and its duration, e.g. for the placeholders of `playTts`.
+    pub fn run(&self) {
+        self.run_with_callbacks(&SessionCallbacks::default());
+    }
+
+    /// Runs the phases like `run` and calls the callbacks when a phase starts and ends and
+    /// when the last phase ended. The summary counts every work phase as completed session.
+    pub fn run_with_callbacks(&self, callbacks: &SessionCallbacks) {
+        let mut records = Vec::new();
+        for session_phase in &self.phases {
+            log::info!(
+                "Starting the {} phase of {} seconds.",
+                session_phase.phase.description(),
+                session_phase.duration.as_secs()
+            );
+            callbacks.phase_started(session_phase.phase);
+            let started_at = Local::now();
+            let clock = current_clock();
+            let timer = Timer::with_clock(session_phase.duration, clock.clone());
+            timer.start();
+            while !timer.remaining().is_zero() {
+                clock.sleep(Duration::from_millis(250));
+            }
+            start_end_event_for_phase(
+                &session_phase.end_event,
+                session_phase.phase,
+                session_phase.duration,
+                &self.linux_lock_priority,
+            );
+            let record = HistoryRecord {
+                started_at,
+                phase: session_phase.phase,
+                duration_secs: session_phase.duration.as_secs(),
+                completed: true,
+                skipped: false,
+                label: None,
+                aborted: None,
+            };
+            callbacks.phase_recorded(&record);
+            records.push(record);
+            callbacks.phase_ended(session_phase.phase);
+        }
+        callbacks.session_completed(records);
+    }
+}
+
+#[test]
+fn test_session_builder() {
+    let session = SessionBuilder::new()
+        .add_phase(PomodoroPhase::Work, Duration::ZERO, EndEvent::DoNothing)
+        .add_phase(
+            PomodoroPhase::ShortBreak,
+            Duration::ZERO,
+            EndEvent::DoNothing,
+        )
+        .build();
+    let phases = session
+        .phases()
+        .iter()
+        .map(|session_phase| session_phase.phase)
+        .collect::<Vec<_>>();
+    assert_eq!(phases, vec![PomodoroPhase::Work, PomodoroPhase::ShortBreak]);
+    session.run();
+    assert!(SessionBuilder::new().build().phases().is_empty());
+}
+
+#[test]
+fn test_run_with_callbacks() {
+    use std::sync::{Arc, Mutex};
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let (start_calls, end_calls, summary_calls) = (calls.clone(), calls.clone(), calls.clone());
+    let record_calls = calls.clone();
+    let callbacks = SessionCallbacks {
+        on_phase_start: Some(Box::new(move |phase| {
+            start_calls.lock().unwrap().push(format!("start {}", phase))
+        })),
+        on_phase_end: Some(Box::new(move |phase| {
+            end_calls.lock().unwrap().push(format!("end {}", phase))
+        })),
+        on_phase_recorded: Some(Box::new(move |record| {
+            record_calls
+                .lock()
+                .unwrap()
+                .push(format!("record {}", record.phase))
+        })),
+        on_session_complete: Some(Box::new(move |summary| {
+            summary_calls
+                .lock()
+                .unwrap()
+                .push(format!("complete {}", summary.completed_sessions))
+        })),
+    };
+    SessionBuilder::new()
+        .add_phase(PomodoroPhase::Work, Duration::ZERO, EndEvent::DoNothing)
+        .add_phase(
+            PomodoroPhase::LongBreak,
+            Duration::ZERO,
+            EndEvent::DoNothing,
+        )
+        .build()
+        .run_with_callbacks(&callbacks);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [
+            "start work",
+            "record work",
+            "end work",
+            "start longBreak",
+            "record longBreak",
+            "end longBreak",
+            "complete 1"
+        ]
+    );
+
+    // Without phases, the session is not completed
+    calls.lock().unwrap().clear();
+    SessionBuilder::new().build().run_with_callbacks(&callbacks);
+    assert!(calls.lock().unwrap().is_empty());
+}
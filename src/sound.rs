@@ -0,0 +1,1218 @@
+//! This module contains the functions to play the sounds of the `Sound` end event.
+//!
+//! All sounds are played with the `rodio` crate. If no sound file is given or the
+//! file doesn't exist, the internal Alarm01.wav embedded in the binary is played.
+//!
+//! The output streams of all sounds are opened in a playback thread with the highest priority,
+//! which the audio threads of the backend inherit, so that the alarm doesn't stutter under heavy
+//! CPU load.
+//!
+//! With the `pw-cat` feature on Linux, the sounds of `play_sound` and `play_sound_from_bytes`
+//! are played on the default PipeWire sink instead if no backend or `SoundBackend::PipeWire` is
+//! chosen, see the `pipewire` module. rodio is used if PipeWire fails.
+//!
+//! rodio is only available with the `native` feature. In the browser, i.e. with the `wasm`
+//! feature on `wasm32`, the sounds of `play_sound` and `play_sound_from_bytes` are played with
+//! the Web Audio API instead, and the functions return without waiting for the sound to end.
+//! The other functions playing sounds are only available with the `native` feature.
+#[cfg(feature = "native")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "native")]
+use rodio::cpal::traits::HostTrait;
+#[cfg(feature = "native")]
+use rodio::cpal::{self, HostId};
+#[cfg(feature = "native")]
+use rodio::decoder::DecoderError;
+#[cfg(feature = "native")]
+use rodio::source::TakeDuration;
+#[cfg(feature = "native")]
+use rodio::{
+    Decoder, DeviceTrait, OutputStream, OutputStreamHandle, PlayError, Sink, Source, StreamError,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::io;
+#[cfg(feature = "native")]
+use std::io::{Cursor, Read, Seek};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "native")]
+use std::sync::atomic::Ordering;
+#[cfg(feature = "native")]
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+#[cfg(feature = "native")]
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "native")]
+use std::time::Instant;
+use thiserror::Error;
+#[cfg(feature = "native")]
+use thread_priority::{set_current_thread_priority, ThreadPriority};
+
+/// The internal default sound, embedded in the binary at compile time.
+const ALARM_SOUND: &[u8] = include_bytes!("../assets/Alarm01.wav");
+
+/// The interval in which the volume is decreased during a fade out.
+#[cfg(feature = "native")]
+const FADE_OUT_STEP: Duration = Duration::from_millis(50);
+
+/// The default length of the preview of `play_sound_preview`.
+pub const SOUND_PREVIEW_DURATION: Duration = Duration::from_secs(3);
+
+/// The interval in which the stop signal of a looping sound is checked.
+#[cfg(feature = "native")]
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The time `play_sound_with_format` waits for a sound of unknown length to end.
+#[cfg(feature = "native")]
+const UNKNOWN_LENGTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The time `play_sound_with_format` waits beyond the length of a sound, e.g. for the audio
+/// device to open and drain its buffer.
+#[cfg(feature = "native")]
+const TIMEOUT_MARGIN: Duration = Duration::from_secs(10);
+
+thread_local! {
+    /// The stop signal of the sounds played on this thread, see `with_stop_signal`.
+    static STOP_SIGNAL: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// The audio backend a sound is played with.
+///
+/// All backends of a platform are compiled in by default, no Cargo feature has to be enabled.
+/// `alsa`, `pulseAudio` and `pipeWire` are available on Linux and the BSDs, `wasapi` on Windows
+/// and `coreAudio` on macOS. PulseAudio and PipeWire are used through their ALSA plugins,
+/// i.e. the ALSA devices `pulse` and `pipewire`, which are installed with e.g. `alsa-plugins-pulseaudio`
+/// and `pipewire-alsa`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum SoundBackend {
+    /// Play directly on the default ALSA device, bypassing the sound server.
+    Alsa,
+    /// PulseAudio via the `pulse` ALSA device.
+    PulseAudio,
+    /// PipeWire via the `pipewire` ALSA device.
+    PipeWire,
+    /// The Windows Audio Session API.
+    Wasapi,
+    /// Core Audio of macOS.
+    CoreAudio,
+}
+
+impl SoundBackend {
+    /// Checks whether the backend is available on this platform, which requires the `native`
+    /// feature.
+    #[must_use]
+    pub fn is_available(self) -> bool {
+        #[cfg(feature = "native")]
+        {
+            self.host_id().is_some()
+        }
+        #[cfg(not(feature = "native"))]
+        {
+            false
+        }
+    }
+
+    /// Gets the host of `cpal`, which `rodio` uses for the output, of the backend.
+    #[cfg(feature = "native")]
+    fn host_id(self) -> Option<HostId> {
+        match self {
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd"
+            ))]
+            SoundBackend::Alsa | SoundBackend::PulseAudio | SoundBackend::PipeWire => {
+                Some(HostId::Alsa)
+            }
+            #[cfg(target_os = "windows")]
+            SoundBackend::Wasapi => Some(HostId::Wasapi),
+            #[cfg(target_os = "macos")]
+            SoundBackend::CoreAudio => Some(HostId::CoreAudio),
+            _ => None,
+        }
+    }
+
+    /// Gets the name of the ALSA device of the sound servers, or `None` for the default device.
+    #[cfg(feature = "native")]
+    fn device_name(self) -> Option<&'static str> {
+        match self {
+            SoundBackend::PulseAudio => Some("pulse"),
+            SoundBackend::PipeWire => Some("pipewire"),
+            _ => None,
+        }
+    }
+}
+
+/// The format of a sound file, to decode a file whose format is not detected correctly,
+/// e.g. a WAV file with another extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SoundFormat {
+    /// WAV.
+    Wav,
+    /// MP3.
+    Mp3,
+    /// Ogg Vorbis.
+    Ogg,
+    /// FLAC.
+    Flac,
+}
+
+#[cfg(feature = "native")]
+impl SoundFormat {
+    /// Decodes the data in this format without detecting the format.
+    fn decode<R>(self, data: R) -> Result<Decoder<R>, DecoderError>
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        match self {
+            SoundFormat::Wav => Decoder::new_wav(data),
+            SoundFormat::Mp3 => Decoder::new_mp3(data),
+            SoundFormat::Ogg => Decoder::new_vorbis(data),
+            SoundFormat::Flac => Decoder::new_flac(data),
+        }
+    }
+}
+
+/// Decodes the data in the given format, or detects the format if it is `None`.
+#[cfg(feature = "native")]
+fn decode<R>(data: R, format: Option<SoundFormat>) -> Result<Decoder<R>, DecoderError>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    match format {
+        Some(format) => format.decode(data),
+        None => Decoder::new(data),
+    }
+}
+
+/// Errors that can occur when playing a sound.
+#[derive(Debug, Error)]
+pub enum PlaySoundError {
+    /// No audio output stream is available.
+    #[cfg(feature = "native")]
+    #[error("Failed to create output stream.")]
+    OutputStream(#[from] StreamError),
+    /// The sink for the output stream cannot be created.
+    #[cfg(feature = "native")]
+    #[error("Failed to create sink.")]
+    Sink(#[from] PlayError),
+    /// The sound data cannot be decoded.
+    #[cfg(feature = "native")]
+    #[error("Failed to decode sound.")]
+    Decode(#[from] DecoderError),
+    /// The Web Audio API of the browser failed to play the sound.
+    #[error("Failed to play the sound with the Web Audio API: {0}")]
+    WebAudio(String),
+    /// Sounds cannot be played without the `native` feature outside of the browser.
+    #[error("Playing sounds requires the `native` feature, or the `wasm` feature in the browser.")]
+    NotSupported,
+    /// The sound file cannot be read.
+    #[error("Failed to read sound file: {path:?}")]
+    ReadFile {
+        /// The path of the sound file.
+        path: PathBuf,
+        /// The error of reading the file.
+        #[source]
+        source: io::Error,
+    },
+    /// The sound did not end within the timeout, e.g. because the audio device hangs.
+    #[error("Playing the sound timed out.")]
+    Timeout,
+    /// The thread playing the sound could not be started or panicked.
+    #[error("The playback thread could not be started or panicked.")]
+    PlaybackThread,
+    /// The audio backend is not available on this platform.
+    #[error("The sound backend {0:?} is not available on this platform.")]
+    UnsupportedBackend(SoundBackend),
+    /// The audio backend has no output device.
+    #[error("The sound backend {0:?} has no output device.")]
+    NoOutputDevice(SoundBackend),
+    /// There is no audio output device at all, e.g. on a headless server.
+    #[error("No audio output device is available. Use another end event than sound on machines without audio hardware.")]
+    NoAudioDevice,
+}
+
+/// Checks whether the default audio host has an output device, without opening it.
+///
+/// In the browser, the Web Audio API is assumed to be available.
+#[must_use]
+pub fn is_audio_available() -> bool {
+    #[cfg(feature = "native")]
+    {
+        cpal::default_host().default_output_device().is_some()
+    }
+    #[cfg(not(feature = "native"))]
+    {
+        cfg!(all(feature = "wasm", target_arch = "wasm32"))
+    }
+}
+
+/// Plays the sounds of `Sound` end events, see `end_events::start_end_event_with_player`.
+///
+/// Tests can use a player which records the sounds instead of playing them, so they don't need
+/// an audio device, and applications can play the sounds with their own audio system.
+pub trait SoundPlayer {
+    /// Plays the sound file, or the internal sound if it is `None`, and blocks until it ended.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sound cannot be played.
+    fn play(&mut self, filepath_sound: &Option<PathBuf>) -> Result<()>;
+}
+
+/// Plays the sounds with `play_sound_with_format` on an audio device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemSoundPlayer {
+    /// The audio backend, or `None` for the default output of the platform.
+    pub backend: Option<SoundBackend>,
+    /// The format of the sound files, or `None` to detect it from the data.
+    pub format: Option<SoundFormat>,
+}
+
+impl SoundPlayer for SystemSoundPlayer {
+    fn play(&mut self, filepath_sound: &Option<PathBuf>) -> Result<()> {
+        Ok(play_sound_with_format(
+            filepath_sound,
+            self.backend,
+            self.format,
+        )?)
+    }
+}
+
+/// A `SoundPlayer` which records the sounds instead of playing them.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct FakeSoundPlayer {
+    /// The sound files played so far.
+    pub calls: Vec<Option<PathBuf>>,
+}
+
+#[cfg(test)]
+impl SoundPlayer for FakeSoundPlayer {
+    fn play(&mut self, filepath_sound: &Option<PathBuf>) -> Result<()> {
+        self.calls.push(filepath_sound.clone());
+        Ok(())
+    }
+}
+
+/// Plays a sound. If filepath_sound is None or the file doesn't exist, plays the internal embedded sound.
+/// If the filepath is provided but the file doesn't exist, prints a warning.
+///
+/// # Errors
+///
+/// Returns `PlaySoundError::NoAudioDevice` if there is no audio output device, or another error
+/// if the output stream cannot be created or the sound cannot be read or decoded.
+pub fn play_sound(filepath_sound: &Option<PathBuf>) -> Result<(), PlaySoundError> {
+    play_sound_with_backend(filepath_sound, None)
+}
+
+/// Plays a sound like `play_sound` on Tokio's blocking thread pool, so that async applications
+/// can await it without blocking their runtime.
+///
+/// The output stream and sink are created and dropped by the blocking task and never cross an
+/// await point. The future must be awaited within a Tokio runtime, it doesn't borrow the path.
+///
+/// This function is only available with the `async` feature.
+///
+/// # Errors
+///
+/// Returns an error like `play_sound`, or `PlaySoundError::PlaybackThread` if the blocking
+/// task panicked or was cancelled.
+#[cfg(feature = "async")]
+pub fn play_sound_async(
+    filepath_sound: &Option<PathBuf>,
+) -> impl std::future::Future<Output = Result<(), PlaySoundError>> + Send + 'static {
+    let filepath_sound = filepath_sound.clone();
+    async move {
+        tokio::task::spawn_blocking(move || play_sound(&filepath_sound))
+            .await
+            .map_err(|_| PlaySoundError::PlaybackThread)?
+    }
+}
+
+/// Plays a sound like `play_sound` with the given audio backend.
+///
+/// If the backend is `None` or cannot be opened, the default output of the platform is used
+/// and a warning is printed in the latter case.
+///
+/// # Errors
+///
+/// Returns an error like `play_sound`.
+pub fn play_sound_with_backend(
+    filepath_sound: &Option<PathBuf>,
+    backend: Option<SoundBackend>,
+) -> Result<(), PlaySoundError> {
+    play_sound_with_format(filepath_sound, backend, None)
+}
+
+/// Plays a sound like `play_sound_with_backend` and decodes the sound file in the given format.
+///
+/// If the format is `None`, it is detected from the data. The format of the internal sound is
+/// always detected.
+///
+/// The playback is stopped if the sound did not end 10 seconds after its length, or after 5
+/// minutes if its length is unknown, so that a hung audio device doesn't block the session.
+///
+/// # Errors
+///
+/// Returns `PlaySoundError::Timeout` if the sound did not end in time, or another error like
+/// `play_sound`.
+pub fn play_sound_with_format(
+    filepath_sound: &Option<PathBuf>,
+    backend: Option<SoundBackend>,
+    format: Option<SoundFormat>,
+) -> Result<(), PlaySoundError> {
+    match external_sound_file(filepath_sound) {
+        Some(path) => {
+            let data = std::fs::read(path).map_err(|source| PlaySoundError::ReadFile {
+                path: path.clone(),
+                source,
+            })?;
+            play_bytes(data, backend, format)
+        }
+        None => play_sound_from_bytes_with_backend(ALARM_SOUND, backend),
+    }
+}
+
+/// Decodes the sound data in the given format and plays it with the backend until it ended,
+/// or stops it after the timeout of `playback_timeout`.
+///
+/// # Errors
+///
+/// Returns an error like `play_sound_with_format`.
+#[cfg(feature = "native")]
+fn play_bytes(
+    data: Vec<u8>,
+    backend: Option<SoundBackend>,
+    format: Option<SoundFormat>,
+) -> Result<(), PlaySoundError> {
+    let source = decode(Cursor::new(data), format)?;
+    let timeout = playback_timeout(source.total_duration());
+    play_source_with_timeout(source, backend, timeout)
+}
+
+/// Plays the sound data with the Web Audio API of the browser. The backend and the format are
+/// ignored, the browser detects the format of the data.
+///
+/// The data is decoded and played asynchronously, so the function returns immediately and
+/// errors of decoding or playing the sound are only logged.
+///
+/// # Errors
+///
+/// Returns `PlaySoundError::WebAudio` if the audio context cannot be created.
+#[cfg(all(not(feature = "native"), feature = "wasm", target_arch = "wasm32"))]
+fn play_bytes(
+    data: Vec<u8>,
+    _backend: Option<SoundBackend>,
+    _format: Option<SoundFormat>,
+) -> Result<(), PlaySoundError> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let web_audio_error = |e: wasm_bindgen::JsValue| PlaySoundError::WebAudio(format!("{:?}", e));
+    let context = web_sys::AudioContext::new().map_err(web_audio_error)?;
+    let decoded = context
+        .decode_audio_data(&js_sys::Uint8Array::from(data.as_slice()).buffer())
+        .map_err(web_audio_error)?;
+    wasm_bindgen_futures::spawn_local(async move {
+        let played = async {
+            let buffer = JsFuture::from(decoded)
+                .await?
+                .dyn_into::<web_sys::AudioBuffer>()?;
+            let source = context.create_buffer_source()?;
+            source.set_buffer(Some(&buffer));
+            source.connect_with_audio_node(&context.destination())?;
+            source.start()
+        };
+        if let Err(e) = played.await {
+            log::warn!("{}", web_audio_error(e));
+        }
+    });
+    Ok(())
+}
+
+/// Fails to play the sound data, since neither rodio nor the Web Audio API is available.
+///
+/// # Errors
+///
+/// Always returns `PlaySoundError::NotSupported`.
+#[cfg(not(any(feature = "native", all(feature = "wasm", target_arch = "wasm32"))))]
+fn play_bytes(
+    _data: Vec<u8>,
+    _backend: Option<SoundBackend>,
+    _format: Option<SoundFormat>,
+) -> Result<(), PlaySoundError> {
+    Err(PlaySoundError::NotSupported)
+}
+
+/// Gets the time to wait for a sound of the given length to end.
+#[cfg(feature = "native")]
+fn playback_timeout(length: Option<Duration>) -> Duration {
+    length.map_or(UNKNOWN_LENGTH_TIMEOUT, |length| length + TIMEOUT_MARGIN)
+}
+
+/// Plays the decoded sound with the backend until it ended, or stops it after the timeout.
+///
+/// # Errors
+///
+/// Returns `PlaySoundError::Timeout` if the sound did not end within the timeout, or another
+/// error if no output stream is available.
+#[cfg(feature = "native")]
+fn play_source_with_timeout(
+    source: Decoder<Cursor<Vec<u8>>>,
+    backend: Option<SoundBackend>,
+    timeout: Duration,
+) -> Result<(), PlaySoundError> {
+    #[cfg(all(feature = "pw-cat", target_os = "linux"))]
+    if matches!(backend, None | Some(SoundBackend::PipeWire)) {
+        return play_source_on_pipewire(source, backend, timeout);
+    }
+    let stop_signal = STOP_SIGNAL.with_borrow(Clone::clone);
+    play_in_playback_thread(backend, Some(timeout), move |sink, stop| {
+        sink.append(source);
+        play_until_end_or_stopped(sink, stop, stop_signal.as_deref());
+        Ok(())
+    })
+}
+
+/// Plays the decoded sound on the default PipeWire sink like `play_source_with_timeout`, or with
+/// rodio on the backend if PipeWire fails, see `pipewire::play_samples`.
+///
+/// # Errors
+///
+/// Returns an error like `play_source_with_timeout`.
+#[cfg(all(feature = "pw-cat", target_os = "linux"))]
+fn play_source_on_pipewire(
+    source: Decoder<Cursor<Vec<u8>>>,
+    backend: Option<SoundBackend>,
+    timeout: Duration,
+) -> Result<(), PlaySoundError> {
+    let stop_signal = STOP_SIGNAL.with_borrow(Clone::clone);
+    let (channels, sample_rate) = (source.channels(), source.sample_rate());
+    let samples: Vec<i16> = source.collect();
+    run_playback_thread(Some(timeout), move |stop| {
+        let is_stopped = || {
+            stop.load(Ordering::Relaxed)
+                || stop_signal
+                    .as_ref()
+                    .is_some_and(|stop| stop.load(Ordering::Relaxed))
+        };
+        match crate::pipewire::play_samples(&samples, channels, sample_rate, is_stopped) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                log::warn!("Failed to play the sound with PipeWire, using rodio: {}", e);
+                let (_stream, stream_handle) = open_output_stream_or_default(backend)?;
+                let sink = Sink::try_new(&stream_handle)?;
+                sink.append(rodio::buffer::SamplesBuffer::new(
+                    channels,
+                    sample_rate,
+                    samples,
+                ));
+                play_until_end_or_stopped(&sink, stop, stop_signal.as_deref());
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Opens an output stream of the backend like `open_output_stream_or_default` and a sink for
+/// it in a playback thread, see `run_playback_thread`, and plays the sound on the sink there.
+///
+/// All sounds are played with this function, so that they are all played with the highest
+/// thread priority.
+///
+/// # Errors
+///
+/// Returns an error if no output stream is available, or an error like `run_playback_thread`.
+#[cfg(feature = "native")]
+fn play_in_playback_thread<F>(
+    backend: Option<SoundBackend>,
+    timeout: Option<Duration>,
+    play: F,
+) -> Result<(), PlaySoundError>
+where
+    F: FnOnce(&Sink, &AtomicBool) -> Result<(), PlaySoundError> + Send + 'static,
+{
+    run_playback_thread(timeout, move |stop| {
+        // The output stream cannot be moved between threads, so it is created here
+        let (_stream, stream_handle) = open_output_stream_or_default(backend)?;
+        let sink = Sink::try_new(&stream_handle)?;
+        play(&sink, stop)
+    })
+}
+
+/// Waits until the sink played its sounds or one of the stop signals is set, and stops it.
+#[cfg(feature = "native")]
+fn play_until_end_or_stopped(sink: &Sink, stop: &AtomicBool, stop_signal: Option<&AtomicBool>) {
+    let is_stopped = || {
+        stop.load(Ordering::Relaxed)
+            || stop_signal.is_some_and(|stop_signal| stop_signal.load(Ordering::Relaxed))
+    };
+    while !sink.empty() && !is_stopped() {
+        thread::sleep(STOP_CHECK_INTERVAL);
+    }
+    sink.stop();
+}
+
+/// Runs the function and stops the sounds it plays on this thread like `play_sound_with_format`
+/// as soon as `stop` is set, e.g. to stop the sounds of end events with the media keys, see
+/// `mpris::MprisPlayer`. The function returns normally, only the sounds are cut short.
+pub fn with_stop_signal<T>(stop: Arc<AtomicBool>, f: impl FnOnce() -> T) -> T {
+    /// Restores the previous stop signal, also if the function panics.
+    struct Restore(Option<Arc<AtomicBool>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            STOP_SIGNAL.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(STOP_SIGNAL.replace(Some(stop)));
+    f()
+}
+
+/// Runs the playback in a new playback thread and waits until it returns.
+///
+/// If the timeout expires first, the stop signal passed to the playback is set, so that it
+/// stops the sink and drops it as soon as the audio device responds again, and
+/// `PlaySoundError::Timeout` is returned without waiting any longer.
+///
+/// # Errors
+///
+/// Returns the error of the playback, `PlaySoundError::Timeout` or
+/// `PlaySoundError::PlaybackThread` if the thread could not be started or panicked.
+#[cfg(feature = "native")]
+fn run_playback_thread<F>(timeout: Option<Duration>, playback: F) -> Result<(), PlaySoundError>
+where
+    F: FnOnce(&AtomicBool) -> Result<(), PlaySoundError> + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_playback = stop.clone();
+    let (sender, receiver) = mpsc::channel();
+    let thread = playback_thread_builder()
+        .spawn(move || {
+            raise_thread_priority();
+            let _ = sender.send(playback(&stop_playback));
+        })
+        .map_err(|_| PlaySoundError::PlaybackThread)?;
+    let result = match timeout {
+        Some(timeout) => receiver.recv_timeout(timeout),
+        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match result {
+        Ok(result) => {
+            let _ = thread.join();
+            result
+        }
+        Err(RecvTimeoutError::Timeout) => {
+            stop.store(true, Ordering::Relaxed);
+            Err(PlaySoundError::Timeout)
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(PlaySoundError::PlaybackThread),
+    }
+}
+
+/// Creates the builder of the threads which play a sound.
+#[cfg(feature = "native")]
+fn playback_thread_builder() -> thread::Builder {
+    thread::Builder::new().name("sound_playback".to_string())
+}
+
+/// Sets the priority of the current thread to the highest one, so that the threads of the audio
+/// backend spawned by it are preferred by the scheduler. Failures are only logged, as real-time
+/// priorities usually require elevated permissions, e.g. `CAP_SYS_NICE` on Linux.
+#[cfg(feature = "native")]
+fn raise_thread_priority() {
+    if let Err(e) = set_current_thread_priority(ThreadPriority::Max) {
+        log::debug!("Failed to raise the priority of the playback thread: {}", e);
+    }
+}
+
+/// Opens an output stream of the audio backend.
+///
+/// # Errors
+///
+/// Returns an error if the backend is not available on this platform or has no usable output device.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn open_output_stream(
+    backend: SoundBackend,
+) -> Result<(OutputStream, OutputStreamHandle), PlaySoundError> {
+    let host = backend
+        .host_id()
+        .and_then(|host_id| cpal::host_from_id(host_id).ok())
+        .ok_or(PlaySoundError::UnsupportedBackend(backend))?;
+    let device = match backend.device_name() {
+        Some(name) => host.output_devices().ok().and_then(|mut devices| {
+            devices.find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        }),
+        None => host.default_output_device(),
+    }
+    .ok_or(PlaySoundError::NoOutputDevice(backend))?;
+    Ok(OutputStream::try_from_device(&device)?)
+}
+
+/// Opens an output stream of the backend, or of the default output if the backend is `None`
+/// or cannot be opened.
+///
+/// # Errors
+///
+/// Returns an error if the default output stream cannot be created.
+#[cfg(feature = "native")]
+fn open_output_stream_or_default(
+    backend: Option<SoundBackend>,
+) -> Result<(OutputStream, OutputStreamHandle), PlaySoundError> {
+    if let Some(backend) = backend {
+        match open_output_stream(backend) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => log::warn!("{} Using the default sound output.", e),
+        }
+    }
+    open_default_output_stream()
+}
+
+/// Opens an output stream of the default output device.
+///
+/// # Errors
+///
+/// Returns `PlaySoundError::NoAudioDevice` if there is no output device, or
+/// `PlaySoundError::OutputStream` if it cannot be opened.
+#[cfg(feature = "native")]
+fn open_default_output_stream() -> Result<(OutputStream, OutputStreamHandle), PlaySoundError> {
+    if !is_audio_available() {
+        return Err(PlaySoundError::NoAudioDevice);
+    }
+    Ok(OutputStream::try_default()?)
+}
+
+/// Plays a sound from the given bytes, e.g. a sound embedded with `include_bytes!`.
+///
+/// The data can be in any format supported by `rodio`, like WAV, MP3, FLAC or Vorbis.
+/// The function returns when the sound ends, or after a timeout like `play_sound_with_format`.
+/// The internal default sound is played with this function as well.
+///
+/// # Errors
+///
+/// Returns an error if no output stream is available or the data cannot be decoded.
+pub fn play_sound_from_bytes(data: &[u8]) -> Result<(), PlaySoundError> {
+    play_sound_from_bytes_with_backend(data, None)
+}
+
+/// Plays a sound from the given bytes like `play_sound_from_bytes` with the given audio backend,
+/// which is chosen like in `play_sound_with_backend`.
+///
+/// # Errors
+///
+/// Returns an error like `play_sound_from_bytes`.
+pub fn play_sound_from_bytes_with_backend(
+    data: &[u8],
+    backend: Option<SoundBackend>,
+) -> Result<(), PlaySoundError> {
+    // The sink requires a 'static source, so the data is copied.
+    play_bytes(data.to_vec(), backend, None)
+}
+
+/// Plays a sound repeatedly until `stop` is set.
+///
+/// The sound is chosen like in `play_sound` and appended to the sink again each time it ends.
+/// The stop signal is checked every 50 ms, so the playback also stops in the middle of a repetition.
+///
+/// # Errors
+///
+/// Returns an error if no output stream is available or the sound cannot be read or decoded.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn play_sound_looping(
+    filepath_sound: &Option<PathBuf>,
+    stop: Arc<AtomicBool>,
+) -> Result<(), PlaySoundError> {
+    let data = read_sound_data(filepath_sound)?;
+    // The first repetition is decoded up front, so that invalid data is reported before the
+    // output stream is opened.
+    let first = decode(Cursor::new(data.clone()), None)?;
+    play_in_playback_thread(None, None, move |sink, _| {
+        sink.append(first);
+        loop {
+            while !sink.empty() {
+                if stop.load(Ordering::Relaxed) {
+                    sink.stop();
+                    return Ok(());
+                }
+                thread::sleep(STOP_CHECK_INTERVAL);
+            }
+            if stop.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            append_bytes(sink, &data)?;
+        }
+    })
+}
+
+/// Plays a sound, but returns at the latest after the timeout.
+///
+/// The sound is chosen like in `play_sound` and played in a separate thread, so that a hung
+/// audio device doesn't block the caller forever. If the timeout expires, the playback is
+/// stopped and the sink is dropped as soon as the audio device responds again.
+///
+/// # Errors
+///
+/// Returns `PlaySoundError::Timeout` if the sound did not end within the timeout, or another
+/// error if no output stream is available or the sound cannot be read or decoded.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn play_sound_with_timeout(
+    filepath_sound: &Option<PathBuf>,
+    timeout: Duration,
+) -> Result<(), PlaySoundError> {
+    let (data, format) = read_sound_file(filepath_sound, None)?;
+    let source = decode(Cursor::new(data), format)?;
+    play_source_with_timeout(source, None, timeout)
+}
+
+/// Plays the beginning of a sound as preview, e.g. to audition a long ambient track, and stops
+/// the playback after the duration, see `SOUND_PREVIEW_DURATION`. Blocks until then or until
+/// the sound ended.
+///
+/// The sound is chosen like in `play_sound`.
+///
+/// # Errors
+///
+/// Returns an error if the sound cannot be read or decoded, or no output stream is available.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn play_sound_preview(
+    filepath_sound: &Option<PathBuf>,
+    duration: Duration,
+) -> Result<(), PlaySoundError> {
+    let (data, format) = read_sound_file(filepath_sound, None)?;
+    let source = preview_source(data, format, duration)?;
+    play_in_playback_thread(None, None, move |sink, _| {
+        sink.append(source);
+        sink.sleep_until_end();
+        Ok(())
+    })
+}
+
+/// Decodes the sound data in the given format and truncates it to the duration.
+///
+/// # Errors
+///
+/// Returns an error if the data cannot be decoded.
+#[cfg(feature = "native")]
+fn preview_source(
+    data: Vec<u8>,
+    format: Option<SoundFormat>,
+    duration: Duration,
+) -> Result<TakeDuration<Decoder<Cursor<Vec<u8>>>>, DecoderError> {
+    Ok(decode(Cursor::new(data), format)?.take_duration(duration))
+}
+
+/// Plays a sound and ends the playback gracefully with a linear fade out.
+///
+/// The volume starts decreasing at `fade_out_start` and reaches zero at
+/// `fade_out_start + fade_out_duration`, where the playback is stopped.
+/// If the sound ends before, the function returns when the sound ends.
+/// The sound is chosen like in `play_sound`.
+///
+/// # Errors
+///
+/// Returns an error if no output stream is available or the sound cannot be decoded.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn play_sound_with_fade_out(
+    filepath_sound: &Option<PathBuf>,
+    fade_out_start: Duration,
+    fade_out_duration: Duration,
+) -> Result<()> {
+    let (data, format) = read_sound_file(filepath_sound, None)?;
+    let source = decode(Cursor::new(data), format)?;
+    play_in_playback_thread(None, None, move |sink, _| {
+        sink.append(source);
+        fade_out(sink, fade_out_start, fade_out_duration);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Plays the sounds of the sink with a fade out like `play_sound_with_fade_out` and stops it at
+/// the end of the fade out or when the sounds ended.
+#[cfg(feature = "native")]
+fn fade_out(sink: &Sink, fade_out_start: Duration, fade_out_duration: Duration) {
+    let start_time = Instant::now();
+    let fade_out_end = fade_out_start + fade_out_duration;
+    while !sink.empty() {
+        let elapsed_time = start_time.elapsed();
+        if elapsed_time >= fade_out_end {
+            break;
+        }
+        if elapsed_time > fade_out_start {
+            let progress =
+                (elapsed_time - fade_out_start).as_secs_f32() / fade_out_duration.as_secs_f32();
+            sink.set_volume(1.0 - progress);
+        }
+        thread::sleep(FADE_OUT_STEP);
+    }
+    sink.stop();
+}
+
+/// Checks that the sound can be played by decoding it without playing it.
+///
+/// The sound is chosen like in `play_sound`, but a missing file is an error instead of
+/// falling back to the internal sound.
+///
+/// # Errors
+///
+/// Returns an error if the sound file doesn't exist or cannot be opened or decoded.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn check_sound(filepath_sound: &Option<PathBuf>) -> Result<()> {
+    check_sound_with_format(filepath_sound, None)
+}
+
+/// Checks that the sound can be played like `check_sound` and decodes the sound file in the
+/// given format.
+///
+/// # Errors
+///
+/// Returns an error like `check_sound`.
+///
+/// This function is only available with the `native` feature.
+#[cfg(feature = "native")]
+pub fn check_sound_with_format(
+    filepath_sound: &Option<PathBuf>,
+    format: Option<SoundFormat>,
+) -> Result<()> {
+    match filepath_sound {
+        Some(path) if !path.as_os_str().is_empty() => {
+            let sound_file = std::fs::File::open(path)
+                .with_context(|| format!("Failed to open sound file: {:?}", path))?;
+            decode(sound_file, format)
+                .with_context(|| format!("Failed to decode sound file: {:?}", path))?;
+        }
+        _ => {
+            Decoder::new(Cursor::new(ALARM_SOUND))
+                .context("Failed to decode internal sound file.")?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the data of the sound chosen like in `play_sound`.
+///
+/// # Errors
+///
+/// Returns an error if the sound file cannot be read.
+#[cfg(feature = "native")]
+fn read_sound_data(filepath_sound: &Option<PathBuf>) -> Result<Vec<u8>, PlaySoundError> {
+    read_sound_file(filepath_sound, None).map(|(data, _)| data)
+}
+
+/// Reads the data of the sound chosen like in `play_sound` together with the format it is
+/// decoded in.
+///
+/// The format is the given one for a sound file and `None` for the internal sound, which is
+/// MP3 in a WAV container and only decoded with format detection.
+///
+/// # Errors
+///
+/// Returns an error if the sound file cannot be read.
+#[cfg(feature = "native")]
+fn read_sound_file(
+    filepath_sound: &Option<PathBuf>,
+    format: Option<SoundFormat>,
+) -> Result<(Vec<u8>, Option<SoundFormat>), PlaySoundError> {
+    match external_sound_file(filepath_sound) {
+        Some(path) => std::fs::read(path)
+            .map(|data| (data, format))
+            .map_err(|source| PlaySoundError::ReadFile {
+                path: path.clone(),
+                source,
+            }),
+        None => Ok((ALARM_SOUND.to_vec(), None)),
+    }
+}
+
+/// Decodes the sound data and appends it to the sink.
+///
+/// # Errors
+///
+/// Returns an error if the data cannot be decoded.
+#[cfg(feature = "native")]
+fn append_bytes(sink: &Sink, data: &[u8]) -> Result<(), DecoderError> {
+    append_bytes_with_format(sink, data, None)
+}
+
+/// Decodes the sound data in the given format and appends it to the sink.
+/// If the format is `None`, it is detected from the data.
+///
+/// # Errors
+///
+/// Returns an error if the data cannot be decoded.
+#[cfg(feature = "native")]
+fn append_bytes_with_format(
+    sink: &Sink,
+    data: &[u8],
+    format: Option<SoundFormat>,
+) -> Result<(), DecoderError> {
+    // The sink requires a 'static source, so the data is copied.
+    let source = decode(Cursor::new(data.to_vec()), format)?;
+    sink.append(source);
+    Ok(())
+}
+
+/// Gets the path of the external sound file to be played.
+///
+/// Returns `None` if filepath_sound is None, empty or the file doesn't exist, in which case
+/// the internal embedded sound should be played. If the filepath is provided but the file
+/// doesn't exist, prints a warning.
+fn external_sound_file(filepath_sound: &Option<PathBuf>) -> Option<&PathBuf> {
+    let path = filepath_sound.as_ref()?;
+    if path.as_os_str().is_empty() {
+        // Empty path - use internal sound
+        None
+    } else if !path.is_file() {
+        // Path provided but file doesn't exist - warn and use internal sound
+        log::warn!(
+            "Sound file not found: {:?}. Using internal default sound instead.",
+            path
+        );
+        None
+    } else {
+        // Valid file path - use external sound
+        Some(path)
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_play_sound_async() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    // The manifest cannot be decoded, so it fails with or without an audio device
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert!(runtime.block_on(play_sound_async(&Some(manifest))).is_err());
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_play_sound_from_bytes() {
+    // The data is decoded before the output stream is opened, so this fails without an audio device
+    assert!(matches!(
+        play_sound_from_bytes(b"no sound"),
+        Err(PlaySoundError::Decode(_))
+    ));
+    assert!(matches!(
+        play_sound_from_bytes_with_backend(b"no sound", Some(SoundBackend::Alsa)),
+        Err(PlaySoundError::Decode(_))
+    ));
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_play_sound_looping() {
+    // The manifest cannot be decoded, so it fails with or without an audio device
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert!(matches!(
+        play_sound_looping(&Some(manifest), Arc::new(AtomicBool::new(false))),
+        Err(PlaySoundError::Decode(_))
+    ));
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_preview_source() {
+    let source = preview_source(ALARM_SOUND.to_vec(), None, Duration::from_secs(1)).unwrap();
+    let samples_per_second = source.sample_rate() as usize * usize::from(source.channels());
+    let samples = source.count();
+    assert!(samples > 0 && samples <= samples_per_second, "{}", samples);
+    assert!(preview_source(b"no sound".to_vec(), None, SOUND_PREVIEW_DURATION).is_err());
+
+    // The manifest cannot be decoded, so it fails with or without an audio device
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert!(matches!(
+        play_sound_preview(&Some(manifest), SOUND_PREVIEW_DURATION),
+        Err(PlaySoundError::Decode(_))
+    ));
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_append_bytes() {
+    let (sink, _queue) = Sink::new_idle();
+    assert!(append_bytes(&sink, ALARM_SOUND).is_ok());
+    assert_eq!(sink.len(), 1);
+    assert!(append_bytes(&sink, b"no sound").is_err());
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_append_bytes_with_format() {
+    let (sink, _queue) = Sink::new_idle();
+    assert!(append_bytes_with_format(&sink, ALARM_SOUND, None).is_ok());
+    assert!(append_bytes_with_format(&sink, ALARM_SOUND, Some(SoundFormat::Mp3)).is_ok());
+    assert!(append_bytes_with_format(&sink, ALARM_SOUND, Some(SoundFormat::Flac)).is_err());
+    assert_eq!(sink.len(), 2);
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_playback_timeout() {
+    assert_eq!(
+        playback_timeout(Some(Duration::from_secs(3))),
+        Duration::from_secs(13)
+    );
+    assert_eq!(playback_timeout(None), UNKNOWN_LENGTH_TIMEOUT);
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_run_playback_thread_with_timeout() {
+    let (sender, receiver) = mpsc::channel();
+    let result = run_playback_thread(Some(Duration::from_millis(10)), move |stop| {
+        // Simulates a hung audio device, which only returns once it is stopped
+        while !stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        sender.send(()).unwrap();
+        Ok(())
+    });
+    assert!(matches!(result, Err(PlaySoundError::Timeout)));
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    assert!(run_playback_thread(Some(Duration::from_secs(5)), |_| Ok(())).is_ok());
+    assert!(matches!(
+        run_playback_thread(None, |_| Err(PlaySoundError::NoAudioDevice)),
+        Err(PlaySoundError::NoAudioDevice)
+    ));
+    assert!(matches!(
+        run_playback_thread(None, |_| panic!("playback failed")),
+        Err(PlaySoundError::PlaybackThread)
+    ));
+    assert!(run_playback_thread(None, |_| {
+        assert_eq!(thread::current().name(), Some("sound_playback"));
+        Ok(())
+    })
+    .is_ok());
+
+    // The sound is decoded before the playback thread is started
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert!(play_sound_with_fade_out(&Some(manifest), Duration::ZERO, Duration::ZERO).is_err());
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_fade_out() {
+    // An idle sink is never played, so only the fade out ends the playback
+    let (sink, _queue) = Sink::new_idle();
+    append_bytes(&sink, ALARM_SOUND).unwrap();
+    let start = Instant::now();
+    fade_out(
+        &sink,
+        Duration::from_millis(100),
+        Duration::from_millis(200),
+    );
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(sink.volume() < 1.0, "{}", sink.volume());
+
+    let (sink, _queue) = Sink::new_idle();
+    append_bytes(&sink, ALARM_SOUND).unwrap();
+    fade_out(&sink, Duration::from_millis(100), Duration::ZERO);
+    assert_eq!(sink.volume(), 1.0);
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_with_stop_signal() {
+    let stop = Arc::new(AtomicBool::new(false));
+    let current = || STOP_SIGNAL.with_borrow(Clone::clone);
+    with_stop_signal(stop.clone(), || {
+        assert!(current().is_some_and(|current| Arc::ptr_eq(&current, &stop)));
+    });
+    assert!(current().is_none());
+
+    // An idle sink never plays its sounds, like a hung audio device
+    let (sink, _queue) = Sink::new_idle();
+    append_bytes(&sink, ALARM_SOUND).unwrap();
+    let setter = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            thread::sleep(STOP_CHECK_INTERVAL);
+            stop.store(true, Ordering::Relaxed);
+        })
+    };
+    // Returns once the stop signal is set
+    play_until_end_or_stopped(&sink, &AtomicBool::new(false), Some(&stop));
+    assert!(stop.load(Ordering::Relaxed));
+    setter.join().unwrap();
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_sound_backend() {
+    let backends = [
+        (SoundBackend::Alsa, "\"alsa\""),
+        (SoundBackend::PulseAudio, "\"pulseAudio\""),
+        (SoundBackend::PipeWire, "\"pipeWire\""),
+        (SoundBackend::Wasapi, "\"wasapi\""),
+        (SoundBackend::CoreAudio, "\"coreAudio\""),
+    ];
+    for (backend, json) in backends {
+        assert_eq!(serde_json::to_string(&backend).unwrap(), json);
+        assert_eq!(serde_json::from_str::<SoundBackend>(json).unwrap(), backend);
+    }
+    assert!(serde_json::from_str::<SoundBackend>("\"jack\"").is_err());
+
+    let (linux, macos) = (cfg!(target_os = "linux"), cfg!(target_os = "macos"));
+    assert_eq!(SoundBackend::Alsa.is_available(), linux);
+    assert_eq!(SoundBackend::PulseAudio.is_available(), linux);
+    assert_eq!(SoundBackend::PipeWire.is_available(), linux);
+    assert_eq!(SoundBackend::Wasapi.is_available(), cfg!(windows));
+    assert_eq!(SoundBackend::CoreAudio.is_available(), macos);
+    let unavailable = if cfg!(target_os = "windows") {
+        SoundBackend::CoreAudio
+    } else {
+        SoundBackend::Wasapi
+    };
+    assert!(matches!(
+        open_output_stream(unavailable),
+        Err(PlaySoundError::UnsupportedBackend(backend)) if backend == unavailable
+    ));
+    assert_eq!(SoundBackend::PulseAudio.device_name(), Some("pulse"));
+    assert_eq!(SoundBackend::Alsa.device_name(), None);
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn test_no_audio_device() {
+    if is_audio_available() {
+        // The sound would be played, so only the preflight is checked
+        assert!(!matches!(
+            open_default_output_stream(),
+            Err(PlaySoundError::NoAudioDevice)
+        ));
+    } else {
+        assert!(matches!(
+            open_default_output_stream(),
+            Err(PlaySoundError::NoAudioDevice)
+        ));
+        assert!(matches!(
+            play_sound(&None),
+            Err(PlaySoundError::NoAudioDevice)
+        ));
+    }
+}
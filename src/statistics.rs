@@ -0,0 +1,562 @@
+//! This module records the finished phases of the Pomodoro timer in the history file.
+//!
+//! The history file contains one JSON record per line and is located in the data directory
+//! of the user, e.g. `~/.local/share/locking-pomodoro-timer/history.jsonl` on Linux.
+use crate::pomodoro_phase::PomodoroPhase;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// The name of the history file in the data directory.
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// A finished phase in the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    /// The time the phase started.
+    pub started_at: DateTime<Local>,
+    /// The phase.
+    pub phase: PomodoroPhase,
+    /// The time spent in the phase in seconds.
+    pub duration_secs: u64,
+    /// Whether the phase ran to the end or was quit early.
+    pub completed: bool,
+    /// Whether the phase was skipped to its end, which doesn't count as completed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    /// The label of the phase, e.g. the name of a project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The reason the phase was aborted, if it was aborted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aborted: Option<AbortReason>,
+}
+
+/// The reason a running Pomodoro session or break was aborted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AbortReason {
+    /// The user stopped the timer, e.g. with ctrl+c or the stop button of a GUI.
+    UserRequested,
+    /// The system or the application is shutting down.
+    Shutdown,
+    /// An error occurred.
+    Error(String),
+}
+
+/// Sanitizes a label for the history file.
+///
+/// Newlines and other control characters are replaced by spaces, consecutive whitespace is
+/// collapsed and leading and trailing whitespace is removed.
+#[must_use]
+pub fn sanitize_label(label: &str) -> String {
+    label
+        .split(|c: char| c.is_control() || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Gets the path of the history file in the data directory of the user.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be determined.
+pub fn history_file_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("Failed to determine the data directory.")?;
+    Ok(data_dir
+        .join(env!("CARGO_PKG_NAME"))
+        .join(HISTORY_FILE_NAME))
+}
+
+/// Appends the record to the history file. The file and its directory are created if necessary.
+///
+/// # Errors
+///
+/// Returns an error if the history file cannot be written.
+pub fn append_to_history(path: &Path, record: &HistoryRecord) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history file: {:?}", path))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+        .with_context(|| format!("Failed to write history file: {:?}", path))?;
+    Ok(())
+}
+
+/// Reads all records of the history file.
+///
+/// If the history file doesn't exist, no records are returned.
+/// Lines which cannot be parsed are skipped with a warning.
+///
+/// # Errors
+///
+/// Returns an error if the history file cannot be read.
+pub fn read_history(path: &Path) -> Result<Vec<HistoryRecord>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let file =
+        File::open(path).with_context(|| format!("Failed to open history file: {:?}", path))?;
+    let mut records = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read history file: {:?}", path))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(e) => log::warn!(
+                "Skipping invalid line {} of the history file: {}",
+                index + 1,
+                e
+            ),
+        }
+    }
+    Ok(records)
+}
+
+/// Resets the history file.
+///
+/// If `before` is `None`, the history file is deleted. Otherwise, only the records which
+/// started before the given date are removed. Invalid lines are removed as well.
+///
+/// # Returns
+/// The number of removed records.
+///
+/// # Errors
+///
+/// Returns an error if the history file cannot be read, written or deleted.
+pub fn reset_history(path: &Path, before: Option<NaiveDate>) -> Result<usize> {
+    let records = read_history(path)?;
+    let Some(before) = before else {
+        if path.is_file() {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to delete history file: {:?}", path))?;
+        }
+        return Ok(records.len());
+    };
+    let (removed, kept): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .partition(|record| record.started_at.date_naive() < before);
+    let mut contents = String::new();
+    for record in &kept {
+        contents.push_str(&serde_json::to_string(record)?);
+        contents.push('\n');
+    }
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write history file: {:?}", path))?;
+    Ok(removed.len())
+}
+
+/// A summary of the Pomodoro sessions in a range of days.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    /// The first day of the summary.
+    pub from: NaiveDate,
+    /// The last day of the summary.
+    pub to: NaiveDate,
+    /// The number of Pomodoro sessions which ran to the end.
+    pub completed_sessions: u32,
+    /// The time spent in all Pomodoro sessions in seconds, including the sessions quit early.
+    pub focused_secs: u64,
+    /// The average duration of the completed Pomodoro sessions in seconds.
+    pub average_session_secs: u64,
+    /// The highest number of consecutive completed Pomodoro sessions without quitting one early.
+    pub longest_streak: u32,
+    /// The distinct labels of the Pomodoro sessions.
+    pub labels: Vec<String>,
+}
+
+impl fmt::Display for SessionSummary {
+    /// Formats the summary as lines of text, e.g. `Focused time: 1 h 15 min`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.from == self.to {
+            writeln!(f, "Summary of {}", self.from)?;
+        } else {
+            writeln!(f, "Summary of {} to {}", self.from, self.to)?;
+        }
+        writeln!(f, "Completed sessions: {}", self.completed_sessions)?;
+        writeln!(
+            f,
+            "Focused time: {}",
+            format_duration_secs(self.focused_secs)
+        )?;
+        writeln!(
+            f,
+            "Average session: {}",
+            format_duration_secs(self.average_session_secs)
+        )?;
+        write!(f, "Longest streak: {} sessions", self.longest_streak)?;
+        if !self.labels.is_empty() {
+            write!(f, "\nLabels: {}", self.labels.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the records as CSV according to RFC 4180 with a header and the columns `date`, `time`,
+/// `phase`, `duration_secs` and `label`, e.g. for spreadsheets.
+///
+/// The date and time are the local start time of the phase, the phase is named as in the
+/// history file, e.g. `shortBreak`, and the label is empty if the phase has none.
+#[must_use]
+pub fn history_to_csv(records: &[&HistoryRecord]) -> String {
+    let mut csv = String::from("date,time,phase,duration_secs,label\r\n");
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\r\n",
+            record.started_at.format("%Y-%m-%d"),
+            record.started_at.format("%H:%M:%S"),
+            record.phase,
+            record.duration_secs,
+            csv_field(record.label.as_deref().unwrap_or_default())
+        ));
+    }
+    csv
+}
+
+/// Quotes the field of a CSV record if it contains a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats the seconds rounded to full minutes, e.g. `1 h 15 min` or `25 min`.
+fn format_duration_secs(seconds: u64) -> String {
+    let minutes = (seconds + 30) / 60;
+    if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} h {} min", minutes / 60, minutes % 60)
+    }
+}
+
+/// Statistics computed from the records of the history file.
+#[derive(Debug, Clone, Default)]
+pub struct Statistics {
+    records: Vec<HistoryRecord>,
+}
+
+impl Statistics {
+    /// Creates the statistics from the given records.
+    pub fn new(records: Vec<HistoryRecord>) -> Self {
+        Statistics { records }
+    }
+
+    /// Reads the statistics from the history file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the history file cannot be read.
+    pub fn from_history_file(path: &Path) -> Result<Self> {
+        Ok(Statistics::new(read_history(path)?))
+    }
+
+    /// Gets the records the statistics are computed from.
+    #[must_use]
+    pub fn records(&self) -> &[HistoryRecord] {
+        &self.records
+    }
+
+    /// Gets the records which started between `since` and `until`, both inclusive.
+    /// Without a date, the records are not limited in that direction.
+    #[must_use]
+    pub fn records_between(
+        &self,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    ) -> Vec<&HistoryRecord> {
+        self.records
+            .iter()
+            .filter(|record| {
+                let date = record.started_at.date_naive();
+                since.is_none_or(|since| since <= date) && until.is_none_or(|until| date <= until)
+            })
+            .collect()
+    }
+
+    /// Summarizes the records which started between `from` and `to`, both inclusive.
+    #[must_use]
+    pub fn summary(&self, from: NaiveDate, to: NaiveDate) -> SessionSummary {
+        let sessions: Vec<&HistoryRecord> = self
+            .records
+            .iter()
+            .filter(|record| {
+                let date = record.started_at.date_naive();
+                record.phase == PomodoroPhase::Work && from <= date && date <= to
+            })
+            .collect();
+        let completed: Vec<&HistoryRecord> = sessions
+            .iter()
+            .copied()
+            .filter(|record| record.completed)
+            .collect();
+        let focused_secs = sessions.iter().map(|record| record.duration_secs).sum();
+        let completed_secs: u64 = completed.iter().map(|record| record.duration_secs).sum();
+        let mut longest_streak = 0;
+        let mut streak = 0;
+        for record in &sessions {
+            streak = if record.completed { streak + 1 } else { 0 };
+            longest_streak = longest_streak.max(streak);
+        }
+        let mut labels: Vec<String> = sessions
+            .iter()
+            .filter_map(|record| record.label.clone())
+            .collect();
+        labels.sort();
+        labels.dedup();
+        SessionSummary {
+            from,
+            to,
+            completed_sessions: completed.len() as u32,
+            focused_secs,
+            average_session_secs: completed_secs
+                .checked_div(completed.len() as u64)
+                .unwrap_or(0),
+            longest_streak,
+            labels,
+        }
+    }
+
+    /// Summarizes the records which started today.
+    #[must_use]
+    pub fn today_summary(&self) -> SessionSummary {
+        let today = Local::now().date_naive();
+        self.summary(today, today)
+    }
+
+    /// Counts the completed Pomodoro sessions which started today.
+    #[must_use]
+    pub fn today_count(&self) -> u32 {
+        let today = Local::now().date_naive();
+        self.records
+            .iter()
+            .filter(|record| {
+                record.phase == PomodoroPhase::Work
+                    && record.completed
+                    && record.started_at.date_naive() == today
+            })
+            .count() as u32
+    }
+}
+
+#[cfg(test)]
+use chrono::TimeZone;
+
+#[test]
+fn test_reset_history_before() {
+    let folder_path = std::env::temp_dir().join("locking_pomodoro_timer_test_reset_history");
+    fs::create_dir_all(&folder_path).unwrap();
+    let file_path = folder_path.join(HISTORY_FILE_NAME);
+    let _ = fs::remove_file(&file_path);
+
+    for day in [1, 2, 3] {
+        let record = HistoryRecord {
+            started_at: Local.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap(),
+            phase: PomodoroPhase::Work,
+            duration_secs: 25 * 60,
+            completed: true,
+            skipped: false,
+            label: None,
+            aborted: None,
+        };
+        append_to_history(&file_path, &record).unwrap();
+    }
+
+    let before = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+    assert_eq!(reset_history(&file_path, Some(before)).unwrap(), 2);
+    let records = read_history(&file_path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].started_at.date_naive(), before);
+
+    assert_eq!(reset_history(&file_path, None).unwrap(), 1);
+    assert!(!file_path.exists());
+    fs::remove_dir_all(&folder_path).unwrap();
+}
+
+#[test]
+fn test_history_to_csv() {
+    let record = |day, phase, label: Option<&str>| HistoryRecord {
+        started_at: Local.with_ymd_and_hms(2024, 5, day, 9, 5, 0).unwrap(),
+        phase,
+        duration_secs: 300,
+        completed: true,
+        skipped: false,
+        label: label.map(str::to_string),
+        aborted: None,
+    };
+    let statistics = Statistics::new(vec![
+        record(1, PomodoroPhase::Work, Some("Project A")),
+        record(2, PomodoroPhase::ShortBreak, Some("Walk, \"outside\"")),
+        record(3, PomodoroPhase::LongBreak, None),
+    ]);
+    let may = |day| NaiveDate::from_ymd_opt(2024, 5, day);
+    assert_eq!(statistics.records_between(None, None).len(), 3);
+    assert_eq!(statistics.records_between(may(2), None).len(), 2);
+    let records = statistics.records_between(may(2), may(2));
+    assert_eq!(
+        history_to_csv(&records),
+        "date,time,phase,duration_secs,label\r\n\
+        2024-05-02,09:05:00,shortBreak,300,\"Walk, \"\"outside\"\"\"\r\n"
+    );
+    assert_eq!(
+        history_to_csv(&statistics.records_between(None, may(1))),
+        "date,time,phase,duration_secs,label\r\n2024-05-01,09:05:00,work,300,Project A\r\n"
+    );
+}
+
+#[test]
+fn test_sanitize_label() {
+    assert_eq!(sanitize_label("Project A"), "Project A");
+    assert_eq!(sanitize_label(" Project\nA\t\u{7}work "), "Project A work");
+}
+
+#[test]
+fn test_today_count() {
+    let record = HistoryRecord {
+        started_at: Local::now(),
+        phase: PomodoroPhase::Work,
+        duration_secs: 25 * 60,
+        completed: true,
+        skipped: false,
+        label: None,
+        aborted: None,
+    };
+    let statistics = Statistics::new(vec![
+        record.clone(),
+        HistoryRecord {
+            completed: false,
+            ..record.clone()
+        },
+        HistoryRecord {
+            phase: PomodoroPhase::ShortBreak,
+            ..record.clone()
+        },
+        HistoryRecord {
+            started_at: Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap(),
+            ..record
+        },
+    ]);
+    assert_eq!(statistics.today_count(), 1);
+}
+
+#[test]
+fn test_summary() {
+    let record = HistoryRecord {
+        started_at: Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap(),
+        phase: PomodoroPhase::Work,
+        duration_secs: 25 * 60,
+        completed: true,
+        skipped: false,
+        label: Some("Project A".to_string()),
+        aborted: None,
+    };
+    let statistics = Statistics::new(vec![
+        record.clone(),
+        HistoryRecord {
+            phase: PomodoroPhase::ShortBreak,
+            duration_secs: 5 * 60,
+            label: None,
+            ..record.clone()
+        },
+        HistoryRecord {
+            duration_secs: 35 * 60,
+            label: Some("Project B".to_string()),
+            ..record.clone()
+        },
+        HistoryRecord {
+            duration_secs: 10 * 60,
+            completed: false,
+            ..record.clone()
+        },
+        record.clone(),
+        HistoryRecord {
+            started_at: Local.with_ymd_and_hms(2024, 5, 2, 9, 0, 0).unwrap(),
+            ..record
+        },
+    ]);
+    let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    let summary = statistics.summary(day, day);
+    assert_eq!(summary.completed_sessions, 3);
+    assert_eq!(summary.focused_secs, (25 + 35 + 10 + 25) * 60);
+    assert_eq!(summary.average_session_secs, (25 + 35 + 25) * 60 / 3);
+    assert_eq!(summary.longest_streak, 2);
+    assert_eq!(summary.labels, vec!["Project A", "Project B"]);
+}
+
+#[test]
+fn test_display_session_summary() {
+    let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    let summary = SessionSummary {
+        from: day,
+        to: day,
+        completed_sessions: 3,
+        focused_secs: 75 * 60,
+        average_session_secs: 25 * 60,
+        longest_streak: 2,
+        labels: vec!["Project A".to_string()],
+    };
+    assert_eq!(
+        summary.to_string(),
+        "Summary of 2024-05-01\n\
+        Completed sessions: 3\n\
+        Focused time: 1 h 15 min\n\
+        Average session: 25 min\n\
+        Longest streak: 2 sessions\n\
+        Labels: Project A"
+    );
+}
+
+#[test]
+fn test_serialize_aborted_record() {
+    let record = HistoryRecord {
+        started_at: Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap(),
+        phase: PomodoroPhase::Work,
+        duration_secs: 10 * 60,
+        completed: false,
+        skipped: false,
+        label: None,
+        aborted: Some(AbortReason::Error("No audio device".to_string())),
+    };
+    let json = serde_json::to_string(&record).unwrap();
+    assert!(json.ends_with(r#""completed":false,"aborted":{"error":"No audio device"}}"#));
+    assert_eq!(
+        serde_json::from_str::<HistoryRecord>(&json).unwrap(),
+        record
+    );
+
+    let json = serde_json::to_string(&HistoryRecord {
+        aborted: Some(AbortReason::UserRequested),
+        ..record.clone()
+    })
+    .unwrap();
+    assert!(json.ends_with(r#""aborted":"userRequested"}"#));
+
+    // Only skipped records have the field
+    let skipped = HistoryRecord {
+        skipped: true,
+        aborted: None,
+        ..record
+    };
+    let json = serde_json::to_string(&skipped).unwrap();
+    assert!(json.ends_with(r#""completed":false,"skipped":true}"#));
+    assert_eq!(
+        serde_json::from_str::<HistoryRecord>(&json).unwrap(),
+        skipped
+    );
+}
@@ -0,0 +1,193 @@
+//! This module contains the theme of the progress bar in the terminal, configured with `theme`
+//! in the options file, and whether the output is colored at all, which is set with `--color`.
+use crate::pomodoro_phase::PomodoroPhase;
+use indicatif::ProgressStyle;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+use unicode_width::UnicodeWidthChar;
+
+/// Whether the output is colored, see `set_colorize`. One of `COLORIZE_UNSET`, `0` and `1`.
+static COLORIZE: AtomicU8 = AtomicU8::new(COLORIZE_UNSET);
+
+/// The value of `COLORIZE` until `set_colorize` is called.
+const COLORIZE_UNSET: u8 = 2;
+
+/// Sets whether the output is colored. If `true`, colors are also emitted if the output is not
+/// a terminal, e.g. piped to a file. If `false`, the output contains no ANSI escape sequences.
+///
+/// Until this is called, the output is only colored if it is written to a terminal.
+pub fn set_colorize(colorize: bool) {
+    COLORIZE.store(u8::from(colorize), Ordering::Relaxed);
+    console::set_colors_enabled(colorize);
+    console::set_colors_enabled_stderr(colorize);
+}
+
+/// Checks whether the output may be colored, see `set_colorize`.
+#[must_use]
+pub fn colorize() -> bool {
+    match COLORIZE.load(Ordering::Relaxed) {
+        COLORIZE_UNSET => std::io::stderr().is_terminal(),
+        colorize => colorize == 1,
+    }
+}
+
+/// A color of the terminal. The colors are taken from the palette of the terminal, so they
+/// match its dark or light theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Color {
+    /// Black.
+    Black,
+    /// Red.
+    Red,
+    /// Green.
+    Green,
+    /// Yellow.
+    Yellow,
+    /// Blue.
+    Blue,
+    /// Magenta.
+    Magenta,
+    /// Cyan.
+    Cyan,
+    /// White.
+    White,
+}
+
+impl Color {
+    /// Gets the name of the color in the templates of `indicatif`.
+    fn style_name(self) -> &'static str {
+        match self {
+            Color::Black => "black",
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+            Color::White => "white",
+        }
+    }
+}
+
+/// The colors and symbols of the progress bar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ThemeConfig {
+    /// The color of the progress bar during Pomodoro sessions.
+    pub work_color: Color,
+    /// The color of the progress bar during breaks.
+    pub break_color: Color,
+    /// The symbol of the elapsed part of the progress bar.
+    pub progress_char: char,
+    /// The symbol of the remaining part of the progress bar.
+    pub empty_char: char,
+    /// Whether the symbols may be Unicode characters. If `false`, `#` and `-` are used instead
+    /// of symbols which are not ASCII, e.g. for terminals with limited fonts.
+    pub use_unicode_symbols: bool,
+}
+
+impl Default for ThemeConfig {
+    /// Creates the theme of a cyan bar during Pomodoro sessions and a green bar during breaks,
+    /// which are readable on dark and light terminals.
+    fn default() -> Self {
+        ThemeConfig {
+            work_color: Color::Cyan,
+            break_color: Color::Green,
+            progress_char: '█',
+            empty_char: '░',
+            use_unicode_symbols: true,
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// Checks that both symbols are printable and equally wide, which the progress bar requires.
+    #[must_use]
+    pub fn has_valid_symbols(&self) -> bool {
+        let (progress_char, empty_char) = self.symbols();
+        match (progress_char.width(), empty_char.width()) {
+            (Some(progress_width), Some(empty_width)) => {
+                progress_width > 0 && progress_width == empty_width
+            }
+            _ => false,
+        }
+    }
+
+    /// Gets the style of the progress bar during the phase. The remaining part is shown in blue.
+    /// If the output is not colored, see `colorize`, the progress bar has no colors.
+    ///
+    /// Symbols which are not valid, see `has_valid_symbols`, are replaced by the default ones.
+    #[must_use]
+    pub fn progress_style(&self, phase: PomodoroPhase) -> ProgressStyle {
+        let color = match phase {
+            PomodoroPhase::Work => self.work_color,
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => self.break_color,
+        };
+        let template = if colorize() {
+            format!(
+                "[{{elapsed}}/{{eta}}] {{wide_bar:.{}/blue}} ",
+                color.style_name()
+            )
+        } else {
+            "[{elapsed}/{eta}] {wide_bar} ".to_string()
+        };
+        let (progress_char, empty_char) = if self.has_valid_symbols() {
+            self.symbols()
+        } else {
+            ThemeConfig::default().symbols()
+        };
+        ProgressStyle::with_template(&template)
+            .unwrap()
+            .progress_chars(&format!("{}{}", progress_char, empty_char))
+    }
+
+    /// Gets the symbols of the elapsed and the remaining part, see `use_unicode_symbols`.
+    fn symbols(&self) -> (char, char) {
+        if self.use_unicode_symbols {
+            (self.progress_char, self.empty_char)
+        } else {
+            (
+                ascii_or(self.progress_char, '#'),
+                ascii_or(self.empty_char, '-'),
+            )
+        }
+    }
+}
+
+/// Gets the character if it is ASCII, else the fallback.
+fn ascii_or(character: char, fallback: char) -> char {
+    if character.is_ascii() {
+        character
+    } else {
+        fallback
+    }
+}
+
+#[test]
+fn test_theme_config() {
+    let theme = ThemeConfig::default();
+    assert!(theme.has_valid_symbols());
+    let _ = theme.progress_style(PomodoroPhase::ShortBreak);
+
+    let ascii = ThemeConfig {
+        use_unicode_symbols: false,
+        ..ThemeConfig::default()
+    };
+    assert_eq!(ascii.symbols(), ('#', '-'));
+    let theme = serde_json::from_str::<ThemeConfig>(
+        r#"{"breakColor": "magenta", "progressChar": "=", "emptyChar": " "}"#,
+    )
+    .unwrap();
+    assert_eq!(theme.break_color, Color::Magenta);
+    assert_eq!(theme.work_color, Color::Cyan);
+    assert!(theme.has_valid_symbols());
+    let wide = ThemeConfig {
+        progress_char: '🍅',
+        ..ThemeConfig::default()
+    };
+    assert!(!wide.has_valid_symbols());
+    let _ = wide.progress_style(PomodoroPhase::Work);
+}
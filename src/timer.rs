@@ -1,21 +1,27 @@
+//! This module contains the timer counting the elapsed time of a Pomodoro session or a break.
+use crate::clock::{current_clock, Clock};
 use log::trace;
 use std::{
     sync::{
         atomic::{AtomicBool, AtomicU32, Ordering},
-        Arc,
+        Arc, Mutex, PoisonError,
     },
-    thread,
     time::Duration,
 };
 
+/// The interval in seconds of the clock in which the timer counts the elapsed time.
+const TICK_SECS: f64 = 0.5;
+
 /// Represents a timer that counts the elapsed time.
 ///
 /// The timer runs in a separate thread and counts the elapsed time in seconds.
-pub(crate) struct Timer {
+pub struct Timer {
     duration: Duration,
     elapsed_time: Arc<AtomicU32>,
     paused: Arc<AtomicBool>,
     should_terminate: Arc<AtomicBool>,
+    skipped_at: Mutex<Option<Duration>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl Timer {
@@ -25,46 +31,60 @@ impl Timer {
     ///
     /// * `duration` - The duration after which the timer should stop.
     pub fn new(duration: Duration) -> Self {
+        Timer::with_clock(duration, current_clock())
+    }
+
+    /// Creates a new Timer instance like `new`, which counts the elapsed time with the clock,
+    /// e.g. a `TestClock` running faster than real time.
+    pub fn with_clock(duration: Duration, clock: Arc<dyn Clock>) -> Self {
         Timer {
             duration,
             elapsed_time: Arc::new(AtomicU32::new(0)),
             paused: Arc::new(AtomicBool::new(false)),
             should_terminate: Arc::new(AtomicBool::new(false)),
+            skipped_at: Mutex::new(None),
+            clock,
         }
     }
 
     /// Starts the timer in a separate thread.
     ///
-    /// The thread sleeps for 0.5 seconds and increments the elapsed time when more than 1 second has passed.
+    /// The thread sleeps for 0.5 seconds of the clock and increments the elapsed time when more than 1 second has passed.
     /// The timer stops when the elapsed time reaches the specified duration.
     /// The timer can be paused and resumed using the `pause` and `resume` methods.
     /// The timer can be stopped using the `stop` method.
     /// The thread terminates when the timer stops.
+    ///
+    /// In the browser, i.e. with the `wasm` feature on `wasm32`, the timer runs as a task of the
+    /// page instead, which waits with `clock::sleep_async`.
     pub fn start(&self) {
-        let elapsed_time_storage = self.elapsed_time.clone();
-        let duration = self.duration;
-        let mut time_buffer = 0.0;
-        let paused = self.paused.clone();
-        let should_terminate = self.should_terminate.clone();
-        thread::spawn(move || {
-            let delta = 0.5;
-            while elapsed_time_storage.load(Ordering::Relaxed) < duration.as_secs() as u32 {
-                if should_terminate.load(Ordering::Relaxed) {
-                    break;
-                }
-                thread::sleep(Duration::from_secs_f64(delta));
-                if paused.load(Ordering::Relaxed) {
-                    continue;
-                }
-                time_buffer += delta;
-                if time_buffer >= 1.0 {
-                    let time_to_add = time_buffer as u32;
-                    time_buffer -= time_to_add as f64;
-                    elapsed_time_storage.fetch_add(time_to_add, Ordering::Relaxed);
-                }
+        let mut counter = ElapsedTimeCounter {
+            elapsed_time: self.elapsed_time.clone(),
+            duration: self.duration,
+            time_buffer: 0.0,
+            paused: self.paused.clone(),
+            should_terminate: self.should_terminate.clone(),
+        };
+        let clock = self.clock.clone();
+        #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+        std::thread::spawn(move || {
+            while counter.is_running() {
+                clock.sleep(Duration::from_secs_f64(TICK_SECS));
+                counter.count(TICK_SECS);
             }
             trace!("Timer thread terminated.");
         });
+        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+        wasm_bindgen_futures::spawn_local(async move {
+            while counter.is_running() {
+                crate::clock::sleep_async(
+                    Duration::from_secs_f64(TICK_SECS).div_f32(clock.speed()),
+                )
+                .await;
+                counter.count(TICK_SECS);
+            }
+            trace!("Timer task terminated.");
+        });
     }
 
     /// Pauses the timer.
@@ -89,12 +109,42 @@ impl Timer {
         } else {
             self.elapsed_time.store(new_elapsed_time, Ordering::Relaxed);
         }
-        log::trace!("Elapsed time after skip: {:?}", self.elapsed_time.load(Ordering::Relaxed));
+        log::trace!(
+            "Elapsed time after skip: {:?}",
+            self.elapsed_time.load(Ordering::Relaxed)
+        );
+    }
+
+    /// Skips to the end of the timer, so that it expires as if its duration elapsed, e.g. to
+    /// advance to the next phase during a demo.
+    ///
+    /// The elapsed time before the first skip to the end is kept, see `skipped_at`.
+    pub fn skip_to_end(&self) {
+        let mut skipped_at = self
+            .skipped_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if skipped_at.is_none() {
+            *skipped_at = Some(self.get_elapsed_time());
+        }
+        self.elapsed_time
+            .store(self.duration.as_secs() as u32, Ordering::Relaxed);
+    }
+
+    /// Gets the elapsed time at which the timer was skipped to its end with `skip_to_end`,
+    /// or `None` if it wasn't.
+    #[must_use]
+    pub fn skipped_at(&self) -> Option<Duration> {
+        *self
+            .skipped_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
     /// Checks if the timer is currently paused.
     ///
     /// Returns `true` if the timer is paused, `false` otherwise.
+    #[must_use]
     pub fn is_paused(&self) -> bool {
         self.paused.load(Ordering::Relaxed)
     }
@@ -105,10 +155,51 @@ impl Timer {
     }
 
     /// Gets the elapsed time of the timer.
+    #[must_use]
     pub fn get_elapsed_time(&self) -> Duration {
         let elapsed_time = self.elapsed_time.load(Ordering::Relaxed);
         Duration::from_secs(elapsed_time as u64)
     }
+
+    /// Gets the remaining time until the timer reaches its duration.
+    ///
+    /// The remaining time has a resolution of one second. Paused and skipped time is taken into account.
+    /// The method does not block and can be called from other threads while the timer is running,
+    /// e.g. by a GUI frontend polling the timer.
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.get_elapsed_time())
+    }
+}
+
+/// Counts the elapsed time of a started `Timer` on its thread.
+struct ElapsedTimeCounter {
+    elapsed_time: Arc<AtomicU32>,
+    duration: Duration,
+    time_buffer: f64,
+    paused: Arc<AtomicBool>,
+    should_terminate: Arc<AtomicBool>,
+}
+
+impl ElapsedTimeCounter {
+    /// Checks that the duration didn't elapse yet and the timer wasn't stopped.
+    fn is_running(&self) -> bool {
+        self.elapsed_time.load(Ordering::Relaxed) < self.duration.as_secs() as u32
+            && !self.should_terminate.load(Ordering::Relaxed)
+    }
+
+    /// Counts the seconds which passed since the last call, unless the timer is paused.
+    fn count(&mut self, delta: f64) {
+        if self.paused.load(Ordering::Relaxed) {
+            return;
+        }
+        self.time_buffer += delta;
+        if self.time_buffer >= 1.0 {
+            let time_to_add = self.time_buffer as u32;
+            self.time_buffer -= time_to_add as f64;
+            self.elapsed_time.fetch_add(time_to_add, Ordering::Relaxed);
+        }
+    }
 }
 
 impl Drop for Timer {
@@ -117,3 +208,63 @@ impl Drop for Timer {
         trace!("Timer dropped.");
     }
 }
+
+#[test]
+fn test_remaining() {
+    let timer = Timer::new(Duration::from_secs(120));
+    assert_eq!(timer.remaining(), Duration::from_secs(120));
+
+    timer.skip(Duration::from_secs(60));
+    assert_eq!(timer.remaining(), Duration::from_secs(60));
+
+    timer.skip(Duration::from_secs(90));
+    assert_eq!(timer.remaining(), Duration::ZERO);
+}
+
+#[test]
+fn test_skip_to_end() {
+    let timer = Timer::new(Duration::from_secs(120));
+    assert_eq!(timer.skipped_at(), None);
+    timer.skip(Duration::from_secs(30));
+    timer.skip_to_end();
+    assert_eq!(timer.remaining(), Duration::ZERO);
+    assert_eq!(timer.skipped_at(), Some(Duration::from_secs(30)));
+    timer.skip_to_end();
+    assert_eq!(timer.skipped_at(), Some(Duration::from_secs(30)));
+}
+
+#[test]
+fn test_timer_with_test_clock() {
+    let timer = Timer::with_clock(
+        Duration::from_secs(30),
+        Arc::new(crate::clock::TestClock::new(100.0)),
+    );
+    let start = std::time::Instant::now();
+    timer.start();
+    while timer.remaining() > Duration::ZERO && start.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_secs(1),
+        "The timer took {:?}.",
+        elapsed
+    );
+}
+
+#[test]
+#[ignore = "takes real time, run with --include-ignored"]
+fn test_timer_accuracy() {
+    let timer = Timer::new(Duration::from_secs(2));
+    let start = std::time::Instant::now();
+    timer.start();
+    while timer.remaining() > Duration::ZERO {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        (Duration::from_millis(1900)..=Duration::from_millis(2200)).contains(&elapsed),
+        "The timer took {:?}.",
+        elapsed
+    );
+}
@@ -0,0 +1,206 @@
+//! This module shows the timer as tray icon on Linux with a badge of the Pomodoro sessions
+//! completed today.
+//!
+//! The icon is a StatusNotifierItem, which is published on the session bus and registered with
+//! the `org.kde.StatusNotifierWatcher` of the desktop, e.g. KDE Plasma, or GNOME with the
+//! AppIndicator extension. StatusNotifierItems have no badge, so the count is shown in the title
+//! and the tooltip of the icon. Docks which support the `com.canonical.Unity.LauncherEntry` API,
+//! e.g. the task manager of KDE Plasma, additionally show the count as badge on the launcher of
+//! `locking-pomodoro-timer.desktop`.
+//!
+//! The timer shows the icon while it runs and updates the badge whenever a Pomodoro session is
+//! recorded in the history, see `session::SessionCallbacks::on_phase_recorded`.
+//!
+//! This module is only available with the `tray` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use locking_pomodoro_timer::tray::TrayIcon;
+//!
+//! // The icon is removed from the tray when it is dropped
+//! let tray = TrayIcon::new(0).unwrap();
+//! tray.set_badge(1).unwrap();
+//! ```
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use zbus::blocking::connection::{Builder, Connection};
+use zbus::interface;
+use zbus::zvariant::Value;
+
+/// The object path of the item, which is fixed by the StatusNotifierItem specification.
+const OBJECT_PATH: &str = "/StatusNotifierItem";
+/// The interface of the item.
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+/// The object path of the launcher entry, which can be any path of the process.
+const LAUNCHER_ENTRY_PATH: &str = "/com/canonical/unity/launcherentry/pomodoro";
+/// The desktop file whose launcher shows the badge.
+const DESKTOP_FILE_URI: &str = "application://locking-pomodoro-timer.desktop";
+/// The name of the icon of the freedesktop icon theme.
+const ICON_NAME: &str = "appointment-soon";
+
+/// The timer shown as tray icon on the session bus.
+///
+/// The icon is removed from the tray when it is dropped.
+pub struct TrayIcon {
+    connection: Connection,
+    count: Arc<AtomicU32>,
+}
+
+impl TrayIcon {
+    /// Shows the tray icon with the badge.
+    ///
+    /// # Arguments
+    /// * `count` - The number of Pomodoro sessions completed today, e.g. `Statistics::today_count`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no session bus or the desktop has no StatusNotifierWatcher.
+    pub fn new(count: u32) -> zbus::Result<Self> {
+        let count = Arc::new(AtomicU32::new(count));
+        let bus_name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+        let connection = Builder::session()?
+            .name(bus_name.as_str())?
+            .serve_at(
+                OBJECT_PATH,
+                StatusNotifierItem {
+                    count: count.clone(),
+                },
+            )?
+            .build()?;
+        connection.call_method(
+            Some("org.kde.StatusNotifierWatcher"),
+            "/StatusNotifierWatcher",
+            Some("org.kde.StatusNotifierWatcher"),
+            "RegisterStatusNotifierItem",
+            &(bus_name.as_str()),
+        )?;
+        let tray = TrayIcon { connection, count };
+        tray.update_launcher_entry()?;
+        Ok(tray)
+    }
+
+    /// Sets the badge to the number of Pomodoro sessions completed today.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the change cannot be signaled on the session bus.
+    pub fn set_badge(&self, count: u32) -> zbus::Result<()> {
+        self.count.store(count, Ordering::Relaxed);
+        for signal in ["NewTitle", "NewToolTip"] {
+            self.connection
+                .emit_signal(None::<()>, OBJECT_PATH, ITEM_INTERFACE, signal, &())?;
+        }
+        self.update_launcher_entry()
+    }
+
+    /// Gets the number of Pomodoro sessions shown on the badge.
+    #[must_use]
+    pub fn badge(&self) -> u32 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Signals the count to the docks showing a badge on the launcher of the timer.
+    fn update_launcher_entry(&self) -> zbus::Result<()> {
+        let count = self.badge();
+        let properties = HashMap::from([
+            ("count", Value::from(i64::from(count))),
+            ("count-visible", Value::from(count > 0)),
+        ]);
+        self.connection.emit_signal(
+            None::<()>,
+            LAUNCHER_ENTRY_PATH,
+            "com.canonical.Unity.LauncherEntry",
+            "Update",
+            &(DESKTOP_FILE_URI, properties),
+        )
+    }
+}
+
+/// Creates the text of the badge shown in the title and tooltip, e.g. `3 Pomodoros today`.
+fn badge_text(count: u32) -> String {
+    match count {
+        1 => "1 Pomodoro today".to_string(),
+        count => format!("{} Pomodoros today", count),
+    }
+}
+
+/// The tooltip of a StatusNotifierItem: the icon name, the icon pixmaps of width, height and
+/// ARGB data, the title and the description.
+type ToolTip = (String, Vec<(i32, i32, Vec<u8>)>, String, String);
+
+/// The `org.kde.StatusNotifierItem` interface of the tray icon.
+struct StatusNotifierItem {
+    count: Arc<AtomicU32>,
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl StatusNotifierItem {
+    fn activate(&self, _x: i32, _y: i32) {}
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "locking-pomodoro-timer"
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> String {
+        format!(
+            "Locking Pomodoro Timer: {}",
+            badge_text(self.count.load(Ordering::Relaxed))
+        )
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> &str {
+        ICON_NAME
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn tool_tip(&self) -> ToolTip {
+        (
+            ICON_NAME.to_string(),
+            Vec::new(),
+            "Locking Pomodoro Timer".to_string(),
+            badge_text(self.count.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+#[test]
+fn test_status_notifier_item() {
+    assert_eq!(badge_text(0), "0 Pomodoros today");
+    assert_eq!(badge_text(1), "1 Pomodoro today");
+    let count = Arc::new(AtomicU32::new(2));
+    let item = StatusNotifierItem {
+        count: count.clone(),
+    };
+    assert_eq!(item.title(), "Locking Pomodoro Timer: 2 Pomodoros today");
+    assert_eq!(item.tool_tip().3, "2 Pomodoros today");
+    // The tray icon changes the count shared with the item
+    count.store(3, Ordering::Relaxed);
+    assert_eq!(item.title(), "Locking Pomodoro Timer: 3 Pomodoros today");
+    assert_eq!(item.tool_tip().3, "3 Pomodoros today");
+}
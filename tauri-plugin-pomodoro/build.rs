@@ -0,0 +1,6 @@
+/// The commands of the plugin, for which the `allow-*` and `deny-*` permissions are generated.
+const COMMANDS: &[&str] = &["start_session", "pause_session", "get_status"];
+
+fn main() {
+    tauri_plugin::Builder::new(COMMANDS).build();
+}
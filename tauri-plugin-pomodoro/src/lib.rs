@@ -0,0 +1,221 @@
+//! A Tauri plugin which embeds the timer of `locking_pomodoro_timer` in a Tauri application, so
+//! that a native GUI can be built without re-implementing the timer.
+//!
+//! The plugin adds these commands, which the frontend invokes with
+//! `invoke("plugin:pomodoro|<command>")`:
+//! * `start_session` - Starts the timer, optionally with `options` overriding the options of the
+//!   plugin, see `PartialPomodoroOptions`. If the timer is already running, it resumes the
+//!   paused Pomodoro session or break.
+//! * `pause_session` - Pauses the current Pomodoro session or break.
+//! * `get_status` - Gets the `TimerStatus`.
+//!
+//! The plugin emits these events, which the frontend receives with `listen()`:
+//! * `pomodoro://tick` - The `TimerStatus` every second while the timer runs, and once more when
+//!   it finished.
+//! * `pomodoro://phase-change` - A `PhaseChange` when a Pomodoro session or break starts or ends.
+//!
+//! The timer runs on its own thread with `start_pomodoro_without_terminal`, including the end
+//! events of the options. It doesn't read the terminal or ask whether to continue, the breaks and
+//! Pomodoro sessions start automatically until `cycles` finished. It is aborted when the
+//! application exits.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! fn builder() -> tauri::Builder<tauri::Wry> {
+//!     tauri::Builder::default().plugin(tauri_plugin_pomodoro::init())
+//! }
+//! ```
+//!
+//! The default permissions of the plugin allow all commands, so the capabilities of the
+//! application only have to contain `pomodoro:default`.
+use locking_pomodoro_timer::cli_utilities::{start_pomodoro_without_terminal, AbortHandle};
+use locking_pomodoro_timer::pomodoro_options::{PartialPomodoroOptions, PomodoroOptions};
+use locking_pomodoro_timer::pomodoro_phase::PomodoroPhase;
+use locking_pomodoro_timer::remote::{RemoteCommand, RemoteControl, TimerStatus};
+use locking_pomodoro_timer::session::{PhaseCallback, SessionCallbacks};
+use locking_pomodoro_timer::statistics::AbortReason;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, Runtime, State};
+
+/// The event emitted with the `TimerStatus` every second while the timer runs.
+pub const TICK_EVENT: &str = "pomodoro://tick";
+/// The event emitted with a `PhaseChange` when a Pomodoro session or break starts or ends.
+pub const PHASE_CHANGE_EVENT: &str = "pomodoro://phase-change";
+
+/// The interval in which `TICK_EVENT` is emitted.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The payload of `PHASE_CHANGE_EVENT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseChange {
+    /// The Pomodoro session or break which started or ended.
+    pub phase: PomodoroPhase,
+    /// Whether the phase started or ended.
+    pub started: bool,
+}
+
+/// The timer of the plugin, which is managed as state of the application.
+struct Pomodoro {
+    options: PomodoroOptions,
+    remote: RemoteControl,
+    running: Arc<AtomicBool>,
+    abort: Mutex<AbortHandle>,
+}
+
+impl Pomodoro {
+    fn new(options: PomodoroOptions) -> Self {
+        Pomodoro {
+            options,
+            remote: RemoteControl::new(),
+            running: Arc::new(AtomicBool::new(false)),
+            abort: Mutex::new(AbortHandle::new()),
+        }
+    }
+
+    /// Creates the abort handle of a new timer, which replaces the one of the previous timer.
+    fn new_abort_handle(&self) -> AbortHandle {
+        let abort = AbortHandle::new();
+        *self.abort.lock().unwrap_or_else(PoisonError::into_inner) = abort.clone();
+        abort
+    }
+
+    /// Aborts the running timer, e.g. when the application exits.
+    fn abort(&self, reason: AbortReason) {
+        self.abort
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .abort(reason);
+    }
+}
+
+/// Initializes the plugin with the default options.
+#[must_use]
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    init_with_options(PomodoroOptions::default())
+}
+
+/// Initializes the plugin with the options, e.g. read with `read_options_from_json`.
+///
+/// The options can be overridden per timer with the `options` of `start_session`.
+#[must_use]
+pub fn init_with_options<R: Runtime>(options: PomodoroOptions) -> TauriPlugin<R> {
+    Builder::new("pomodoro")
+        .invoke_handler(tauri::generate_handler![
+            start_session,
+            pause_session,
+            get_status
+        ])
+        .setup(move |app, _api| {
+            app.manage(Pomodoro::new(options));
+            Ok(())
+        })
+        .on_event(|app, event| {
+            if matches!(event, RunEvent::Exit) {
+                app.state::<Pomodoro>().abort(AbortReason::Shutdown);
+            }
+        })
+        .build()
+}
+
+/// Starts the timer, or resumes it if it is already running.
+#[tauri::command]
+fn start_session<R: Runtime>(
+    app: AppHandle<R>,
+    pomodoro: State<'_, Pomodoro>,
+    options: Option<PartialPomodoroOptions>,
+) -> Result<(), String> {
+    if pomodoro.running.swap(true, Ordering::SeqCst) {
+        return send(&pomodoro.remote, RemoteCommand::Resume);
+    }
+    let options = PomodoroOptions::merge(pomodoro.options.clone(), options.unwrap_or_default());
+    let abort = pomodoro.new_abort_handle();
+    let remote = pomodoro.remote.clone();
+    let running = pomodoro.running.clone();
+    emit_ticks(app.clone(), remote.clone(), running.clone());
+    thread::Builder::new()
+        .name("pomodoro".to_string())
+        .spawn(move || {
+            let callbacks = SessionCallbacks {
+                on_phase_start: Some(phase_change_callback(app.clone(), true)),
+                on_phase_end: Some(phase_change_callback(app, false)),
+                ..SessionCallbacks::default()
+            };
+            if let Err(e) = start_pomodoro_without_terminal(&options, &abort, &remote, &callbacks) {
+                log::warn!("{}", e);
+            }
+            running.store(false, Ordering::SeqCst);
+        })
+        .map_err(|e| {
+            pomodoro.running.store(false, Ordering::SeqCst);
+            format!("Failed to start the timer: {}", e)
+        })?;
+    Ok(())
+}
+
+/// Pauses the current Pomodoro session or break.
+#[tauri::command]
+fn pause_session(pomodoro: State<'_, Pomodoro>) -> Result<(), String> {
+    send(&pomodoro.remote, RemoteCommand::Pause)
+}
+
+/// Gets the status of the timer.
+#[tauri::command]
+fn get_status(pomodoro: State<'_, Pomodoro>) -> TimerStatus {
+    pomodoro.remote.status()
+}
+
+/// Sends the command to the timer and fails if the timer isn't started yet.
+fn send(remote: &RemoteControl, command: RemoteCommand) -> Result<(), String> {
+    if remote.send(command) {
+        Ok(())
+    } else {
+        Err("The timer isn't started yet.".to_string())
+    }
+}
+
+/// Emits `TICK_EVENT` every second on another thread while the timer runs.
+fn emit_ticks<R: Runtime>(app: AppHandle<R>, remote: RemoteControl, running: Arc<AtomicBool>) {
+    let ticker = thread::Builder::new()
+        .name("pomodoro_tick".to_string())
+        .spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                emit(&app, TICK_EVENT, remote.status());
+                thread::sleep(TICK_INTERVAL);
+            }
+            emit(&app, TICK_EVENT, remote.status());
+        });
+    if let Err(e) = ticker {
+        log::warn!("Failed to start emitting {}: {}", TICK_EVENT, e);
+    }
+}
+
+/// Creates the callback emitting `PHASE_CHANGE_EVENT` when a phase starts or ends.
+fn phase_change_callback<R: Runtime>(app: AppHandle<R>, started: bool) -> PhaseCallback {
+    Box::new(move |phase| emit(&app, PHASE_CHANGE_EVENT, PhaseChange { phase, started }))
+}
+
+/// Emits the event to the frontend and logs a failure.
+fn emit<R: Runtime>(app: &AppHandle<R>, event: &str, payload: impl Serialize + Clone) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+#[test]
+fn test_phase_change() {
+    let change = PhaseChange {
+        phase: PomodoroPhase::Work,
+        started: true,
+    };
+    assert_eq!(
+        serde_json::to_value(change).unwrap(),
+        serde_json::json!({"phase": "work", "started": true})
+    );
+}